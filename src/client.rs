@@ -4,8 +4,9 @@ extern crate katome;
 extern crate toml;
 extern crate log4rs;
 
-use katome::{Assemble, BasicAsm, Config};
-use katome::collections::PtGraph;
+use katome::{Assemble, BasicAsm, Config, MultiKAsm};
+use katome::asm::{basic_assembler, multi_k_assembler};
+use katome::collections::{PtGraph, VecGIR};
 use std::fs::File;
 use std::io::Read;
 use toml::{Parser, Value};
@@ -14,7 +15,12 @@ fn main() {
     log4rs::init_file("./config/log4rs.yaml", Default::default()).unwrap();
     let config = parse_config("./config/settings.toml".to_string());
     println!("{:?}", config);
-    BasicAsm::assemble::<String, PtGraph>(config);
+    match (config.k_mer_sizes.is_some(), config.compacted_graph.unwrap_or(false)) {
+        (true, true) => multi_k_assembler::assemble_compacted::<String, PtGraph, VecGIR>(config),
+        (true, false) => MultiKAsm::assemble::<String, PtGraph>(config),
+        (false, true) => basic_assembler::assemble_compacted::<String, PtGraph, VecGIR>(config),
+        (false, false) => BasicAsm::assemble::<String, PtGraph>(config),
+    }
 }
 
 /// Attempt to load and parse the config file into our Config struct.
@@ -0,0 +1,196 @@
+//! Bloom filter pre-pass which drops singleton k-mers.
+//!
+//! Most of the distinct k-mers in the input are singletons caused by
+//! sequencing errors, yet each of them takes space in the collection. The
+//! pre-pass reads input twice:
+//!
+//! 1. every k-mer is inserted into the `seen` filter. K-mers which were already
+//!    present in it are inserted into the `repeated` filter as well.
+//! 2. reads are split into runs of consecutive k-mers present in the
+//!    `repeated` filter and only those runs are added to the collection.
+//!
+//! False positives of the filters let some singletons through, but k-mers
+//! seen at least twice are never dropped.
+
+use algorithms::builder::{AddReads, for_each_read};
+use algorithms::estimator::input_bytes;
+use config::InputFileType;
+use prelude::K_SIZE;
+use utils::reverse_complement_of;
+
+use fixedbitset::FixedBitSet;
+use metrohash::MetroHash64;
+
+use std::cmp;
+use std::f64::consts::LN_2;
+use std::hash::Hasher;
+use std::path::PathBuf;
+
+/// Space-efficient probabilistic set of sequences.
+pub struct BloomFilter {
+    bits: FixedBitSet,
+    size: usize,
+    hashes: usize,
+}
+
+impl BloomFilter {
+    /// Creates filter which holds `expected_items` with the false positive
+    /// rate not higher than `fp_rate`.
+    pub fn with_rate(expected_items: usize, fp_rate: f64) -> BloomFilter {
+        assert!(fp_rate > 0.0 && fp_rate < 1.0,
+                "False positive rate has to be in range (0, 1)");
+        let n = cmp::max(expected_items, 1) as f64;
+        let size = (-n * fp_rate.ln() / (LN_2 * LN_2)).ceil() as usize;
+        let hashes = cmp::max(((size as f64 / n) * LN_2).round() as usize, 1);
+        BloomFilter {
+            bits: FixedBitSet::with_capacity(size),
+            size: size,
+            hashes: hashes,
+        }
+    }
+
+    /// Inserts sequence into the filter, returns true if it was (probably)
+    /// present before.
+    pub fn insert(&mut self, sequence: &[u8]) -> bool {
+        let (h1, h2) = hash_pair(sequence);
+        let mut present = true;
+        for i in 0..self.hashes {
+            let bit = self.bit(h1, h2, i);
+            present &= self.bits.put(bit);
+        }
+        present
+    }
+
+    /// Checks if the sequence is (probably) present in the filter.
+    pub fn contains(&self, sequence: &[u8]) -> bool {
+        let (h1, h2) = hash_pair(sequence);
+        (0..self.hashes).all(|i| self.bits.contains(self.bit(h1, h2, i)))
+    }
+
+    /// Gets number of bits used by the filter.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Gets number of hash functions used by the filter.
+    pub fn hashes(&self) -> usize {
+        self.hashes
+    }
+
+    // double hashing, see Kirsch and Mitzenmacher "Less Hashing, Same
+    // Performance: Building a Better Bloom Filter"
+    #[inline]
+    fn bit(&self, h1: u64, h2: u64, i: usize) -> usize {
+        (h1.wrapping_add((i as u64).wrapping_mul(h2)) % self.size as u64) as usize
+    }
+}
+
+fn hash_pair(sequence: &[u8]) -> (u64, u64) {
+    let mut first = MetroHash64::with_seed(0);
+    first.write(sequence);
+    let mut second = MetroHash64::with_seed(0x5851_F42D_4C95_7F2D);
+    second.write(sequence);
+    (first.finish(), second.finish())
+}
+
+/// Creates collection from the FASTA/FASTQ input files, skipping k-mers which
+/// occur only once. Returns collection and total number of read bytes.
+pub fn create_filtered<T: AddReads>(input_files: &[PathBuf], ft: InputFileType,
+                                    reverse_complement: bool, fp_rate: f64)
+                                    -> (T, usize) {
+    let k = unsafe { K_SIZE };
+    let expected = input_bytes(input_files, ft);
+    let mut seen = BloomFilter::with_rate(expected, fp_rate);
+    let mut repeated = BloomFilter::with_rate(expected, fp_rate);
+    info!("Bloom filters use {} bits and {} hash functions each",
+          seen.size(),
+          seen.hashes());
+    info!("Searching for repeated k-mers");
+    let mut distinct = 0_usize;
+    for_each_read(input_files, ft, |read| {
+        if read.len() < k {
+            return;
+        }
+        for window in read.windows(k) {
+            let kmer = canonical(window, reverse_complement);
+            if !seen.insert(&kmer) {
+                distinct += 1;
+            }
+            else {
+                repeated.insert(&kmer);
+            }
+        }
+    });
+    drop(seen);
+    info!("Starting to build collection");
+    let mut collection = T::init(Some(distinct), Some(distinct), ft);
+    let mut dropped = 0_usize;
+    let total = for_each_read(input_files, ft, |read| {
+        if read.len() < k {
+            return;
+        }
+        // start of the current run of repeated k-mers
+        let mut start = None;
+        for (i, window) in read.windows(k).enumerate() {
+            if repeated.contains(&canonical(window, reverse_complement)) {
+                if start.is_none() {
+                    start = Some(i);
+                }
+                continue;
+            }
+            dropped += 1;
+            if let Some(s) = start.take() {
+                collection.add_read_fastaq(&read[s..i - 1 + k], reverse_complement);
+            }
+        }
+        if let Some(s) = start {
+            collection.add_read_fastaq(&read[s..], reverse_complement);
+        }
+    });
+    info!("Collection built, dropped {} occurrences of singleton k-mers, ~{} distinct k-mers \
+           in the input",
+          dropped,
+          distinct);
+    (collection, total)
+}
+
+/// Gets the lexicographically smaller of the k-mer and its reverse
+/// complement, or the k-mer itself if reverse complements are not used.
+pub fn canonical(kmer: &[u8], reverse_complement: bool) -> Vec<u8> {
+    if !reverse_complement {
+        return kmer.to_vec();
+    }
+    let rc = reverse_complement_of(kmer);
+    if rc[..] < kmer[..] { rc } else { kmer.to_vec() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remembers_inserted_sequences() {
+        let mut bf = BloomFilter::with_rate(100, 0.01);
+        assert!(!bf.insert(b"ACGT"));
+        assert!(bf.insert(b"ACGT"));
+        assert!(bf.contains(b"ACGT"));
+    }
+
+    #[test]
+    fn keeps_false_positive_rate() {
+        let mut bf = BloomFilter::with_rate(1000, 0.01);
+        for i in 0..1000_u32 {
+            bf.insert(format!("{}", i).as_bytes());
+        }
+        let false_positives = (1000..11_000_u32)
+            .filter(|i| bf.contains(format!("{}", i).as_bytes()))
+            .count();
+        assert!(false_positives < 300);
+    }
+
+    #[test]
+    fn creates_canonical_kmers() {
+        assert_eq!(canonical(b"TTGC", true), b"GCAA".to_vec());
+        assert_eq!(canonical(b"TTGC", false), b"TTGC".to_vec());
+    }
+}
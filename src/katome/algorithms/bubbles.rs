@@ -0,0 +1,188 @@
+//! Bubble popping.
+//!
+//! Bubble consists of short paths (branches) which diverge at one node and
+//! converge at another one. Bubbles are caused by sequencing errors and SNPs
+//! in the middle of reads, and since the collapser breaks contigs at both ends
+//! of them, each bubble splits the contig in two.
+//!
+//! Popping removes weaker branches of the bubble and adds their weight to the
+//! strongest one, provided that the removed branch is short, weak enough and
+//! its sequence is similar to the kept one. Only branches in which all inner
+//! nodes have single incoming and single outgoing edge are considered. Popping
+//! may turn the nested bubble into such one, so it is repeated until no
+//! bubble is left.
+
+use algorithms::builder::Build;
+use algorithms::pruner::Clean;
+use collections::GraphMut;
+use collections::graphs::{EdgeDirection, Id};
+use prelude::{K1_SIZE, Weight};
+use slices::BasicSlice;
+
+use std::cmp;
+use std::collections::HashMap;
+use std::mem;
+
+/// Criteria for popping the branch of the bubble.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BubbleParams {
+    /// Maximal number of k-mers in the branch.
+    pub max_length: usize,
+    /// Maximal ratio of the average weight of the popped branch to the average
+    /// weight of the kept one.
+    pub max_coverage_ratio: f64,
+    /// Minimal similarity of sequences of the popped and the kept branch -
+    /// one minus their edit distance divided by the length of the longer one.
+    pub min_similarity: f64,
+}
+
+/// Describes structure with bubbles which can be popped.
+pub trait Poppable: Build {
+    /// Pops all bubbles which meet the criteria, returns number of removed
+    /// branches.
+    fn pop_bubbles(&mut self, params: &BubbleParams) -> usize;
+}
+
+// Simple path starting with the outgoing edge of the branching node.
+struct Branch<G: GraphMut> {
+    edges: Vec<G::EdgeIdentifier>,
+    sequence: Vec<u8>,
+    // sum of edge weights multiplied by numbers of their k-mers
+    weight: f64,
+}
+
+impl<G: GraphMut> Branch<G> {
+    fn avg_weight(&self) -> f64 {
+        self.weight / (self.sequence.len() - unsafe { K1_SIZE }) as f64
+    }
+}
+
+impl<G: GraphMut> Poppable for G {
+    fn pop_bubbles(&mut self, params: &BubbleParams) -> usize {
+        info!("Starting bubble popping");
+        let mut popped = 0;
+        loop {
+            let mut to_remove: Vec<G::EdgeIdentifier> = vec![];
+            for i in 0..self.node_count() {
+                let node = G::NodeIdentifier::new(i);
+                if self.out_degree(node) > 1 {
+                    popped += pop_bubbles_at(self, node, params, &mut to_remove);
+                }
+            }
+            if to_remove.is_empty() {
+                break;
+            }
+            trace!("Removing {} edges of popped branches", to_remove.len());
+            self.remove_edges(to_remove);
+            self.remove_single_vertices();
+        }
+        info!("Popped {} branches of bubbles", popped);
+        popped
+    }
+}
+
+// Pops bubbles diverging at the node. Edges of the popped branches are only
+// collected, weights of the kept ones are updated in place.
+fn pop_bubbles_at<G: GraphMut>(graph: &mut G, node: G::NodeIdentifier, params: &BubbleParams,
+                            to_remove: &mut Vec<G::EdgeIdentifier>)
+                            -> usize {
+    let mut bubbles: HashMap<G::NodeIdentifier, Vec<Branch<G>>> = HashMap::new();
+    for e in graph.edges_of(node, EdgeDirection::Outgoing) {
+        if let Some((end, branch)) = follow_branch(graph, e, params.max_length) {
+            if end != node {
+                bubbles.entry(end).or_insert_with(Vec::new).push(branch);
+            }
+        }
+    }
+    let mut popped = 0;
+    for (_, mut branches) in bubbles.into_iter().filter(|&(_, ref b)| b.len() > 1) {
+        let strongest = (1..branches.len()).fold(0, |s, i| {
+            if branches[i].avg_weight() > branches[s].avg_weight() { i } else { s }
+        });
+        let kept = branches.swap_remove(strongest);
+        let kept_weight = kept.avg_weight();
+        for branch in branches {
+            let weight = branch.avg_weight();
+            if weight > params.max_coverage_ratio * kept_weight ||
+               similarity(&kept.sequence, &branch.sequence) < params.min_similarity {
+                continue;
+            }
+            for &e in &kept.edges {
+                let w = unwrap!(graph.edge_weight_mut(e));
+                w.1 = w.1.saturating_add(G::Weight::from_f64(weight));
+            }
+            to_remove.extend(branch.edges);
+            popped += 1;
+        }
+    }
+    popped
+}
+
+// Follows the simple path starting with the edge until it reaches the node
+// with more than one incoming edge. Returns such node and the path, unless the
+// path diverges, ends or gets longer than `max_length` k-mers.
+fn follow_branch<G: GraphMut>(graph: &G, mut edge: G::EdgeIdentifier, max_length: usize)
+                           -> Option<(G::NodeIdentifier, Branch<G>)> {
+    let k1 = unsafe { K1_SIZE };
+    let mut branch = Branch {
+        edges: vec![],
+        sequence: vec![],
+        weight: 0.0,
+    };
+    loop {
+        let (slice, weight) = *unwrap!(graph.edge_weight(edge));
+        let name = slice.byte_name();
+        branch.weight += weight.to_f64() * (name.len() - k1) as f64;
+        if branch.sequence.is_empty() {
+            branch.sequence = name;
+        }
+        else {
+            branch.sequence.extend_from_slice(&name[k1..]);
+        }
+        branch.edges.push(edge);
+        if branch.sequence.len() - k1 > max_length {
+            return None;
+        }
+        let target = unwrap!(graph.edge_endpoints(edge)).1;
+        if graph.in_degree(target) > 1 {
+            return Some((target, branch));
+        }
+        if graph.out_degree(target) != 1 {
+            return None;
+        }
+        edge = unwrap!(graph.first_edge(target, EdgeDirection::Outgoing));
+    }
+}
+
+// Gets similarity of the sequences based on their Levenshtein distance.
+fn similarity(a: &[u8], b: &[u8]) -> f64 {
+    let longer = cmp::max(a.len(), b.len());
+    if longer == 0 {
+        return 1.0;
+    }
+    let mut previous = (0..b.len() + 1).collect::<Vec<usize>>();
+    let mut current = vec![0; b.len() + 1];
+    for (i, x) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, y) in b.iter().enumerate() {
+            let substitution = previous[j] + if x == y { 0 } else { 1 };
+            current[j + 1] = cmp::min(substitution, cmp::min(previous[j + 1], current[j]) + 1);
+        }
+        mem::swap(&mut previous, &mut current);
+    }
+    1.0 - previous[b.len()] as f64 / longer as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::similarity;
+
+    #[test]
+    fn computes_similarity() {
+        assert_eq!(similarity(b"", b""), 1.0);
+        assert_eq!(similarity(b"ACGT", b"ACGT"), 1.0);
+        assert_eq!(similarity(b"ACGT", b"ACCT"), 0.75);
+        assert_eq!(similarity(b"ACGT", b"AGT"), 0.75);
+        assert_eq!(similarity(b"ACGT", b"TTTT"), 0.25);
+    }
+}
@@ -1,13 +1,17 @@
 //! Collection builder.
 
+use algorithms::bloom::create_filtered;
+use algorithms::corrector::create_corrected;
+use algorithms::estimator::estimate_size;
+use algorithms::partitioner::{PartitionParams, create_partitioned};
 use config::InputFileType;
-use prelude::{EdgeWeight, Idx};
+use prelude::{Idx, Weight};
 
 
 use std::error::Error;
 use std::fs::{File, metadata, canonicalize};
 use std::io;
-use std::io::BufRead;
+use std::io::{BufRead, Cursor, Read, Seek, SeekFrom};
 use std::io::BufReader;
 use std::io::Result as Res;
 use std::path::{Path, PathBuf};
@@ -24,37 +28,309 @@ pub trait Init: Default {
     }
 }
 
-/// Description of how collection should be built.
-pub trait Build: Init {
+/// Collection to which reads are added while the `GIR`/`Graph` is built.
+pub trait AddReads: Init {
+    /// Type of the edge weight.
+    type Weight: Weight;
+
     /// Adds a single FASTA/FASTAQ read to the collection.
     fn add_read_fastaq(&mut self, read: &[u8], reverse_complement: bool);
     /// Adds a single BFCounter read to the collection.
-    fn add_read_bfc(&mut self, _read: &[u8], _weight: EdgeWeight, _reverse_complement: bool) {
-        // GIRs don't need to implement that function, as they serve the purpose
-        // of doing what BFCounter does
-        unreachable!()
+    fn add_read_bfc(&mut self, read: &[u8], weight: Self::Weight, reverse_complement: bool);
+    /// Gets number of distinct k-mers added to the collection, if it is known
+    /// without sorting or counting them. Used to report accuracy of the size
+    /// estimate.
+    fn kmer_count(&self) -> Option<usize> {
+        None
     }
+}
+
+/// Description of how collection should be built.
+///
+/// Reads are added to the `Builder`, which is turned into the collection once
+/// the whole input is read. Collections which can take reads directly are
+/// their own `Builder`s.
+pub trait Build: Sized {
+    /// Type of the edge weight.
+    type Weight: Weight;
+    /// Collection to which reads are added.
+    type Builder: AddReads<Weight = Self::Weight>;
+
+    /// Creates collection out of the `Builder` with all reads added.
+    fn from_builder(builder: Self::Builder) -> Self;
 
     /// Creates `GIR`/`Graph` from the supplied file,
     /// return with information about total number of read bytes.
     ///
     /// Currently supports fastaq format.
     fn create<P: AsRef<Path>>(input_files: &[P], ft: InputFileType, reverse_complement: bool,
-                              minimal_weight_threshold: EdgeWeight)
-                              -> (Self, usize)
-        where Self: Sized {
+                              minimal_weight_threshold: Self::Weight)
+                              -> (Self, usize) {
+        let files = check_files(input_files);
+        let (builder, total) =
+            create_builder(&files, ft, reverse_complement, minimal_weight_threshold);
+        (Self::from_builder(builder), total)
+    }
+
+    /// Creates `GIR`/`Graph` from the supplied FASTA/FASTQ files using
+    /// minimizer-partitioned, external-memory k-mer counting. Only solid
+    /// k-mers are added to the collection, through `add_read_bfc`. Returns
+    /// collection with information about total number of read bytes.
+    fn create_partitioned<P: AsRef<Path>>(input_files: &[P], ft: InputFileType,
+                                          reverse_complement: bool, params: &PartitionParams)
+                                          -> (Self, usize) {
+        let files = check_files(input_files);
+        let (builder, total) = create_partitioned(&files, ft, reverse_complement, params);
+        (Self::from_builder(builder), total)
+    }
+
+    /// Creates `GIR`/`Graph` from the supplied FASTA/FASTQ files, dropping
+    /// k-mers which occur only once. Singletons are detected by the Bloom
+    /// filter pre-pass with the given false positive rate, so the input is
+    /// read twice. Returns collection with information about total number of
+    /// read bytes.
+    fn create_filtered<P: AsRef<Path>>(input_files: &[P], ft: InputFileType,
+                                       reverse_complement: bool, fp_rate: f64)
+                                       -> (Self, usize) {
         let files = check_files(input_files);
-        match ft {
-            InputFileType::Fasta => create_fasta(&files, reverse_complement),
-            InputFileType::Fastq => create_fastq(&files, reverse_complement),
-            InputFileType::BFCounter => {
-                create_bfc(&files, reverse_complement, minimal_weight_threshold)
+        let (builder, total) = create_filtered(&files, ft, reverse_complement, fp_rate);
+        (Self::from_builder(builder), total)
+    }
+
+    /// Creates `GIR`/`Graph` from the supplied FASTA/FASTQ files, correcting
+    /// sequencing errors in reads before they are added. K-mers occurring at
+    /// least `threshold` times are solid and weak k-mers of reads are made
+    /// solid by single base substitutions, so the input is read twice.
+    /// Returns collection with information about total number of read bytes.
+    fn create_corrected<P: AsRef<Path>>(input_files: &[P], ft: InputFileType,
+                                        reverse_complement: bool, threshold: usize)
+                                        -> (Self, usize) {
+        let files = check_files(input_files);
+        let (builder, total) = create_corrected(&files, ft, reverse_complement, threshold);
+        (Self::from_builder(builder), total)
+    }
+}
+
+/// Collection to which reads can be added after it is created.
+pub trait Update: Build {
+    /// Adds reads from the supplied files to the already created `GIR`/`Graph`.
+    /// Weights of k-mers which are already in the collection are increased.
+    /// Returns collection with information about number of added read bytes.
+    fn update<P: AsRef<Path>>(self, input_files: &[P], ft: InputFileType,
+                              reverse_complement: bool, minimal_weight_threshold: Self::Weight)
+                              -> (Self, usize);
+}
+
+/// Creates `Builder` with all k-mers from the supplied (checked) files,
+/// return with information about total number of read bytes.
+pub fn create_builder<T: AddReads>(input_files: &[PathBuf], ft: InputFileType,
+                                   reverse_complement: bool, minimal_weight_threshold: T::Weight)
+                                   -> (T, usize) {
+    match ft {
+        InputFileType::Fasta => create_fasta(input_files, reverse_complement),
+        InputFileType::Fastq => create_fastq(input_files, reverse_complement),
+        InputFileType::BFCounter => {
+            create_bfc(input_files, reverse_complement, minimal_weight_threshold)
+        }
+    }
+}
+
+/// Adds reads from the supplied files to the collection. Returns number of
+/// added read bytes.
+pub fn add_reads<T: AddReads, P: AsRef<Path>>(collection: &mut T, input_files: &[P],
+                                              ft: InputFileType, reverse_complement: bool,
+                                              minimal_weight_threshold: T::Weight)
+                                              -> usize {
+    let files = check_files(input_files);
+    info!("Adding reads to the collection");
+    match ft {
+        InputFileType::Fasta | InputFileType::Fastq => {
+            for_each_read(&files, ft, |read| collection.add_read_fastaq(read, reverse_complement))
+        }
+        InputFileType::BFCounter => {
+            add_bfc(collection, &files, reverse_complement, minimal_weight_threshold)
+        }
+    }
+}
+
+/// Calls `f` on every read from the FASTA/FASTQ input files. Reads containing
+/// symbols other than `A`, `C`, `G`, `T` are skipped. Returns total number of
+/// read bytes.
+pub fn for_each_read<F: FnMut(&[u8])>(input_files: &[PathBuf], ft: InputFileType, mut f: F)
+                                      -> usize {
+    let mut total = 0_usize;
+    for file in input_files {
+        total += for_each_record(open_reader(file), ft, false, &mut f);
+        info!("Done with {}", file.display());
+    }
+    total
+}
+
+/// Calls `f` on reads from the part of the FASTA/FASTQ file, which starts at
+/// `offset` and is `length` bytes long. Reading starts from the first record
+/// after `offset` and stops at the end of the part, so the last FASTA read
+/// may be truncated, while the cut off FASTQ read is skipped. Reads containing
+/// symbols other than `A`, `C`, `G`, `T` are skipped. Returns total number of
+/// read bytes.
+pub fn for_each_read_in<F: FnMut(&[u8])>(file: &Path, offset: u64, length: u64,
+                                         ft: InputFileType, mut f: F)
+                                         -> usize {
+    let mut reader = BufReader::new(open_reader(file));
+    if let Err(why) = reader.seek(SeekFrom::Start(offset)) {
+        panic!("Couldn't seek in {}: {}", file.display(), why);
+    }
+    let (first, consumed) = find_record(&mut reader, offset > 0, ft);
+    let part = Cursor::new(first.into_bytes()).chain(reader.take(length.saturating_sub(consumed)));
+    for_each_record(part, ft, true, &mut f)
+}
+
+// Finds the first record at or after the current position of the reader,
+// skipping the first line if it may be cut off. A FASTA sequence cut in the
+// middle becomes a read of its own. Returns lines of the input from the
+// beginning of the record and number of bytes read from the reader.
+fn find_record<R: BufRead>(reader: &mut R, cut_off: bool, ft: InputFileType) -> (String, u64) {
+    let mut consumed = 0_u64;
+    let mut next_line = || {
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(n) => consumed += n as u64,
+            Err(why) => panic!("Couldn't read the input: {}", why),
+        }
+        line
+    };
+    if cut_off {
+        next_line();
+    }
+    let first = match ft {
+        InputFileType::Fasta => {
+            let line = next_line();
+            if line.is_empty() || line.starts_with('>') {
+                line
+            }
+            else {
+                format!(">\n{}", line)
             }
         }
+        InputFileType::Fastq => {
+            // quality may start with '@' as well, but only the header is
+            // followed by the sequence and the separator
+            let mut lines: Vec<String> = vec![];
+            while lines.len() < 3 ||
+                  !(lines[0].starts_with('@') && lines[2].starts_with('+')) {
+                if lines.len() == 3 {
+                    lines.remove(0);
+                }
+                let line = next_line();
+                if line.is_empty() {
+                    lines.clear();
+                    break;
+                }
+                lines.push(line);
+            }
+            lines.concat()
+        }
+        InputFileType::BFCounter => panic!("BFCounter input consists of already counted k-mers"),
+    };
+    (first, consumed)
+}
+
+// Record of the FASTA/FASTQ file.
+trait Sequence {
+    fn sequence(&self) -> &[u8];
+}
+
+impl Sequence for fasta::Record {
+    fn sequence(&self) -> &[u8] {
+        self.seq()
     }
 }
 
-fn check_files<P: AsRef<Path>>(input_files: &[P]) -> Vec<PathBuf> {
+impl Sequence for fastq::Record {
+    fn sequence(&self) -> &[u8] {
+        self.seq()
+    }
+}
+
+// Calls `f` on every read from the FASTA/FASTQ input, which consists of `A`,
+// `C`, `G`, `T` only. The last record of the `truncated` input may be cut off,
+// otherwise malformed records are reported. Returns total number of read
+// bytes.
+fn for_each_record<R: Read, F: FnMut(&[u8])>(reader: R, ft: InputFileType, truncated: bool,
+                                             f: &mut F)
+                                             -> usize {
+    match ft {
+        InputFileType::Fasta => read_records(fasta::Reader::new(reader).records(), truncated, f),
+        InputFileType::Fastq => read_records(fastq::Reader::new(reader).records(), truncated, f),
+        InputFileType::BFCounter => panic!("BFCounter input consists of already counted k-mers"),
+    }
+}
+
+fn read_records<I, S, F>(records: I, truncated: bool, f: &mut F) -> usize
+    where I: Iterator<Item = io::Result<S>>,
+          S: Sequence,
+          F: FnMut(&[u8]) {
+    let mut total = 0_usize;
+    for record in records {
+        let record = match record {
+            Ok(r) => r,
+            Err(_) if truncated => break,
+            Err(why) => panic!("Couldn't read the record: {}", why),
+        };
+        let seq = record.sequence();
+        if is_acgt(seq) {
+            total += seq.len() as Idx;
+            f(seq);
+        }
+    }
+    total
+}
+
+fn is_acgt(seq: &[u8]) -> bool {
+    seq.iter().all(|&x| "ACGT".bytes().any(|i| i == x))
+}
+
+fn open_reader(file: &Path) -> File {
+    match File::open(file) {
+        Ok(f) => f,
+        Err(why) => panic!("Couldn't open {}: {}", file.display(), why),
+    }
+}
+
+/// Calls `f` on every pair of reads from the two FASTA/FASTQ files, which hold
+/// the first and the second mates in the same order. Pairs in which any read
+/// contains symbols other than `A`, `C`, `G`, `T` are skipped.
+pub fn for_each_read_pair<F: FnMut(&[u8], &[u8])>(first: &Path, second: &Path, ft: InputFileType,
+                                                  mut f: F) {
+    let (a, b) = (open_reader(first), open_reader(second));
+    match ft {
+        InputFileType::Fasta => {
+            read_pairs(fasta::Reader::new(a).records(), fasta::Reader::new(b).records(), &mut f)
+        }
+        InputFileType::Fastq => {
+            read_pairs(fastq::Reader::new(a).records(), fastq::Reader::new(b).records(), &mut f)
+        }
+        InputFileType::BFCounter => panic!("BFCounter input consists of already counted k-mers"),
+    }
+}
+
+fn read_pairs<I, S, F>(first: I, second: I, f: &mut F)
+    where I: Iterator<Item = io::Result<S>>,
+          S: Sequence,
+          F: FnMut(&[u8], &[u8]) {
+    for (x, y) in first.zip(second) {
+        let (x, y) = match (x, y) {
+            (Ok(x), Ok(y)) => (x, y),
+            (Err(why), _) | (_, Err(why)) => panic!("Couldn't read the record: {}", why),
+        };
+        if is_acgt(x.sequence()) && is_acgt(y.sequence()) {
+            f(x.sequence(), y.sequence());
+        }
+    }
+}
+
+/// Resolves paths of the input files. Panics if any of them does not exist or
+/// is a directory.
+pub fn check_files<P: AsRef<Path>>(input_files: &[P]) -> Vec<PathBuf> {
     let mut output = vec![];
     for file in input_files {
         let file = match canonicalize(file) {
@@ -76,31 +352,67 @@ fn check_files<P: AsRef<Path>>(input_files: &[P]) -> Vec<PathBuf> {
     output
 }
 
-fn create_bfc<T: Sized + Init + Build>(input_files: &[PathBuf], reverse_complement: bool,
-                                       minimal_weight_threshold: EdgeWeight)
-                                       -> (T, usize) {
-    let mut total = 0_usize;
+fn create_bfc<T: AddReads>(input_files: &[PathBuf], reverse_complement: bool,
+                           minimal_weight_threshold: T::Weight)
+                           -> (T, usize) {
     let mut edge_count = 0_usize;
     for file in input_files {
         edge_count += count_lines(file);
     }
     let mut collection = T::init(Some(edge_count), None, InputFileType::BFCounter);
+    info!("Starting to build collection");
+    let total = add_bfc(&mut collection, input_files, reverse_complement, minimal_weight_threshold);
+    info!("Collection built");
+    (collection, total)
+}
+
+fn create_fasta<T: AddReads>(input_files: &[PathBuf], reverse_complement: bool) -> (T, usize) {
+    let estimate = estimate_size(input_files, InputFileType::Fasta, reverse_complement);
+    info!("Estimated {} distinct k-mers and {} distinct (k-1)-mers",
+          estimate.kmers,
+          estimate.nodes);
+    let mut collection = T::init(Some(estimate.kmers), Some(estimate.nodes), InputFileType::Fasta);
+    info!("Starting to build collection");
+    let total = for_each_read(input_files, InputFileType::Fasta, |read| {
+        collection.add_read_fastaq(read, reverse_complement)
+    });
+    report_size(&collection, estimate.kmers);
+    (collection, total)
+}
+
+fn create_fastq<T: AddReads>(input_files: &[PathBuf], reverse_complement: bool) -> (T, usize) {
+    let estimate = estimate_size(input_files, InputFileType::Fastq, reverse_complement);
+    info!("Estimated {} distinct k-mers and {} distinct (k-1)-mers",
+          estimate.kmers,
+          estimate.nodes);
+    let mut collection = T::init(Some(estimate.kmers), Some(estimate.nodes), InputFileType::Fastq);
+    info!("Starting to build collection");
+    let total = for_each_read(input_files, InputFileType::Fastq, |read| {
+        collection.add_read_fastaq(read, reverse_complement)
+    });
+    report_size(&collection, estimate.kmers);
+    (collection, total)
+}
+
+fn add_bfc<T: AddReads>(collection: &mut T, input_files: &[PathBuf], reverse_complement: bool,
+                        minimal_weight_threshold: T::Weight)
+                        -> usize {
+    let mut total = 0_usize;
     let readers: Vec<_> = match input_files.iter().map(lines_from_file).collect() {
         Ok(r) => r,
         Err(why) => panic!("Couldn't open all files: {}", Error::description(&why)),
     };
-    info!("Starting to build collection");
     for reader in readers {
         for e in reader {
             let e_ = e.unwrap();
             let mut iter = e_.split('\t');
             let edge = iter.next().unwrap().bytes().collect::<Vec<u8>>();
-            let weight = match iter.next().unwrap().parse::<EdgeWeight>() {
+            let weight = match T::Weight::parse(iter.next().unwrap()) {
                 Ok(w) => w,
                 Err(e) => {
-                    panic!("Parse int error (if the kind is overflow user should change type of \
-                            EdgeWeight in prelude.rs): {}",
-                           e.description())
+                    panic!("Couldn't parse weight (on overflow use collection with the wider \
+                            weight type): {}",
+                           e)
                 }
             };
             if weight < minimal_weight_threshold {
@@ -110,58 +422,18 @@ fn create_bfc<T: Sized + Init + Build>(input_files: &[PathBuf], reverse_compleme
             collection.add_read_bfc(&edge, weight, reverse_complement);
         }
     }
-    info!("Collection built");
-    (collection, total)
-}
-
-// TODO: remove nasty code duplication
-fn create_fasta<T: Sized + Init + Build>(input_files: &[PathBuf], reverse_complement: bool)
-                                         -> (T, usize) {
-    let mut total = 0_usize;
-    let mut collection = T::default();
-    let readers: Vec<_> = match input_files.iter().map(fasta::Reader::from_file).collect() {
-        Ok(r) => r,
-        Err(why) => panic!("Couldn't open all files: {}", Error::description(&why)),
-    };
-    info!("Starting to build collection");
-    for reader in readers {
-        for sequence in reader.records() {
-            let res = sequence.unwrap();
-            let seq = res.seq();
-            if !seq.iter().all(|&x| "ACGT".bytes().any(|i| i == x)) {
-                continue;
-            }
-            total += seq.len() as Idx;
-            collection.add_read_fastaq(seq, reverse_complement);
-        }
-    }
-    info!("Collection built");
-    (collection, total)
+    total
 }
 
-fn create_fastq<T: Sized + Init + Build>(input_files: &[PathBuf], reverse_complement: bool)
-                                         -> (T, usize) {
-    let mut total = 0_usize;
-    let mut collection = T::default();
-    let readers: Vec<_> = match input_files.iter().map(fastq::Reader::from_file).collect() {
-        Ok(r) => r,
-        Err(why) => panic!("Couldn't open all files: {}", Error::description(&why)),
-    };
-    info!("Starting to build collection");
-    for (reader, filename) in readers.into_iter().zip(input_files.iter()) {
-        for sequence in reader.records() {
-            let res = sequence.unwrap();
-            let seq = res.seq();
-            if !seq.iter().all(|&x| "ACGT".bytes().any(|i| i == x)) {
-                continue;
-            }
-            total += seq.len() as Idx;
-            collection.add_read_fastaq(seq, reverse_complement);
+fn report_size<T: AddReads>(collection: &T, estimated: usize) {
+    match collection.kmer_count() {
+        Some(count) => {
+            info!("Collection built, {} distinct k-mers (estimated {})",
+                  count,
+                  estimated)
         }
-        info!("Done with {}", filename.display());
+        None => info!("Collection built"),
     }
-    info!("Collection built");
-    (collection, total)
 }
 
 fn lines_from_file<P: AsRef<Path>>(filename: P) -> Res<io::Lines<io::BufReader<File>>> {
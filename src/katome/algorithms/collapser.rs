@@ -1,20 +1,47 @@
 //! Create string representation of contigs out of `Graph`.
+//!
+//! Before the traversal, cycles of the shrunk graph with the single entry and
+//! the single exit (e.g. tandem repeats) may be unrolled into the entry edge.
+//! The number of repeats is estimated from the ratio of weights of the cycle
+//! and its flanking edges, so contigs traversing unrolled entries are
+//! reported.
+//!
+//! Contigs stop at branching nodes, unless the ratio of dominance is given -
+//! then the contig continues along the edge, which weight dominates weights
+//! of the alternatives.
 
+use algorithms::components::{connected_components, tarjan_scc};
+use algorithms::copy_number::{copy_numbers, unit_coverage};
+use algorithms::pruner::Clean;
 use algorithms::shrinker::Shrinkable;
-use collections::Graph;
-use collections::graphs::pt_graph::{EdgeIndex, NodeIndex, PtGraph};
+use asm::SEQUENCES;
+use collections::GraphMut;
+use collections::graphs::{EdgeDirection, Id};
+use compress::compress_edge;
+use config::CollapseMode;
+use prelude::Weight;
 use slices::BasicSlice;
 
 use fixedbitset::FixedBitSet;
 
-use petgraph::EdgeDirection;
-use petgraph::algo::{connected_components, tarjan_scc};
-use petgraph::visit::EdgeRef;
+use std::collections::HashSet;
 
 /// Collapse `Graph` into `SerializedContigs`.
 pub trait Collapsable: Shrinkable {
     /// Collapses `Graph` into `SerializedContigs`.
     fn collapse(self) -> SerializedContigs;
+    /// Collapses `Graph` into `SerializedContigs`, traversing each edge of
+    /// the shrunk graph as many times as its copy number inferred with the
+    /// min-cost flow, instead of its weight.
+    fn collapse_with_copy_numbers(self) -> SerializedContigs;
+    /// Collapses `Graph` into `SerializedContigs` in the given mode. If the
+    /// `dominance` ratio is given, contigs continue through branching nodes
+    /// along edges which weights dominate the alternatives by that ratio. If
+    /// `unroll` is set, cycles with the single entry and the single exit are
+    /// unrolled beforehand. Returns also indices of contigs which contain
+    /// unrolled cycles, with the number of repeats estimated from the weights.
+    fn collapse_in_mode(self, mode: CollapseMode, dominance: Option<f64>, unroll: bool)
+                        -> (SerializedContigs, Vec<usize>);
 }
 
 /// Representation of serialized contig.
@@ -22,68 +49,280 @@ pub type SerializedContig = String;
 /// Collection of serialized contigs.
 pub type SerializedContigs = Vec<String>;
 
-impl Collapsable for PtGraph {
-    fn collapse(mut self) -> SerializedContigs {
-        let mut contigs: SerializedContigs = vec![];
-        info!("Starting collapse of the graph");
+impl<G: GraphMut> Collapsable for G {
+    fn collapse(self) -> SerializedContigs {
+        self.collapse_in_mode(CollapseMode::Weights, None, false).0
+    }
+
+    fn collapse_with_copy_numbers(self) -> SerializedContigs {
+        self.collapse_in_mode(CollapseMode::CopyNumbers, None, false).0
+    }
+
+    fn collapse_in_mode(mut self, mode: CollapseMode, dominance: Option<f64>, unroll: bool)
+                        -> (SerializedContigs, Vec<usize>) {
         // ensure that we don't end up with straight paths longer than
         // one edge
         self.shrink();
-        let node_count = self.node_count();
-        let mut ambiguous_nodes = FixedBitSet::with_capacity(node_count);
-        let mut single_vertices: Vec<NodeIndex> = vec![];
-        info!("Graph has {} weakly connected components", connected_components(&self));
+        if mode == CollapseMode::CopyNumbers {
+            set_copy_numbers(&mut self);
+        }
+        let unrolled = if unroll {
+            unroll_cycles(&mut self)
+        }
+        else {
+            HashSet::new()
+        };
+        let (contigs, estimated) = collapse_shrunk(self, dominance, &unrolled);
+        if !estimated.is_empty() {
+            info!("{} contigs contain cycles with the estimated number of repeats",
+                  estimated.len());
+        }
+        (contigs, estimated)
+    }
+}
+
+// Sets weight of each edge to its copy number, as the collapse decreases
+// weight of the edge each time it is traversed.
+fn set_copy_numbers<G: GraphMut>(graph: &mut G) {
+    let unit = unit_coverage(graph);
+    info!("Inferring copy numbers of {} edges with unit coverage {}",
+          graph.edge_count(),
+          unit);
+    let copies = copy_numbers(graph, unit);
+    info!("Edges have {} copies in total", copies.iter().sum::<usize>());
+    for (i, &c) in copies.iter().enumerate() {
+        unwrap!(graph.edge_weight_mut(G::EdgeIdentifier::new(i))).1 =
+            G::Weight::from_f64(c as f64);
+    }
+}
+
+// Cycle with the single entry and the single exit.
+struct Cycle<G: GraphMut> {
+    entry: G::EdgeIdentifier,
+    exit: G::EdgeIdentifier,
+    // edges of the cycle, starting at the target of the entry
+    edges: Vec<G::EdgeIdentifier>,
+}
+
+#[inline]
+fn source_of<G: GraphMut>(graph: &G, edge: G::EdgeIdentifier) -> G::NodeIdentifier {
+    unwrap!(graph.edge_endpoints(edge)).0
+}
+
+#[inline]
+fn target_of<G: GraphMut>(graph: &G, edge: G::EdgeIdentifier) -> G::NodeIdentifier {
+    unwrap!(graph.edge_endpoints(edge)).1
+}
+
+/// Finds strongly connected components of the `Graph` which form a simple
+/// cycle (of any length) with exactly one incoming and one outgoing edge.
+fn find_cycles<G: GraphMut>(graph: &G) -> Vec<Cycle<G>> {
+    let sccs = tarjan_scc(graph);
+    let mut component = vec![0; graph.node_count()];
+    for (i, scc) in sccs.iter().enumerate() {
+        for n in scc {
+            component[n.index()] = i;
+        }
+    }
+    let mut cycles = vec![];
+    for (i, scc) in sccs.iter().enumerate() {
+        let inside = |n: G::NodeIdentifier| component[n.index()] == i;
+        let mut entries = vec![];
+        let mut exits = vec![];
+        let mut next = vec![None; graph.node_count()];
+        let mut simple = true;
+        for &n in scc {
+            let (inner, outer): (Vec<_>, Vec<_>) = graph.edges_of(n, EdgeDirection::Outgoing)
+                .into_iter()
+                .partition(|&e| inside(target_of(graph, e)));
+            simple &= inner.len() == 1;
+            next[n.index()] = inner.first().cloned();
+            exits.extend(outer);
+            entries.extend(graph.edges_of(n, EdgeDirection::Incoming)
+                .into_iter()
+                .filter(|&e| !inside(source_of(graph, e))));
+        }
+        if !simple || entries.len() != 1 || exits.len() != 1 {
+            continue;
+        }
+        let start = target_of(graph, entries[0]);
+        let mut edges = vec![];
+        let mut node = start;
         loop {
-            // this is a loop over nodes which have in_degree == 0
-            loop {
-                // get all starting nodes
-                let externals = self.externals(EdgeDirection::Incoming).collect::<Vec<NodeIndex>>();
-                if externals.is_empty() {
-                    break;
+            let e = unwrap!(next[node.index()]);
+            edges.push(e);
+            node = target_of(graph, e);
+            if node == start {
+                break;
+            }
+        }
+        cycles.push(Cycle {
+            entry: entries[0],
+            exit: exits[0],
+            edges: edges,
+        });
+    }
+    cycles
+}
+
+/// Unrolls cycles with the single entry and the single exit into their entry
+/// edges. Cycle is repeated as many times as the ratio of its weight to the
+/// weight of the flanking edges. Returns indices of sequences of the unrolled
+/// entries, which unlike identifiers of edges survive removals.
+fn unroll_cycles<G: GraphMut>(graph: &mut G) -> HashSet<usize> {
+    let mut unrolled = HashSet::new();
+    loop {
+        let mut claimed: HashSet<G::EdgeIdentifier> = HashSet::new();
+        let mut to_remove = vec![];
+        for cycle in find_cycles(graph) {
+            // cycle may be entered directly from the exit of the other one
+            if claimed.contains(&cycle.entry) || claimed.contains(&cycle.exit) {
+                continue;
+            }
+            claimed.insert(cycle.entry);
+            claimed.insert(cycle.exit);
+            let exit_source = source_of(graph, cycle.exit);
+            let entry_idx = unwrap!(graph.edge_weight(cycle.entry)).0.idx();
+            let (sequence, repeats) = {
+                // cycle is traversed up to the exit once and then repeated
+                let split = unwrap!(cycle.edges
+                    .iter()
+                    .position(|&e| source_of(graph, e) == exit_source));
+                let (to_exit, rest) = cycle.edges.split_at(split);
+                let weight = |e: G::EdgeIdentifier| unwrap!(graph.edge_weight(e)).1.to_f64();
+                let flank = (weight(cycle.entry) + weight(cycle.exit)) / 2.0;
+                // average weight of the repeated part, per k-mer
+                let (total, length) = rest.iter().fold((0.0, 0), |(w, l), &e| {
+                    let length = unwrap!(graph.edge_weight(e)).0.remainder().len();
+                    (w + weight(e) * length as f64, l + length)
+                });
+                let ratio = total / length as f64 / if flank > 0.0 { flank } else { 1.0 };
+                let repeats = ratio.round() as usize;
+                let remainders = |edges: &[G::EdgeIdentifier]| {
+                    edges.iter()
+                        .map(|&e| unwrap!(graph.edge_weight(e)).0.remainder())
+                        .collect::<String>()
+                };
+                let mut sequence = unwrap!(graph.edge_weight(cycle.entry)).0.name();
+                sequence.push_str(&remainders(to_exit));
+                let round = format!("{}{}", remainders(rest), remainders(to_exit));
+                for _ in 0..repeats {
+                    sequence.push_str(&round);
                 }
-                // create contigs from each starting node
-                for n in externals {
-                    let contigs_ = contigs_from_vertex(&mut self,
-                                                       n,
-                                                       &mut ambiguous_nodes,
-                                                       &mut single_vertices);
-                    contigs.extend(contigs_);
+                (sequence, repeats)
+            };
+            trace!("Unrolling cycle of {} edges {} times", cycle.edges.len(), repeats);
+            {
+                let mut s = SEQUENCES.write();
+                s.replace(entry_idx, &compress_edge(sequence.as_bytes()));
+                for &e in &cycle.edges {
+                    s.clear_at(unwrap!(graph.edge_weight(e)).0.idx());
                 }
-                // remove fake starting nodes (nodes with in_degree == out_degree == 0
-                // self.remove_single_vertices();
-                remove_single_with_ambiguity(&mut self, &mut single_vertices, &mut ambiguous_nodes);
             }
-
-            // Cycle in the input -- use dfspostorder to get the starting node
-            // in the cycle. At this point no node in the graph has in_degree == 0
-            // and so we need to find a starting node somewhere in the 'highest'
-            // cycle in terms of topology of the subgraph.
-            if self.node_count() != 0 {
-                // we guarantee that there's at least one node to unwrap here
-                let node_in_cycle = unwrap!(tarjan_scc(&self).iter().last())[0];
-                contigs.extend(contigs_from_vertex(&mut self,
-                                                   node_in_cycle,
-                                                   &mut ambiguous_nodes,
-                                                   &mut single_vertices));
-                remove_single_with_ambiguity(&mut self, &mut single_vertices, &mut ambiguous_nodes);
+            // exit has to leave the target of the entry
+            let entry_target = target_of(graph, cycle.entry);
+            if exit_source != entry_target {
+                let exit_target = target_of(graph, cycle.exit);
+                let exit_weight = *unwrap!(graph.edge_weight(cycle.exit));
+                graph.add_edge(entry_target, exit_target, exit_weight);
+                to_remove.push(cycle.exit);
             }
-            else {
+            to_remove.extend(cycle.edges);
+            unrolled.insert(entry_idx);
+        }
+        if to_remove.is_empty() {
+            break;
+        }
+        graph.remove_edges(to_remove);
+        graph.remove_single_vertices();
+    }
+    if !unrolled.is_empty() {
+        info!("Unrolled {} cycles with the single entry and exit", unrolled.len());
+    }
+    unrolled
+}
+
+// Collapses the shrunk graph, returns also indices of contigs which traverse
+// edges with the given sequences.
+fn collapse_shrunk<G: GraphMut>(mut graph: G, dominance: Option<f64>, unrolled: &HashSet<usize>)
+                             -> (SerializedContigs, Vec<usize>) {
+    let mut contigs: SerializedContigs = vec![];
+    let mut estimated = vec![];
+    info!("Starting collapse of the graph");
+    let node_count = graph.node_count();
+    let mut ambiguous_nodes = FixedBitSet::with_capacity(node_count);
+    let mut single_vertices: Vec<G::NodeIdentifier> = vec![];
+    info!("Graph has {} weakly connected components", connected_components(&graph));
+    loop {
+        // this is a loop over nodes which have in_degree == 0
+        loop {
+            // get all starting nodes
+            let externals = (0..graph.node_count())
+                .map(G::NodeIdentifier::new)
+                .filter(|&n| graph.in_degree(n) == 0)
+                .collect::<Vec<G::NodeIdentifier>>();
+            if externals.is_empty() {
                 break;
             }
+            // create contigs from each starting node
+            for n in externals {
+                let contigs_ = contigs_from_vertex(&mut graph,
+                                                   n,
+                                                   &mut ambiguous_nodes,
+                                                   &mut single_vertices,
+                                                   dominance,
+                                                   unrolled);
+                add_contigs(&mut contigs, &mut estimated, contigs_);
+            }
+            // remove fake starting nodes (nodes with in_degree == out_degree == 0
+            // graph.remove_single_vertices();
+            remove_single_with_ambiguity(&mut graph, &mut single_vertices, &mut ambiguous_nodes);
+        }
+
+        // Cycle in the input -- use dfspostorder to get the starting node
+        // in the cycle. At this point no node in the graph has in_degree == 0
+        // and so we need to find a starting node somewhere in the 'highest'
+        // cycle in terms of topology of the subgraph.
+        if graph.node_count() != 0 {
+            // we guarantee that there's at least one node to unwrap here
+            let node_in_cycle = unwrap!(tarjan_scc(&graph).iter().last())[0];
+            let contigs_ = contigs_from_vertex(&mut graph,
+                                               node_in_cycle,
+                                               &mut ambiguous_nodes,
+                                               &mut single_vertices,
+                                               dominance,
+                                               unrolled);
+            add_contigs(&mut contigs, &mut estimated, contigs_);
+            remove_single_with_ambiguity(&mut graph, &mut single_vertices, &mut ambiguous_nodes);
+        }
+        else {
+            break;
+        }
+    }
+    trace!("{} nodes left in the graph after collapse", graph.node_count());
+    info!("Collapse ended. Created {} contigs which have {} nucleotides",
+          contigs.len(),
+          contigs.iter().map(|x| x.len()).sum::<usize>());
+    (contigs, estimated)
+}
 
+// Appends contigs, remembering indices of those which traverse unrolled
+// cycles.
+#[inline]
+fn add_contigs(contigs: &mut SerializedContigs, estimated: &mut Vec<usize>,
+               new: Vec<(SerializedContig, bool)>) {
+    for (contig, unrolled) in new {
+        if unrolled {
+            estimated.push(contigs.len());
         }
-        trace!("{} nodes left in the graph after collapse", self.node_count());
-        info!("Collapse ended. Created {} contigs which have {} nucleotides",
-              contigs.len(),
-              contigs.iter().map(|x| x.len()).sum::<usize>());
-        contigs
+        contigs.push(contig);
     }
 }
 
 /// Remove given nodes, but save information about ambiguity in the process.
 #[inline]
-fn remove_single_with_ambiguity(graph: &mut PtGraph, to_remove: &mut Vec<NodeIndex>,
-                                ambiguous_nodes: &mut FixedBitSet) {
+fn remove_single_with_ambiguity<G: GraphMut>(graph: &mut G, to_remove: &mut Vec<G::NodeIdentifier>,
+                                          ambiguous_nodes: &mut FixedBitSet) {
     // reverse sort node indices such that removal won't cause any swaps withing to_remove
     to_remove.sort_by(|a, b| b.cmp(a));
     let mut last_node = graph.node_count();
@@ -96,16 +335,24 @@ fn remove_single_with_ambiguity(graph: &mut PtGraph, to_remove: &mut Vec<NodeInd
 }
 
 #[inline]
-fn contigs_from_vertex(graph: &mut PtGraph, v: NodeIndex, ambiguous_nodes: &mut FixedBitSet,
-                       single_vertices: &mut Vec<NodeIndex>)
-                       -> SerializedContigs {
-    let mut contigs: SerializedContigs = vec![];
+fn contigs_from_vertex<G: GraphMut>(graph: &mut G, v: G::NodeIdentifier,
+                                 ambiguous_nodes: &mut FixedBitSet,
+                                 single_vertices: &mut Vec<G::NodeIdentifier>,
+                                 dominance: Option<f64>, unrolled: &HashSet<usize>)
+                                 -> Vec<(SerializedContig, bool)> {
+    let mut contigs = vec![];
     let mut contig: SerializedContig = String::new();
+    // whether the contig traverses the unrolled entry of the cycle
+    let mut estimated = false;
     let mut current_vertex = v;
     let mut current_edge_index;
     let mut simple_loop_;
+    // whether the contig arrived by the edge dominating other incoming edges
+    let mut arrived_dominant = true;
     let mut num_in = graph.in_degree(current_vertex);
     let mut num_out = graph.out_degree(current_vertex);
+    // buffer for edges of the visited vertices
+    let mut edges = vec![];
     loop {
         simple_loop_ = None;
         if num_out == 0 {
@@ -113,7 +360,7 @@ fn contigs_from_vertex(graph: &mut PtGraph, v: NodeIndex, ambiguous_nodes: &mut
                 single_vertices.push(current_vertex);
             }
             if !contig.is_empty() {
-                contigs.push(contig.clone());
+                contigs.push((contig.clone(), estimated));
             }
             return contigs;
         }
@@ -123,8 +370,9 @@ fn contigs_from_vertex(graph: &mut PtGraph, v: NodeIndex, ambiguous_nodes: &mut
                                      num_out);
         if ambiguous_nodes.contains(current_vertex.index()) {
             if !contig.is_empty() {
-                contigs.push(contig.clone());
+                contigs.push((contig.clone(), estimated));
                 contig.clear();
+                estimated = false;
             }
         }
         else {
@@ -133,13 +381,23 @@ fn contigs_from_vertex(graph: &mut PtGraph, v: NodeIndex, ambiguous_nodes: &mut
                     // make sure that we are not dealing with the loopy end
                     // if it is a self-loop then it must be of shape
                     // a -> b, b -> b
-                    if self_loop(graph, current_vertex).is_none() {
-                        simple_loop_ = simple_loop(graph, current_edge_index);
+                    if self_loop(graph, current_vertex, &mut edges).is_none() {
+                        simple_loop_ = simple_loop(graph, current_edge_index, &mut edges);
                         if simple_loop_.is_none() {
-                            ambiguous_nodes.insert(current_vertex.index());
-                            if !contig.is_empty() {
-                                contigs.push(contig.clone());
-                                contig.clear();
+                            match dominant_edge(graph,
+                                                current_vertex,
+                                                arrived_dominant,
+                                                dominance,
+                                                &mut edges) {
+                                Some(e) => current_edge_index = e,
+                                None => {
+                                    ambiguous_nodes.insert(current_vertex.index());
+                                    if !contig.is_empty() {
+                                        contigs.push((contig.clone(), estimated));
+                                        contig.clear();
+                                        estimated = false;
+                                    }
+                                }
                             }
                         }
                     }
@@ -148,28 +406,47 @@ fn contigs_from_vertex(graph: &mut PtGraph, v: NodeIndex, ambiguous_nodes: &mut
                     // because we handle simple loops in the match arm for 1 then
                     // any vertex with 2 outgoing edges has either a self-loop or
                     // is ambiguous
-                    if let Some(e) = self_loop(graph, current_vertex) {
+                    if let Some(e) = self_loop(graph, current_vertex, &mut edges) {
+                        current_edge_index = e;
+                    }
+                    else if let Some(e) = dominant_edge(graph,
+                                                        current_vertex,
+                                                        arrived_dominant,
+                                                        dominance,
+                                                        &mut edges) {
                         current_edge_index = e;
                     }
                     else {
                         ambiguous_nodes.insert(current_vertex.index());
                         if !contig.is_empty() {
-                            contigs.push(contig.clone());
+                            contigs.push((contig.clone(), estimated));
                             contig.clear();
+                            estimated = false;
                         }
                     }
                 }
                 (0, 1) | (1, 1) => {}
                 _ => {
-                    // ambiguous edge
-                    ambiguous_nodes.insert(current_vertex.index());
-                    if !contig.is_empty() {
-                        contigs.push(contig.clone());
-                        contig.clear();
+                    if let Some(e) = dominant_edge(graph,
+                                                   current_vertex,
+                                                   arrived_dominant,
+                                                   dominance,
+                                                   &mut edges) {
+                        current_edge_index = e;
+                    }
+                    else {
+                        // ambiguous edge
+                        ambiguous_nodes.insert(current_vertex.index());
+                        if !contig.is_empty() {
+                            contigs.push((contig.clone(), estimated));
+                            contig.clear();
+                            estimated = false;
+                        }
                     }
                 }
             }
         }
+        estimated |= unrolled.contains(&unwrap!(graph.edge_weight(current_edge_index)).0.idx());
         if contig.is_empty() {
             contig = unwrap!(graph.edge_weight(current_edge_index)).0.name();
         }
@@ -178,9 +455,18 @@ fn contigs_from_vertex(graph: &mut PtGraph, v: NodeIndex, ambiguous_nodes: &mut
         }
         let (_, target) = unwrap!(graph.edge_endpoints(current_edge_index));
         num_in = graph.in_degree(target);
+        arrived_dominant = match dominance {
+            Some(ratio) if simple_loop_.is_none() => {
+                graph.fill_edges(target, EdgeDirection::Incoming, &mut edges);
+                dominates(graph, current_edge_index, &edges, ratio)
+            }
+            _ => true,
+        };
         if let Some(e) = simple_loop_ {
-            contig.push_str(&unwrap!(graph.edge_weight(e)).0.remainder());
-            // make sure to possibly remove edges in the right order (petgraph
+            let &(ref slice, _) = unwrap!(graph.edge_weight(e));
+            estimated |= unrolled.contains(&slice.idx());
+            contig.push_str(&slice.remainder());
+            // make sure to possibly remove edges in the right order (graph
             // will switch the index of the last edge is anything prior to it is
             // removed)
             if current_edge_index < e {
@@ -212,13 +498,17 @@ fn contigs_from_vertex(graph: &mut PtGraph, v: NodeIndex, ambiguous_nodes: &mut
 /// It is assumed that node has 1 or 2 outgoing edges
 /// TODO add simple diagram to illustrate
 #[inline]
-fn self_loop(graph: &PtGraph, node: NodeIndex) -> Option<EdgeIndex> {
+fn self_loop<G: GraphMut>(graph: &G, node: G::NodeIdentifier,
+                          edges: &mut Vec<G::EdgeIdentifier>)
+                          -> Option<G::EdgeIdentifier> {
     if graph.in_degree(node) > 2 {
         return None;
     }
-    for e in graph.edges_directed(node, EdgeDirection::Outgoing) {
-        if e.target() == e.source() {
-            return Some(e.id());
+    graph.fill_edges(node, EdgeDirection::Outgoing, edges);
+    for &e in edges.iter() {
+        let (source, target) = unwrap!(graph.edge_endpoints(e));
+        if target == source {
+            return Some(e);
         }
     }
     None
@@ -232,7 +522,9 @@ fn self_loop(graph: &PtGraph, node: NodeIndex) -> Option<EdgeIndex> {
 /// Edges source has to have exactly one outgoing edge (functions argument).
 /// TODO add dragram
 #[inline]
-fn simple_loop(graph: &PtGraph, edge: EdgeIndex) -> Option<EdgeIndex> {
+fn simple_loop<G: GraphMut>(graph: &G, edge: G::EdgeIdentifier,
+                            edges: &mut Vec<G::EdgeIdentifier>)
+                            -> Option<G::EdgeIdentifier> {
     let (source, target) = unwrap!(graph.edge_endpoints(edge));
     let in_source = graph.in_degree(source);
     if in_source == 0 || in_source > 2 {
@@ -246,25 +538,71 @@ fn simple_loop(graph: &PtGraph, edge: EdgeIndex) -> Option<EdgeIndex> {
     if in_target != 1 || out_target != 2 {
         return None;
     }
-    for e in graph.edges_directed(target, EdgeDirection::Outgoing) {
+    graph.fill_edges(target, EdgeDirection::Outgoing, edges);
+    for &e in edges.iter() {
         // if weight of the edge going to the target doesn't have higher weight
         // then such loop would be broken at the source and contig would never
         // reach to the specified target. This in turns leads to
         // misassemblies/mismatches in the final contigs.
-        if e.target() == source && e.weight().1 < unwrap!(graph.edge_weight(edge)).1 {
-            return Some(e.id());
+        if unwrap!(graph.edge_endpoints(e)).1 == source &&
+           unwrap!(graph.edge_weight(e)).1 < unwrap!(graph.edge_weight(edge)).1 {
+            return Some(e);
         }
     }
     None
 }
 
+/// Gets the outgoing edge of the branching node, which weight dominates
+/// weights of other outgoing edges by the given ratio.
+///
+/// Contig may continue through the node only if it arrived there by the
+/// dominant incoming edge as well.
+#[inline]
+fn dominant_edge<G: GraphMut>(graph: &G, node: G::NodeIdentifier, arrived_dominant: bool,
+                           dominance: Option<f64>, edges: &mut Vec<G::EdgeIdentifier>)
+                           -> Option<G::EdgeIdentifier> {
+    let ratio = match dominance {
+        Some(r) if arrived_dominant => r,
+        _ => return None,
+    };
+    graph.fill_edges(node, EdgeDirection::Outgoing, edges);
+    let mut strongest = match edges.first() {
+        Some(&e) => e,
+        None => return None,
+    };
+    for &e in &edges[1..] {
+        if unwrap!(graph.edge_weight(e)).1 > unwrap!(graph.edge_weight(strongest)).1 {
+            strongest = e;
+        }
+    }
+    if dominates(graph, strongest, edges, ratio) {
+        Some(strongest)
+    }
+    else {
+        None
+    }
+}
+
+/// Checks if weight of the edge is at least `ratio` times higher than weight
+/// of every alternative.
+#[inline]
+fn dominates<G: GraphMut>(graph: &G, edge: G::EdgeIdentifier, alternatives: &[G::EdgeIdentifier],
+                       ratio: f64)
+                       -> bool {
+    let weight = unwrap!(graph.edge_weight(edge)).1.to_f64();
+    alternatives.iter()
+        .cloned()
+        .filter(|&e| e != edge)
+        .all(|e| weight >= ratio * unwrap!(graph.edge_weight(e)).1.to_f64())
+}
+
 #[inline]
-fn decrease_weight(graph: &mut PtGraph, edge: EdgeIndex) {
+fn decrease_weight<G: GraphMut>(graph: &mut G, edge: G::EdgeIdentifier) {
     {
         let edge_mut = unwrap!(graph.edge_weight_mut(edge),
                                "Trying to decrease weight of non-existent edge");
-        edge_mut.1 -= 1;
-        if edge_mut.1 > 0 {
+        edge_mut.1 = edge_mut.1 - G::Weight::one();
+        if edge_mut.1 > G::Weight::zero() {
             return;
         }
     }
@@ -302,12 +640,12 @@ mod tests {
             {
                 let mut seq = SEQUENCES.write();
                 seq.clear();
-                seq.push(vec![].into_boxed_slice());
-                seq.push(c1.into_boxed_slice());
-                seq.push(c2.into_boxed_slice());
-                seq.push(c3.into_boxed_slice());
-                seq.push(c4.into_boxed_slice());
-                seq.push(c5.into_boxed_slice());
+                seq.push(&[]);
+                seq.push(&c1);
+                seq.push(&c2);
+                seq.push(&c3);
+                seq.push(&c4);
+                seq.push(&c5);
             }
             let mut $g: PtGraph = PtGraph::default();
             let $w = $g.add_node(());
@@ -465,4 +803,47 @@ mod tests {
             assert_eq!(contigs[0], format!("{}GGTGCGCT", &name[..unsafe { K1_SIZE }]));
         })
     });
+
+    // w -> x -> y -> x, y -> z
+    test!(unrolls_cycle_with_single_entry_and_exit, {
+        setup!(_l, graph, name, _second, _w, _x);
+        catch_unwind(|| {
+            let y = graph.add_node(());
+            let z = graph.add_node(());
+            graph.add_edge(_w, _x, (EdgeSlice::new(1), 1));
+            graph.add_edge(_x, y, (EdgeSlice::new(2), 2));
+            graph.add_edge(y, _x, (EdgeSlice::new(3), 2));
+            graph.add_edge(y, z, (EdgeSlice::new(4), 1));
+            assert_eq!(graph.edge_count(), 4);
+            let (contigs, estimated) = graph.collapse_in_mode(CollapseMode::Weights, None, true);
+            assert_eq!(contigs.len(), 1);
+            assert_eq!(contigs[0], format!("{}GCGCGT", &name[..unsafe { K_SIZE }]));
+            assert_eq!(estimated, vec![0]);
+        })
+    });
+
+    // w -> x -> y, x -> z
+    macro_rules! extends_through_branching_node {
+        ($n:ident, $r:expr, $c:expr, $l:expr) => {
+            test!($n, {
+                setup!(_l, graph, name, second, _w, _x);
+                catch_unwind(|| {
+                    let y = graph.add_node(());
+                    let z = graph.add_node(());
+                    graph.add_edge(_w, _x, (EdgeSlice::new(1), 1));
+                    graph.add_edge(_x, y, (EdgeSlice::new(2), 2));
+                    graph.add_edge(_x, z, (EdgeSlice::new(5), 1));
+                    let (contigs, _) = graph.collapse_in_mode(CollapseMode::Weights, $r, false);
+                    assert_eq!(contigs.len(), $c);
+                    assert_eq!(contigs[0].as_str(), &name[..unsafe { K_SIZE } + $l]);
+                    // the weaker edge is left for the other contig
+                    assert!(contigs.contains(&second));
+                })
+            });
+        }
+    }
+
+    extends_through_branching_node!(extends_along_dominant_edge, Some(2.0), 3, 1);
+    extends_through_branching_node!(stops_if_no_edge_dominates, Some(2.5), 4, 0);
+    extends_through_branching_node!(stops_at_branching_node, None, 4, 0);
 }
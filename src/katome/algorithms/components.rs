@@ -0,0 +1,268 @@
+//! Connected components of the `Graph`.
+
+use algorithms::pruner::Clean;
+use collections::{Graph, GraphMut};
+use collections::graphs::Id;
+use collections::graphs::EdgeDirection::Outgoing;
+use prelude::{K1_SIZE, Weight};
+use slices::BasicSlice;
+use stats::ComponentStats;
+
+use std::cmp;
+
+/// Counts weakly connected components of the `Graph`.
+pub fn connected_components<G: Graph>(graph: &G) -> usize {
+    let mut parents = (0..graph.node_count()).collect::<Vec<usize>>();
+    let mut components = graph.node_count();
+    for i in 0..graph.edge_count() {
+        let (source, target) = unwrap!(graph.edge_endpoints(G::EdgeIdentifier::new(i)));
+        let a = find_root(&mut parents, source.index());
+        let b = find_root(&mut parents, target.index());
+        if a != b {
+            parents[a] = b;
+            components -= 1;
+        }
+    }
+    components
+}
+
+/// Removes weakly connected components of the `Graph` which total length is
+/// lower than `min_length` or total weight is lower than `min_weight`. Length
+/// of the component is the sum of lengths of its edges without their source
+/// (k-1)-mers, i.e. the number of k-mers in the unshrunk `Graph`.
+pub fn remove_small_components<G: GraphMut>(graph: &mut G, min_length: usize, min_weight: f64)
+                                         -> ComponentStats {
+    let k1 = unsafe { K1_SIZE };
+    let mut parents = (0..graph.node_count()).collect::<Vec<usize>>();
+    for i in 0..graph.edge_count() {
+        let (source, target) = unwrap!(graph.edge_endpoints(G::EdgeIdentifier::new(i)));
+        let a = find_root(&mut parents, source.index());
+        let b = find_root(&mut parents, target.index());
+        parents[a] = b;
+    }
+    let roots = (0..graph.node_count())
+        .map(|n| find_root(&mut parents, n))
+        .collect::<Vec<usize>>();
+    let root_of = |graph: &G, e: G::EdgeIdentifier| {
+        roots[unwrap!(graph.edge_endpoints(e)).0.index()]
+    };
+    // total length and weight of each component, indexed by its root
+    let mut totals = vec![(0, 0.0); graph.node_count()];
+    for i in 0..graph.edge_count() {
+        let e = G::EdgeIdentifier::new(i);
+        let &(slice, weight) = unwrap!(graph.edge_weight(e));
+        let total = &mut totals[root_of(graph, e)];
+        total.0 += slice.byte_name().len() - k1;
+        total.1 += weight.to_f64();
+    }
+    let small = |root: usize| totals[root].0 < min_length || totals[root].1 < min_weight;
+    let mut stats = ComponentStats::default();
+    for n in (0..graph.node_count()).filter(|&n| roots[n] == n) {
+        stats.components += 1;
+        if small(n) {
+            stats.removed_components += 1;
+            stats.removed_length += totals[n].0;
+        }
+    }
+    let to_remove = (0..graph.edge_count())
+        .map(G::EdgeIdentifier::new)
+        .filter(|&e| small(root_of(graph, e)))
+        .collect::<Vec<G::EdgeIdentifier>>();
+    stats.removed_edges = to_remove.len();
+    graph.remove_edges(to_remove);
+    graph.remove_single_vertices();
+    stats
+}
+
+/// Finds representative of the set containing `x` in the disjoint-set forest
+/// given by `parents`, halving the path to it on the way.
+pub fn find_root(parents: &mut [usize], mut x: usize) -> usize {
+    while parents[x] != x {
+        // path halving
+        parents[x] = parents[parents[x]];
+        x = parents[x];
+    }
+    x
+}
+
+// Per-node data of the Tarjan's algorithm.
+#[derive(Clone, Copy)]
+struct NodeData {
+    index: Option<usize>,
+    lowlink: usize,
+    on_stack: bool,
+}
+
+/// Finds strongly connected components of the `Graph` with the Tarjan's
+/// algorithm.
+///
+/// Components are returned in the reverse topological order, i.e. the last
+/// component has no incoming edges from the other ones. Nodes of the component
+/// are stored in the order in which they were removed from the stack, exactly
+/// as `petgraph::algo::tarjan_scc` does.
+pub fn tarjan_scc<G: Graph>(graph: &G) -> Vec<Vec<G::NodeIdentifier>> {
+    let mut sccs = vec![];
+    let empty = NodeData {
+        index: None,
+        lowlink: !0,
+        on_stack: false,
+    };
+    let mut nodes = vec![empty; graph.node_count()];
+    let mut stack = vec![];
+    let mut index = 0;
+    // nodes which are visited at the moment along with their successors and
+    // position of the next successor to check, emulates the recursion
+    let mut visits: Vec<(G::NodeIdentifier, Vec<G::NodeIdentifier>, usize)> = vec![];
+    for start in (0..graph.node_count()).map(G::NodeIdentifier::new) {
+        if nodes[start.index()].index.is_some() {
+            continue;
+        }
+        visit(graph, start, &mut nodes, &mut stack, &mut index, &mut visits);
+        while !visits.is_empty() {
+            let (v, w) = {
+                let last = unwrap!(visits.last_mut());
+                if last.2 == last.1.len() {
+                    (last.0, None)
+                }
+                else {
+                    last.2 += 1;
+                    (last.0, Some(last.1[last.2 - 1]))
+                }
+            };
+            match w {
+                Some(w) => {
+                    match nodes[w.index()].index {
+                        None => visit(graph, w, &mut nodes, &mut stack, &mut index, &mut visits),
+                        Some(w_index) => {
+                            if nodes[w.index()].on_stack {
+                                let data = &mut nodes[v.index()];
+                                data.lowlink = cmp::min(data.lowlink, w_index);
+                            }
+                        }
+                    }
+                }
+                None => {
+                    visits.pop();
+                    // v is a root node, pop the stack and generate an SCC
+                    if nodes[v.index()].lowlink == unwrap!(nodes[v.index()].index) {
+                        let mut scc = vec![];
+                        loop {
+                            let w = unwrap!(stack.pop());
+                            nodes[w.index()].on_stack = false;
+                            scc.push(w);
+                            if w == v {
+                                break;
+                            }
+                        }
+                        sccs.push(scc);
+                    }
+                    // propagate lowlink to the parent
+                    if let Some(&(parent, _, _)) = visits.last() {
+                        let lowlink = nodes[v.index()].lowlink;
+                        let data = &mut nodes[parent.index()];
+                        data.lowlink = cmp::min(data.lowlink, lowlink);
+                    }
+                }
+            }
+        }
+    }
+    sccs
+}
+
+fn visit<G: Graph>(graph: &G, v: G::NodeIdentifier, nodes: &mut [NodeData],
+                   stack: &mut Vec<G::NodeIdentifier>, index: &mut usize,
+                   visits: &mut Vec<(G::NodeIdentifier, Vec<G::NodeIdentifier>, usize)>) {
+    nodes[v.index()] = NodeData {
+        index: Some(*index),
+        lowlink: *index,
+        on_stack: true,
+    };
+    *index += 1;
+    stack.push(v);
+    let successors = graph.edges_of(v, Outgoing)
+        .into_iter()
+        .map(|e| unwrap!(graph.edge_endpoints(e)).1)
+        .collect();
+    visits.push((v, successors, 0));
+}
+
+#[cfg(test)]
+mod tests {
+    use ::asm::SEQUENCES;
+    use ::asm::lock::LOCK;
+    use ::collections::graphs::pt_graph::PtGraph;
+    use ::compress::compress_edge;
+    use ::slices::{BasicSlice, EdgeSlice};
+    use ::stats::ComponentStats;
+    use super::*;
+
+    // path of three strong k-mers and a single weak k-mer
+    fn two_components() -> PtGraph {
+        let sequence = {
+            let mut s = SEQUENCES.write();
+            s.clear();
+            s.push(&[]);
+            EdgeSlice::new(s.push(&compress_edge(&[b'A'; 40])))
+        };
+        PtGraph::from_edges(&[(0, 1, (sequence, 5)),
+                              (1, 2, (sequence, 5)),
+                              (2, 3, (sequence, 5)),
+                              (4, 5, (sequence, 2))])
+    }
+
+    #[test]
+    fn counts_weakly_connected_components() {
+        let mut g = PtGraph::from_edges(&[(0, 1, (EdgeSlice::default(), 1)),
+                                          (2, 1, (EdgeSlice::default(), 1)),
+                                          (3, 4, (EdgeSlice::default(), 1))]);
+        g.add_node(());
+        assert_eq!(connected_components(&g), 3);
+    }
+
+    #[test]
+    fn finds_strongly_connected_components() {
+        // 0 -> 1 -> 2 -> 1, 2 -> 3
+        let g = PtGraph::from_edges(&[(0, 1, (EdgeSlice::default(), 1)),
+                                      (1, 2, (EdgeSlice::default(), 1)),
+                                      (2, 1, (EdgeSlice::default(), 1)),
+                                      (2, 3, (EdgeSlice::default(), 1))]);
+        let sccs = tarjan_scc(&g);
+        assert_eq!(sccs.len(), 3);
+        assert_eq!(sccs[0].len(), 1);
+        assert_eq!(sccs[0][0].index(), 3);
+        let mut cycle = sccs[1].iter().map(|n| n.index()).collect::<Vec<usize>>();
+        cycle.sort();
+        assert_eq!(cycle, vec![1, 2]);
+        assert_eq!(sccs[2][0].index(), 0);
+    }
+
+    #[test]
+    fn removes_short_components() {
+        let _l = LOCK.lock().unwrap();
+        let mut g = two_components();
+        assert_eq!(remove_small_components(&mut g, 2, 0.0),
+                   ComponentStats {
+                       components: 2,
+                       removed_components: 1,
+                       removed_edges: 1,
+                       removed_length: 1,
+                   });
+        assert_eq!(g.node_count(), 4);
+        assert_eq!(g.edge_count(), 3);
+    }
+
+    #[test]
+    fn removes_weak_components() {
+        let _l = LOCK.lock().unwrap();
+        let mut g = two_components();
+        assert_eq!(remove_small_components(&mut g, 0, 16.0),
+                   ComponentStats {
+                       components: 2,
+                       removed_components: 2,
+                       removed_edges: 4,
+                       removed_length: 4,
+                   });
+        assert_eq!(g.node_count(), 0);
+        assert_eq!(g.edge_count(), 0);
+    }
+}
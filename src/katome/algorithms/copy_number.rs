@@ -0,0 +1,302 @@
+//! Copy number inference.
+//!
+//! Weight of the edge of the shrunk graph is roughly proportional to the
+//! number of copies of its sequence in the genome. Copy numbers of all edges
+//! are inferred at once by solving the min-cost flow problem, in which each
+//! edge is used at least once, flow is conserved at every node and using the
+//! edge `x` times costs `length * |x - weight / unit_coverage|`, where length is
+//! the number of k-mers of the edge. Nodes without incoming (outgoing) edges
+//! are free sources (sinks), imbalance of other nodes is penalized more than
+//! any disagreement with weights.
+//!
+//! Convex costs are split into arcs with increasing unit costs. Arcs with the
+//! negative cost are saturated upfront and the resulting excesses are routed
+//! along the successive shortest paths (Dijkstra with potentials), so that
+//! residual network never has an arc with the negative reduced cost.
+
+use collections::Graph;
+use collections::graphs::Id;
+use prelude::{K1_SIZE, Weight};
+
+use std::cmp;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+// Costs are kept as integers, scaled by this factor.
+const COST_SCALE: f64 = 1000.0;
+// Capacity of the uncapped arcs and distance to the unreachable nodes.
+const INFINITE: i64 = i64::max_value() / 4;
+
+// Arc of the residual network.
+struct Arc {
+    to: usize,
+    capacity: i64,
+    cost: i64,
+    // position of the reverse arc in the adjacency list of `to`
+    reverse: usize,
+}
+
+// Position of the arc - its source and index in the source's adjacency list.
+type ArcPosition = (usize, usize);
+
+// Residual network stored as adjacency lists.
+struct Network {
+    arcs: Vec<Vec<Arc>>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+struct State {
+    distance: i64,
+    node: usize,
+}
+
+// reversed, so that `BinaryHeap` pops the closest node first
+impl Ord for State {
+    fn cmp(&self, other: &State) -> Ordering {
+        match other.distance.cmp(&self.distance) {
+            Ordering::Equal => self.node.cmp(&other.node),
+            o => o,
+        }
+    }
+}
+
+impl PartialOrd for State {
+    fn partial_cmp(&self, other: &State) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Network {
+    fn new(nodes: usize) -> Network {
+        Network { arcs: (0..nodes).map(|_| vec![]).collect() }
+    }
+
+    fn add_arc(&mut self, from: usize, to: usize, capacity: i64, cost: i64) -> ArcPosition {
+        let forward = self.arcs[from].len();
+        let backward = self.arcs[to].len() + if from == to { 1 } else { 0 };
+        self.arcs[from].push(Arc {
+            to: to,
+            capacity: capacity,
+            cost: cost,
+            reverse: backward,
+        });
+        self.arcs[to].push(Arc {
+            to: from,
+            capacity: 0,
+            cost: -cost,
+            reverse: forward,
+        });
+        (from, forward)
+    }
+
+    // Adds arc with the negative cost and sends flow through all of its
+    // capacity, updating excesses of its endpoints.
+    fn add_saturated_arc(&mut self, excess: &mut [i64], from: usize, to: usize, capacity: i64,
+                         cost: i64)
+                         -> ArcPosition {
+        let arc = self.add_arc(from, to, capacity, cost);
+        self.push(arc, capacity);
+        excess[from] -= capacity;
+        excess[to] += capacity;
+        arc
+    }
+
+    fn push(&mut self, (node, index): ArcPosition, amount: i64) {
+        let (to, reverse) = {
+            let arc = &mut self.arcs[node][index];
+            arc.capacity -= amount;
+            (arc.to, arc.reverse)
+        };
+        self.arcs[to][reverse].capacity += amount;
+    }
+
+    // Flow through the arc is equal to the capacity of its reverse arc.
+    fn flow(&self, (node, index): ArcPosition) -> i64 {
+        let arc = &self.arcs[node][index];
+        self.arcs[arc.to][arc.reverse].capacity
+    }
+
+    // Sends as much flow as possible from the source to the sink, along the
+    // cheapest paths. Arcs with free capacity can't have negative costs.
+    fn min_cost_flow(&mut self, source: usize, sink: usize) {
+        let n = self.arcs.len();
+        let mut potential = vec![0_i64; n];
+        loop {
+            let mut distance = vec![INFINITE; n];
+            let mut previous: Vec<Option<ArcPosition>> = vec![None; n];
+            let mut heap = BinaryHeap::new();
+            distance[source] = 0;
+            heap.push(State {
+                distance: 0,
+                node: source,
+            });
+            while let Some(State { distance: d, node }) = heap.pop() {
+                if d > distance[node] {
+                    continue;
+                }
+                for (i, arc) in self.arcs[node].iter().enumerate() {
+                    if arc.capacity == 0 {
+                        continue;
+                    }
+                    let next = d + arc.cost + potential[node] - potential[arc.to];
+                    if next < distance[arc.to] {
+                        distance[arc.to] = next;
+                        previous[arc.to] = Some((node, i));
+                        heap.push(State {
+                            distance: next,
+                            node: arc.to,
+                        });
+                    }
+                }
+            }
+            if distance[sink] == INFINITE {
+                return;
+            }
+            for v in 0..n {
+                if distance[v] < INFINITE {
+                    potential[v] += distance[v];
+                }
+            }
+            let mut amount = INFINITE;
+            let mut v = sink;
+            while let Some((u, i)) = previous[v] {
+                amount = cmp::min(amount, self.arcs[u][i].capacity);
+                v = u;
+            }
+            v = sink;
+            while let Some((u, i)) = previous[v] {
+                self.push((u, i), amount);
+                v = u;
+            }
+        }
+    }
+}
+
+#[inline]
+fn scaled(cost: f64) -> i64 {
+    (cost * COST_SCALE).round() as i64
+}
+
+#[inline]
+fn length_of<G: Graph>(graph: &G, edge: G::EdgeIdentifier) -> usize {
+    unwrap!(graph.edge_sequence(edge)).len() - unsafe { K1_SIZE }
+}
+
+/// Estimates weight of the single copy of the edge, as the median of edge
+/// weights in which each edge counts as many times as it has k-mers.
+pub fn unit_coverage<G: Graph>(graph: &G) -> f64 {
+    let mut weights = (0..graph.edge_count())
+        .map(G::EdgeIdentifier::new)
+        .map(|e| (unwrap!(graph.weight(e)).to_f64(), length_of(graph, e)))
+        .collect::<Vec<(f64, usize)>>();
+    weights.sort_by(|a, b| unwrap!(a.0.partial_cmp(&b.0)));
+    let total = weights.iter().map(|w| w.1).sum::<usize>();
+    let mut seen = 0;
+    for (weight, length) in weights {
+        seen += length;
+        if 2 * seen >= total {
+            return if weight > 0.0 { weight } else { 1.0 };
+        }
+    }
+    1.0
+}
+
+/// Infers copy number of each edge of the graph, indexed by edges.
+///
+/// Graph should be shrunk, otherwise lengths of edges don't differentiate
+/// costs.
+pub fn copy_numbers<G: Graph>(graph: &G, unit_coverage: f64) -> Vec<usize> {
+    let n = graph.node_count();
+    let (hub, source, sink) = (n, n + 1, n + 2);
+    let mut network = Network::new(n + 3);
+    let mut excess = vec![0_i64; n];
+    let lengths = (0..graph.edge_count())
+        .map(|i| length_of(graph, G::EdgeIdentifier::new(i)))
+        .collect::<Vec<usize>>();
+    let penalty = scaled((lengths.iter().sum::<usize>() + 1) as f64);
+    let mut edge_arcs = Vec::with_capacity(lengths.len());
+    for (i, &length) in lengths.iter().enumerate() {
+        let e = G::EdgeIdentifier::new(i);
+        let (s, t) = unwrap!(graph.edge_endpoints(e));
+        let (s, t) = (s.index(), t.index());
+        let length = length as f64;
+        let copies = unwrap!(graph.weight(e)).to_f64() / unit_coverage;
+        // cost of increasing the copy number from `x - 1` to `x`
+        let marginal = |x: i64| {
+            length * ((x as f64 - copies).abs() - (x as f64 - 1.0 - copies).abs())
+        };
+        // the first copy is mandatory
+        excess[s] -= 1;
+        excess[t] += 1;
+        let mut arcs = vec![];
+        let whole = copies.floor() as i64;
+        if whole > 1 {
+            arcs.push(network.add_saturated_arc(&mut excess, s, t, whole - 1, scaled(-length)));
+        }
+        let next = cmp::max(whole + 1, 2);
+        let next_cost = scaled(marginal(next));
+        if next_cost < 0 {
+            arcs.push(network.add_saturated_arc(&mut excess, s, t, 1, next_cost));
+        }
+        else if next_cost < scaled(length) {
+            arcs.push(network.add_arc(s, t, 1, next_cost));
+        }
+        arcs.push(network.add_arc(s, t, INFINITE, scaled(length)));
+        edge_arcs.push(arcs);
+    }
+    for v in 0..n {
+        let node = G::NodeIdentifier::new(v);
+        let start = if graph.in_degree(node) == 0 { 0 } else { penalty };
+        let end = if graph.out_degree(node) == 0 { 0 } else { penalty };
+        network.add_arc(hub, v, INFINITE, start);
+        network.add_arc(v, hub, INFINITE, end);
+        if excess[v] > 0 {
+            network.add_arc(source, v, excess[v], 0);
+        }
+        else if excess[v] < 0 {
+            network.add_arc(v, sink, -excess[v], 0);
+        }
+    }
+    network.min_cost_flow(source, sink);
+    edge_arcs.iter()
+        .map(|arcs| 1 + arcs.iter().map(|&a| network.flow(a)).sum::<i64>() as usize)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{INFINITE, Network};
+
+    #[test]
+    fn sends_flow_along_cheapest_paths() {
+        // 0 -> 1 -> 3 costs 2, 0 -> 2 -> 3 costs 5, 4 is the source, 5 the sink
+        let mut network = Network::new(6);
+        let a = network.add_arc(0, 1, 2, 1);
+        let b = network.add_arc(1, 3, 2, 1);
+        let c = network.add_arc(0, 2, INFINITE, 2);
+        let d = network.add_arc(2, 3, INFINITE, 3);
+        network.add_arc(4, 0, 3, 0);
+        network.add_arc(3, 5, 3, 0);
+        network.min_cost_flow(4, 5);
+        assert_eq!(network.flow(a), 2);
+        assert_eq!(network.flow(b), 2);
+        assert_eq!(network.flow(c), 1);
+        assert_eq!(network.flow(d), 1);
+    }
+
+    #[test]
+    fn cancels_flow_of_saturated_arcs() {
+        // saturated 0 -> 1 has to be partially cancelled, as 1 can pass only
+        // one unit further
+        let mut network = Network::new(4);
+        let mut excess = vec![0; 2];
+        let a = network.add_saturated_arc(&mut excess, 0, 1, 3, -1);
+        assert_eq!(excess, vec![-3, 3]);
+        assert_eq!(network.flow(a), 3);
+        network.add_arc(1, 0, 1, 0);
+        network.add_arc(2, 1, 3, 0);
+        network.add_arc(0, 3, 3, 0);
+        network.min_cost_flow(2, 3);
+        assert_eq!(network.flow(a), 1);
+    }
+}
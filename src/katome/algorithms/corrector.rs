@@ -0,0 +1,338 @@
+//! K-mer spectrum based correction of sequencing errors.
+//!
+//! K-mers which occur in the input at least `threshold` times are solid,
+//! the remaining ones are weak. Single sequencing error makes weak every
+//! k-mer covering it, so each run of consecutive weak k-mers of the read
+//! points at the bases shared by all of its k-mers. Similarly to Quake and BFC
+//! the pre-pass reads input twice:
+//!
+//! 1. occurrences of all k-mers are counted.
+//! 2. in each read the base is substituted for every run of weak k-mers, if
+//!    exactly one substitution makes all k-mers covering it solid. Reads are
+//!    then added to the collection.
+//!
+//! Reads in which some run can't be corrected unambiguously are added as they
+//! are, leaving the errors to the pruner.
+//!
+//! K-mers are counted in their compressed, canonical form. Lookups compress
+//! k-mers into buffers reused within the read, so they don't allocate.
+
+use algorithms::builder::{AddReads, for_each_read};
+use compress::{CHARS_PER_CARRIER, encode_fasta_symbol};
+use config::InputFileType;
+use prelude::{CDC, K_SIZE};
+
+use metrohash::MetroHash;
+
+use std::cmp;
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault as BuildHash;
+use std::path::PathBuf;
+
+const BASES: &'static [u8] = b"ACGT";
+
+/// Statistics of the error correction.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CorrectionStats {
+    /// Number of reads.
+    pub reads: usize,
+    /// Number of reads with weak k-mers.
+    pub erroneous_reads: usize,
+    /// Number of reads in which all weak k-mers were made solid.
+    pub corrected_reads: usize,
+    /// Number of substituted bases.
+    pub corrected_bases: usize,
+}
+
+// Compressed k-mer and its reverse complement, reused between the lookups.
+#[derive(Default)]
+struct KeyBuffer {
+    forward: Vec<CDC>,
+    reverse: Vec<CDC>,
+}
+
+impl KeyBuffer {
+    // Compresses the k-mer given by its symbols. If `reverse_complement` is
+    // set returns the smaller one of the k-mer and its reverse complement.
+    // Symbols are encoded in their alphabetical order, so compressed k-mers
+    // compare just like the uncompressed ones.
+    fn canonical<I>(&mut self, kmer: I, reverse_complement: bool) -> &[CDC]
+        where I: DoubleEndedIterator<Item = u8> + Clone
+    {
+        pack(kmer.clone().map(|s| encode_fasta_symbol(s, 0)), &mut self.forward);
+        if !reverse_complement {
+            return &self.forward;
+        }
+        // complement of the encoded symbol is its negation
+        pack(kmer.rev().map(|s| 3 - encode_fasta_symbol(s, 0)), &mut self.reverse);
+        if self.reverse < self.forward {
+            &self.reverse
+        }
+        else {
+            &self.forward
+        }
+    }
+}
+
+// Packs two bit codes of symbols into carriers, starting from the most
+// significant bits, just like `compress_node`.
+fn pack<I: Iterator<Item = CDC>>(codes: I, packed: &mut Vec<CDC>) {
+    packed.clear();
+    let mut carrier: CDC = 0;
+    let mut length = 0;
+    for code in codes {
+        carrier = carrier << 2 | code;
+        length += 1;
+        if length % CHARS_PER_CARRIER == 0 {
+            packed.push(carrier);
+            carrier = 0;
+        }
+    }
+    let remainder = length % CHARS_PER_CARRIER;
+    if remainder != 0 {
+        packed.push(carrier << (2 * (CHARS_PER_CARRIER - remainder)));
+    }
+}
+
+/// Numbers of occurrences of k-mers in the input.
+pub struct KmerSpectrum {
+    counts: HashMap<Box<[CDC]>, u32, BuildHash<MetroHash>>,
+    threshold: u32,
+    reverse_complement: bool,
+}
+
+impl KmerSpectrum {
+    /// Creates empty spectrum, in which k-mers occurring at least `threshold`
+    /// times are solid. K-mers are counted along with their reverse
+    /// complements if `reverse_complement` is set.
+    pub fn new(threshold: usize, reverse_complement: bool) -> KmerSpectrum {
+        KmerSpectrum {
+            counts: HashMap::default(),
+            threshold: threshold as u32,
+            reverse_complement: reverse_complement,
+        }
+    }
+
+    /// Counts k-mers of the read.
+    pub fn add_read(&mut self, read: &[u8]) {
+        let k = unsafe { K_SIZE };
+        if read.len() < k {
+            return;
+        }
+        let mut buffer = KeyBuffer::default();
+        for window in read.windows(k) {
+            let key = buffer.canonical(window.iter().cloned(), self.reverse_complement);
+            if let Some(count) = self.counts.get_mut(key) {
+                *count = count.saturating_add(1);
+                continue;
+            }
+            self.counts.insert(key.to_vec().into_boxed_slice(), 1);
+        }
+    }
+
+    /// Gets number of distinct k-mers.
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Checks if the spectrum is empty.
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Gets number of distinct solid k-mers.
+    pub fn solid_count(&self) -> usize {
+        self.counts.values().filter(|&&count| count >= self.threshold).count()
+    }
+
+    /// Checks if the k-mer occurs at least `threshold` times.
+    pub fn is_solid(&self, kmer: &[u8]) -> bool {
+        self.solid(kmer.iter().cloned(), &mut KeyBuffer::default())
+    }
+
+    fn solid<I>(&self, kmer: I, buffer: &mut KeyBuffer) -> bool
+        where I: DoubleEndedIterator<Item = u8> + Clone
+    {
+        self.counts
+            .get(buffer.canonical(kmer, self.reverse_complement))
+            .map_or(false, |&count| count >= self.threshold)
+    }
+
+    /// Corrects the read, returns it along with the number of substituted
+    /// bases. Returns none if weak k-mers of the read can't be made solid.
+    pub fn correct(&self, read: &[u8]) -> Option<(Vec<u8>, usize)> {
+        let k = unsafe { K_SIZE };
+        let mut read = read.to_vec();
+        if read.len() < k {
+            return Some((read, 0));
+        }
+        let mut buffer = KeyBuffer::default();
+        let weak = (0..read.len() - k + 1)
+            .filter(|&i| !self.solid(read[i..i + k].iter().cloned(), &mut buffer))
+            .collect::<Vec<usize>>();
+        let mut corrected = 0;
+        for (first, last) in runs(&weak) {
+            // error is covered by every k-mer of the run
+            let mut fix = None;
+            for p in last..cmp::min(first + k, read.len()) {
+                for &base in BASES.iter().filter(|&&b| b != read[p]) {
+                    if !self.solid_with(&read, p, base, &mut buffer) {
+                        continue;
+                    }
+                    if fix.is_some() {
+                        return None;
+                    }
+                    fix = Some((p, base));
+                }
+            }
+            match fix {
+                Some((p, base)) => {
+                    read[p] = base;
+                    corrected += 1;
+                }
+                None => return None,
+            }
+        }
+        Some((read, corrected))
+    }
+
+    // Checks if all k-mers covering the position are solid after substituting
+    // the base at it.
+    fn solid_with(&self, read: &[u8], position: usize, base: u8, buffer: &mut KeyBuffer)
+                  -> bool {
+        let k = unsafe { K_SIZE };
+        let first = position.saturating_sub(k - 1);
+        let last = cmp::min(position, read.len() - k);
+        (first..last + 1).all(|start| {
+            let kmer = (start..start + k).map(|i| if i == position { base } else { read[i] });
+            self.solid(kmer, buffer)
+        })
+    }
+}
+
+// Splits sorted positions into runs of consecutive ones, given by their first
+// and last position.
+fn runs(positions: &[usize]) -> Vec<(usize, usize)> {
+    let mut runs: Vec<(usize, usize)> = vec![];
+    for &p in positions {
+        if let Some(run) = runs.last_mut() {
+            if run.1 + 1 == p {
+                run.1 = p;
+                continue;
+            }
+        }
+        runs.push((p, p));
+    }
+    runs
+}
+
+/// Creates collection from the FASTA/FASTQ input files, correcting reads
+/// against the spectrum of k-mers with the given `threshold`. Returns
+/// collection and total number of read bytes.
+pub fn create_corrected<T: AddReads>(input_files: &[PathBuf], ft: InputFileType,
+                                     reverse_complement: bool, threshold: usize)
+                                     -> (T, usize) {
+    info!("Counting k-mers for error correction");
+    let mut spectrum = KmerSpectrum::new(threshold, reverse_complement);
+    for_each_read(input_files, ft, |read| spectrum.add_read(read));
+    let solid = spectrum.solid_count();
+    info!("Counted {} distinct k-mers, {} of them solid", spectrum.len(), solid);
+    info!("Starting to build collection");
+    // weak k-mers left in the reads which can't be corrected are not counted
+    let mut collection = T::init(Some(solid), Some(solid), ft);
+    let mut stats = CorrectionStats::default();
+    let total = for_each_read(input_files, ft, |read| {
+        stats.reads += 1;
+        match spectrum.correct(read) {
+            Some((_, 0)) => collection.add_read_fastaq(read, reverse_complement),
+            Some((corrected, bases)) => {
+                stats.erroneous_reads += 1;
+                stats.corrected_reads += 1;
+                stats.corrected_bases += bases;
+                collection.add_read_fastaq(&corrected, reverse_complement);
+            }
+            None => {
+                stats.erroneous_reads += 1;
+                collection.add_read_fastaq(read, reverse_complement);
+            }
+        }
+    });
+    info!("Collection built, {} out of {} reads had weak k-mers, corrected {} bases in {} reads",
+          stats.erroneous_reads,
+          stats.reads,
+          stats.corrected_bases,
+          stats.corrected_reads);
+    (collection, total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::{BASES, runs};
+    use utils::reverse_complement_of;
+
+    // deterministic pseudo-random read
+    fn read(length: usize, seed: u64) -> Vec<u8> {
+        let mut x = seed;
+        (0..length)
+            .map(|_| {
+                x = x.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                BASES[(x >> 33) as usize & 3]
+            })
+            .collect()
+    }
+
+    fn spectrum(reference: &[u8]) -> KmerSpectrum {
+        let mut spectrum = KmerSpectrum::new(3, false);
+        for _ in 0..5 {
+            spectrum.add_read(reference);
+        }
+        spectrum
+    }
+
+    fn substituted(reference: &[u8], positions: &[usize]) -> Vec<u8> {
+        let mut read = reference.to_vec();
+        for &p in positions {
+            read[p] = if read[p] == b'A' { b'C' } else { b'A' };
+        }
+        read
+    }
+
+    #[test]
+    fn splits_positions_into_runs() {
+        assert_eq!(runs(&[1, 2, 3, 7, 9, 10]), vec![(1, 3), (7, 7), (9, 10)]);
+        assert_eq!(runs(&[]), vec![]);
+    }
+
+    #[test]
+    fn counts_reverse_complements_together() {
+        let reference = read(200, 2);
+        let mut spectrum = KmerSpectrum::new(2, true);
+        spectrum.add_read(&reference);
+        spectrum.add_read(&reverse_complement_of(&reference));
+        assert_eq!(spectrum.solid_count(), spectrum.len());
+        let k = unsafe { K_SIZE };
+        assert!(reference.windows(k).all(|w| spectrum.is_solid(w)));
+    }
+
+    #[test]
+    fn keeps_read_without_weak_kmers() {
+        let reference = read(200, 1);
+        assert_eq!(spectrum(&reference).correct(&reference), Some((reference.clone(), 0)));
+    }
+
+    #[test]
+    fn corrects_separate_errors() {
+        let reference = read(200, 1);
+        // errors at both ends and in the middle of the read
+        let erroneous = substituted(&reference, &[0, 100, 199]);
+        assert_eq!(spectrum(&reference).correct(&erroneous), Some((reference.clone(), 3)));
+    }
+
+    #[test]
+    fn gives_up_on_close_errors() {
+        let reference = read(200, 1);
+        let erroneous = substituted(&reference, &[100, 103]);
+        assert_eq!(spectrum(&reference).correct(&erroneous), None);
+    }
+}
@@ -0,0 +1,227 @@
+//! Estimation of the collection size from the input files.
+//!
+//! Collections reallocate (and hash tables rehash) repeatedly if they grow
+//! from the default capacity. To avoid it a sample of the reads, taken evenly
+//! across the input, is scanned before the build and distinct k-mers and
+//! (k-1)-mers in it are counted with HyperLogLog sketches. Counts are then
+//! extrapolated to the whole input. Reads overlap more and more as the input
+//! grows, so the extrapolation follows the number of new sequences gained by
+//! the second half of the sample, instead of scaling the sample linearly.
+
+use algorithms::builder::for_each_read_in;
+use config::InputFileType;
+use prelude::{K_SIZE, K1_SIZE};
+use utils::reverse_complement_of;
+
+use metrohash::MetroHash64;
+
+use std::cmp;
+use std::fs::metadata;
+use std::hash::Hasher;
+use std::path::PathBuf;
+
+/// Number of bytes of the input sampled to estimate size of the collection.
+pub const SAMPLE_BYTES: usize = 16 * 1024 * 1024;
+// Number of evenly spaced parts of the input, which make up the sample.
+const PARTS: u64 = 16;
+// Number of bits of the hash used to select the register.
+const PRECISION: u32 = 14;
+
+/// HyperLogLog cardinality estimator, see Flajolet et al. "HyperLogLog: the
+/// analysis of a near-optimal cardinality estimation algorithm".
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> HyperLogLog {
+        HyperLogLog { registers: vec![0; 1 << PRECISION] }
+    }
+}
+
+impl HyperLogLog {
+    /// Adds sequence to the sketch.
+    pub fn insert(&mut self, sequence: &[u8]) {
+        let mut hasher = MetroHash64::with_seed(0);
+        hasher.write(sequence);
+        let hash = hasher.finish();
+        let register = (hash >> (64 - PRECISION)) as usize;
+        // position of the first set bit in the remaining bits
+        let rank = cmp::min((hash << PRECISION).leading_zeros(), 64 - PRECISION) + 1;
+        if self.registers[register] < rank as u8 {
+            self.registers[register] = rank as u8;
+        }
+    }
+
+    /// Estimates number of distinct sequences added to the sketch.
+    pub fn estimate(&self) -> usize {
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum = self.registers.iter().map(|&r| 2_f64.powi(-(r as i32))).sum::<f64>();
+        let raw = alpha * m * m / sum;
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        if raw <= 2.5 * m && zeros > 0 {
+            // linear counting works better for small cardinalities
+            (m * (m / zeros as f64).ln()).round() as usize
+        }
+        else {
+            raw.round() as usize
+        }
+    }
+}
+
+/// Estimated size of the collection.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SizeEstimate {
+    /// Estimated number of distinct k-mers (edges).
+    pub kmers: usize,
+    /// Estimated number of distinct (k-1)-mers (nodes).
+    pub nodes: usize,
+}
+
+// Sketches of the whole sample and of every other read of the sample.
+#[derive(Default)]
+struct Sample {
+    all: HyperLogLog,
+    half: HyperLogLog,
+}
+
+impl Sample {
+    fn insert(&mut self, sequence: &[u8], in_half: bool) {
+        self.all.insert(sequence);
+        if in_half {
+            self.half.insert(sequence);
+        }
+    }
+
+    // Sequences gained by adding the second half of the sample to the first
+    // one are the upper bound of the gain of every further half. It can't be
+    // larger than the first half itself, so the estimate never exceeds the
+    // linear scaling.
+    fn extrapolate(&self, scale: f64) -> usize {
+        let all = self.all.estimate() as f64;
+        let half = self.half.estimate() as f64;
+        let gain = (all - half).max(0.0).min(half);
+        (all + 2.0 * gain * (scale - 1.0)).round() as usize
+    }
+}
+
+/// Estimates number of distinct k-mers and (k-1)-mers in the FASTA/FASTQ
+/// input files, based on the sample of roughly `SAMPLE_BYTES` bytes of them.
+pub fn estimate_size(input_files: &[PathBuf], ft: InputFileType, reverse_complement: bool)
+                     -> SizeEstimate {
+    estimate_size_from_sample(input_files, ft, reverse_complement, SAMPLE_BYTES)
+}
+
+/// Estimates number of distinct k-mers and (k-1)-mers in the FASTA/FASTQ
+/// input files, based on the sample of roughly `sample_bytes` bytes of the
+/// files. The sample consists of evenly spaced parts of the input, so that it
+/// spans all of it, and is scaled by sizes of the files.
+pub fn estimate_size_from_sample(input_files: &[PathBuf], ft: InputFileType,
+                                 reverse_complement: bool, sample_bytes: usize)
+                                 -> SizeEstimate {
+    let (k, k1) = unsafe { (K_SIZE, K1_SIZE) };
+    let sizes = file_sizes(input_files);
+    let total = sizes.iter().sum::<u64>();
+    if total == 0 {
+        return SizeEstimate::default();
+    }
+    let mut kmers = Sample::default();
+    let mut nodes = Sample::default();
+    let mut sampled = 0_u64;
+    let mut in_half = false;
+    for (file, offset, length) in sample_parts(&sizes, sample_bytes as u64) {
+        sampled += length;
+        for_each_read_in(&input_files[file], offset, length, ft, |read| {
+            in_half = !in_half;
+            if read.len() < k {
+                return;
+            }
+            let rc = if reverse_complement { reverse_complement_of(read) } else { vec![] };
+            for r in &[read, &rc[..]] {
+                for window in r.windows(k) {
+                    kmers.insert(window, in_half);
+                }
+                for window in r.windows(k1) {
+                    nodes.insert(window, in_half);
+                }
+            }
+        });
+    }
+    if sampled < total {
+        info!("Sampled {} bytes of the input out of {}", sampled, total);
+    }
+    let scale = total as f64 / sampled as f64;
+    SizeEstimate {
+        kmers: kmers.extrapolate(scale),
+        nodes: nodes.extrapolate(scale),
+    }
+}
+
+// Parts of the input files, given as index of the file, offset and length,
+// which make up the sample. Small input is sampled whole, otherwise `PARTS`
+// parts are evenly spaced across all of the files.
+fn sample_parts(sizes: &[u64], sample_bytes: u64) -> Vec<(usize, u64, u64)> {
+    let total = sizes.iter().sum::<u64>();
+    if total <= sample_bytes {
+        return sizes.iter().enumerate().map(|(i, &size)| (i, 0, size)).collect();
+    }
+    let length = cmp::max(1, sample_bytes / PARTS);
+    (0..PARTS)
+        .map(|p| {
+            let mut offset = p * total / PARTS;
+            let mut file = 0;
+            while offset >= sizes[file] {
+                offset -= sizes[file];
+                file += 1;
+            }
+            (file, offset, cmp::min(length, sizes[file] - offset))
+        })
+        .collect()
+}
+
+/// Estimates number of read bytes (and thus the upper bound on the number of
+/// k-mers) in the FASTA/FASTQ input files from their sizes.
+pub fn input_bytes(input_files: &[PathBuf], ft: InputFileType) -> usize {
+    let bytes = file_sizes(input_files).iter().sum::<u64>() as usize;
+    match ft {
+        // roughly half of the FASTQ file consists of qualities
+        InputFileType::Fastq => bytes / 2,
+        _ => bytes,
+    }
+}
+
+fn file_sizes(input_files: &[PathBuf]) -> Vec<u64> {
+    input_files.iter()
+        .map(|f| match metadata(f) {
+            Ok(m) => m.len(),
+            Err(why) => panic!("Couldn't read metadata of {}: {}", f.display(), why),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_small_sets() {
+        let mut hll = HyperLogLog::default();
+        for i in 0..100_u32 {
+            hll.insert(format!("{}", i % 50).as_bytes());
+        }
+        // collisions of registers are possible, but unlikely
+        let estimate = hll.estimate();
+        assert!(estimate >= 49 && estimate <= 51);
+    }
+
+    #[test]
+    fn estimates_large_sets() {
+        let mut hll = HyperLogLog::default();
+        for i in 0..200_000_u32 {
+            hll.insert(format!("{}", i).as_bytes());
+        }
+        let estimate = hll.estimate() as f64;
+        assert!((estimate - 200_000.0).abs() / 200_000.0 < 0.05);
+    }
+}
@@ -0,0 +1,170 @@
+//! Gap closing.
+//!
+//! Contigs end at ambiguous nodes of the graph, so the sequence missing
+//! between contigs joined in the scaffold is often spelled by some path of
+//! the graph. Gap is closed if exactly one path leads from the last node of
+//! the preceding contig to the first node of the following one, and the
+//! sequence between them differs from the estimated gap by at most the given
+//! tolerance.
+//!
+//! Collapsing destroys the graph, so the graph has to be copied beforehand.
+
+use algorithms::collapser::SerializedContigs;
+use algorithms::scaffolder::{ScaffoldPart, Scaffolds};
+use asm::SEQUENCES;
+use collections::Graph;
+use collections::graphs::{EdgeDirection, Id, SequencesToNodes, index_nodes};
+use collections::graphs::pt_graph::{NodeIndex, WeightedPtGraph};
+use compress::{compress_edge, compress_node};
+use prelude::K1_SIZE;
+use slices::{BasicSlice, EdgeSlice};
+use utils::reverse_complement_of;
+
+use std::cmp;
+
+// Number of edges after which the search for the path is abandoned.
+const MAX_SEARCH_STEPS: usize = 100000;
+
+/// Copies the graph into the `PtGraph` with its own sequences, so that the
+/// copy is left intact when the graph is collapsed.
+pub fn copy_graph<G: Graph>(graph: &G) -> WeightedPtGraph<G::Weight> {
+    let mut copy = WeightedPtGraph::with_capacity(graph.node_count(), graph.edge_count());
+    for _ in 0..graph.node_count() {
+        copy.add_node(());
+    }
+    for i in 0..graph.edge_count() {
+        let e = G::EdgeIdentifier::new(i);
+        let (source, target) = unwrap!(graph.edge_endpoints(e));
+        let sequence = compress_edge(&unwrap!(graph.edge_sequence(e)));
+        let weight = unwrap!(graph.weight(e));
+        let id = SEQUENCES.write().push(&sequence);
+        copy.add_edge(NodeIndex::new(source.index()),
+                      NodeIndex::new(target.index()),
+                      (EdgeSlice::new(id), weight));
+    }
+    copy
+}
+
+/// Replaces gaps between contigs of scaffolds with sequences of unique paths
+/// of the graph, which lengths differ from the estimated gaps by at most
+/// `tolerance`. Returns number of closed gaps.
+pub fn close_gaps<G: Graph>(graph: &G, contigs: &SerializedContigs, scaffolds: &mut Scaffolds,
+                            tolerance: usize)
+                            -> usize {
+    let nodes = index_nodes(graph);
+    let mut closed = 0;
+    let mut gaps = 0;
+    for scaffold in &mut scaffolds.scaffolds {
+        // gaps are always surrounded by contigs
+        for i in 1..scaffold.len().saturating_sub(1) {
+            let length = match scaffold[i] {
+                ScaffoldPart::Gap(length) => length,
+                _ => continue,
+            };
+            gaps += 1;
+            let left = oriented_contig(contigs, &scaffold[i - 1]);
+            let right = oriented_contig(contigs, &scaffold[i + 1]);
+            if let Some(fill) = fill_gap(graph, &nodes, &left, &right, length, tolerance) {
+                scaffold[i] = ScaffoldPart::Fill(unwrap!(String::from_utf8(fill)));
+                closed += 1;
+            }
+        }
+    }
+    info!("Closed {} out of {} gaps", closed, gaps);
+    closed
+}
+
+fn oriented_contig(contigs: &SerializedContigs, part: &ScaffoldPart) -> Vec<u8> {
+    match *part {
+        ScaffoldPart::Contig(c, true) => contigs[c].as_bytes().to_vec(),
+        ScaffoldPart::Contig(c, false) => reverse_complement_of(contigs[c].as_bytes()),
+        _ => panic!("Gap has to be surrounded by contigs"),
+    }
+}
+
+// Finds sequence between the contigs, searching the other strand if the graph
+// doesn't contain their ends.
+fn fill_gap<G: Graph>(graph: &G, nodes: &SequencesToNodes<G::NodeIdentifier>, left: &[u8],
+                      right: &[u8], length: usize, tolerance: usize)
+                      -> Option<Vec<u8>> {
+    let k1 = unsafe { K1_SIZE };
+    if left.len() < k1 || right.len() < k1 {
+        return None;
+    }
+    let (last, first) = (&left[left.len() - k1..], &right[..k1]);
+    let node = |sequence: &[u8]| {
+        let mut key = vec![];
+        compress_node(sequence, &mut key);
+        nodes.get(&key).cloned()
+    };
+    if let (Some(source), Some(target)) = (node(last), node(first)) {
+        return unique_fill(graph, source, target, length, tolerance);
+    }
+    let (last, first) = (reverse_complement_of(first), reverse_complement_of(last));
+    match (node(&last), node(&first)) {
+        (Some(source), Some(target)) => {
+            unique_fill(graph, source, target, length, tolerance)
+                .map(|fill| reverse_complement_of(&fill))
+        }
+        _ => None,
+    }
+}
+
+// Gets sequence between the nodes, if exactly one path of the right length
+// connects them.
+fn unique_fill<G: Graph>(graph: &G, source: G::NodeIdentifier, target: G::NodeIdentifier,
+                         length: usize, tolerance: usize)
+                         -> Option<Vec<u8>> {
+    let k1 = unsafe { K1_SIZE };
+    // path spells the gap followed by the target node, paths shorter than
+    // the node would make contigs overlap
+    let min = cmp::max((length + k1).saturating_sub(tolerance), k1);
+    let max = length + k1 + tolerance;
+    let mut found = None;
+    let mut steps = 0;
+    let mut path = vec![];
+    // edges left to try at each node of the path, along with length of the
+    // path up to the node
+    let mut stack = vec![(graph.edges_of(source, EdgeDirection::Outgoing), 0)];
+    loop {
+        let next = match stack.last_mut() {
+            Some(&mut (ref mut edges, spelled)) => edges.pop().map(|e| (e, spelled)),
+            None => break,
+        };
+        let (e, spelled) = match next {
+            Some(n) => n,
+            None => {
+                stack.pop();
+                path.pop();
+                continue;
+            }
+        };
+        steps += 1;
+        if steps > MAX_SEARCH_STEPS {
+            return None;
+        }
+        let remainder = unwrap!(graph.edge_sequence(e)).len() - k1;
+        let spelled = spelled + remainder;
+        if spelled > max {
+            continue;
+        }
+        let node = unwrap!(graph.edge_endpoints(e)).1;
+        path.push(e);
+        if node == target && spelled >= min {
+            if found.is_some() {
+                return None;
+            }
+            found = Some(path.clone());
+        }
+        stack.push((graph.edges_of(node, EdgeDirection::Outgoing), spelled));
+    }
+    found.map(|path| {
+        let mut fill = vec![];
+        for e in path {
+            fill.extend_from_slice(&unwrap!(graph.edge_sequence(e))[k1..]);
+        }
+        let gap = fill.len() - k1;
+        fill.truncate(gap);
+        fill
+    })
+}
@@ -4,3 +4,13 @@ pub mod standardizer;
 pub mod collapser;
 pub mod builder;
 pub mod shrinker;
+pub mod components;
+pub mod partitioner;
+pub mod bloom;
+pub mod corrector;
+pub mod estimator;
+pub mod bubbles;
+pub mod copy_number;
+pub mod threading;
+pub mod scaffolder;
+pub mod gap_closer;
@@ -0,0 +1,287 @@
+//! Minimizer-partitioned external-memory k-mer counting.
+//!
+//! Keeping every distinct k-mer in a single hash table is the peak-memory
+//! bottleneck of building the collection. Partitioned counting works in two
+//! passes instead:
+//!
+//! 1. every k-mer of every read is written into one of the on-disk buckets,
+//!    chosen by the k-mer's minimizer (the smallest m-mer inside of it). Since
+//!    consecutive k-mers usually share their minimizer, equal k-mers always end
+//!    up in the same bucket.
+//! 2. each bucket is loaded, sorted and run-length counted independently, so
+//!    the memory needed is bounded by the size of the largest bucket. Solid
+//!    k-mers (weight not lower than the threshold) are saved with their
+//!    weights and finally added to the collection through `AddReads::add_read_bfc`,
+//!    exactly like the BFCounter input.
+//!
+//! When reverse complements are requested k-mers are counted in their
+//! canonical form, as BFCounter does.
+
+use algorithms::builder::{AddReads, for_each_read};
+use compress::{compress_edge, decompress_edge, encode_fasta_symbol};
+use config::InputFileType;
+use mmap::scratch_file_name;
+use prelude::{EdgeWeight, K_SIZE, Weight};
+use utils::reverse_complement_of;
+
+use std::cmp;
+use std::fs::{File, remove_file};
+use std::io;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Parameters of the partitioned k-mer counting.
+#[derive(Clone, Debug)]
+pub struct PartitionParams {
+    /// Directory in which buckets are stored.
+    pub scratch_directory: PathBuf,
+    /// Size of the minimizer, at most 32.
+    pub minimizer_size: usize,
+    /// Number of on-disk buckets.
+    pub partitions: usize,
+    /// Minimal weight of the k-mer which is added to the collection.
+    pub solid_threshold: EdgeWeight,
+}
+
+/// Counts k-mers from the input files in the external memory and creates
+/// collection out of solid ones. Returns collection and total number of read
+/// bytes.
+pub fn create_partitioned<T: AddReads>(input_files: &[PathBuf], ft: InputFileType,
+                                       reverse_complement: bool, params: &PartitionParams)
+                                       -> (T, usize) {
+    let k = unsafe { K_SIZE };
+    assert!(params.minimizer_size > 0 && params.minimizer_size <= cmp::min(k, 32),
+            "Minimizer size has to be in range [1, min(k, 32)]");
+    assert!(params.partitions > 0, "There has to be at least one partition");
+    let record_size = compress_edge(&vec![b'A'; k]).len();
+    let mut buckets = Buckets::new(&params.scratch_directory, params.partitions);
+    info!("Partitioning k-mers into {} buckets", params.partitions);
+    let total = for_each_read(input_files, ft, |read| {
+        buckets.add_read(read, params.minimizer_size, reverse_complement)
+    });
+    let bucket_paths = buckets.finish();
+    info!("Counting k-mers in buckets");
+    let solid_path = params.scratch_directory.join(scratch_file_name("solid"));
+    let mut counts = PartitionCounts::default();
+    {
+        let mut solid = BufWriter::new(create_file(&solid_path));
+        for path in &bucket_paths {
+            count_bucket(path,
+                         record_size,
+                         params.solid_threshold,
+                         &mut solid,
+                         &mut counts);
+            if let Err(why) = remove_file(path) {
+                warn!("Couldn't remove bucket {}: {}", path.display(), why);
+            }
+        }
+    }
+    info!("Counted {} k-mers, {} distinct, {} solid. Largest bucket held {} k-mers",
+          counts.total,
+          counts.distinct,
+          counts.solid,
+          counts.largest_bucket);
+    let edges = if reverse_complement { 2 * counts.solid } else { counts.solid };
+    let mut collection = T::init(Some(edges), None, InputFileType::BFCounter);
+    let mut reader = BufReader::new(open_file(&solid_path));
+    let mut record = vec![0; record_size + 4];
+    while read_record(&mut reader, &mut record) {
+        let kmer = decompress_edge(&record[..record_size]);
+        let weight = decode_weight(&record[record_size..]);
+        // canonical palindromes are their own reverse complements
+        let add_complement = reverse_complement && reverse_complement_of(&kmer) != kmer;
+        collection.add_read_bfc(&kmer, T::Weight::from_f64(weight as f64), add_complement);
+    }
+    if let Err(why) = remove_file(&solid_path) {
+        warn!("Couldn't remove {}: {}", solid_path.display(), why);
+    }
+    info!("Collection built");
+    (collection, total)
+}
+
+// Statistics gathered during counting of the buckets.
+#[derive(Default)]
+struct PartitionCounts {
+    total: usize,
+    distinct: usize,
+    solid: usize,
+    largest_bucket: usize,
+}
+
+// On-disk buckets filled during the first pass.
+struct Buckets {
+    paths: Vec<PathBuf>,
+    writers: Vec<BufWriter<File>>,
+}
+
+impl Buckets {
+    fn new(dir: &Path, partitions: usize) -> Buckets {
+        let paths = (0..partitions)
+            .map(|_| dir.join(scratch_file_name("bucket")))
+            .collect::<Vec<PathBuf>>();
+        let writers = paths.iter().map(|p| BufWriter::new(create_file(p))).collect();
+        Buckets {
+            paths: paths,
+            writers: writers,
+        }
+    }
+
+    fn add_read(&mut self, read: &[u8], minimizer_size: usize, reverse_complement: bool) {
+        let k = unsafe { K_SIZE };
+        if read.len() < k {
+            return;
+        }
+        let minimizers = minimizers(read, k, minimizer_size, reverse_complement);
+        for (window, minimizer) in read.windows(k).zip(minimizers) {
+            let compressed = if reverse_complement {
+                let rc = reverse_complement_of(window);
+                compress_edge(cmp::min(window, &rc[..]))
+            }
+            else {
+                compress_edge(window)
+            };
+            let bucket = bucket_of(minimizer, self.writers.len());
+            if let Err(why) = self.writers[bucket].write_all(&compressed) {
+                panic!("Couldn't write to {}: {}", self.paths[bucket].display(), why);
+            }
+        }
+    }
+
+    fn finish(self) -> Vec<PathBuf> {
+        for (mut w, p) in self.writers.into_iter().zip(self.paths.iter()) {
+            if let Err(why) = w.flush() {
+                panic!("Couldn't write to {}: {}", p.display(), why);
+            }
+        }
+        self.paths
+    }
+}
+
+/// Load single bucket, count its k-mers and save the solid ones.
+fn count_bucket<W: Write>(path: &Path, record_size: usize, threshold: EdgeWeight, solid: &mut W,
+                          counts: &mut PartitionCounts) {
+    let mut data = vec![];
+    if let Err(why) = open_file(path).read_to_end(&mut data) {
+        panic!("Couldn't read {}: {}", path.display(), why);
+    }
+    let mut records = data.chunks(record_size).collect::<Vec<&[u8]>>();
+    counts.total += records.len();
+    counts.largest_bucket = cmp::max(counts.largest_bucket, records.len());
+    records.sort();
+    let mut i = 0;
+    while i < records.len() {
+        let mut j = i + 1;
+        while j < records.len() && records[j] == records[i] {
+            j += 1;
+        }
+        counts.distinct += 1;
+        let weight = cmp::min(j - i, EdgeWeight::max_value() as usize) as EdgeWeight;
+        if weight >= threshold {
+            counts.solid += 1;
+            let result = solid.write_all(records[i]).and_then(|_| solid.write_all(&encode_weight(weight)));
+            if let Err(why) = result {
+                panic!("Couldn't save solid k-mers: {}", why);
+            }
+        }
+        i = j;
+    }
+}
+
+/// Computes minimizer for every k-mer in the read. Minimizer is the smallest
+/// m-mer, encoded with two bits per symbol. If `canonical` is set m-mers and
+/// their reverse complements are considered, which makes minimizer of the
+/// k-mer and its reverse complement equal.
+fn minimizers(read: &[u8], k: usize, m: usize, canonical: bool) -> Vec<u64> {
+    let mask = if m == 32 { !0 } else { (1_u64 << (2 * m)) - 1 };
+    let mut mmers = Vec::with_capacity(read.len() - m + 1);
+    let mut forward = 0_u64;
+    let mut reverse = 0_u64;
+    for (i, &c) in read.iter().enumerate() {
+        let code = encode_fasta_symbol(c, 0) as u64;
+        forward = ((forward << 2) | code) & mask;
+        reverse = (reverse >> 2) | ((3 - code) << (2 * (m - 1)));
+        if i + 1 >= m {
+            mmers.push(if canonical { cmp::min(forward, reverse) } else { forward });
+        }
+    }
+    (0..read.len() - k + 1)
+        .map(|j| *unwrap!(mmers[j..j + k - m + 1].iter().min()))
+        .collect()
+}
+
+/// Chooses bucket for the minimizer. Minimizers are hashed first, because
+/// lexicographically small m-mers (e.g. poly-A) are far more common.
+#[inline]
+fn bucket_of(minimizer: u64, partitions: usize) -> usize {
+    (minimizer.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32) as usize % partitions
+}
+
+fn encode_weight(weight: EdgeWeight) -> [u8; 4] {
+    let w = weight as u32;
+    [w as u8, (w >> 8) as u8, (w >> 16) as u8, (w >> 24) as u8]
+}
+
+fn decode_weight(bytes: &[u8]) -> EdgeWeight {
+    (bytes[0] as u32 | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16 |
+     (bytes[3] as u32) << 24) as EdgeWeight
+}
+
+/// Reads a single record, returns false at the end of the file.
+fn read_record<R: Read>(reader: &mut R, record: &mut [u8]) -> bool {
+    match reader.read_exact(record) {
+        Ok(()) => true,
+        Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => false,
+        Err(why) => panic!("Couldn't read solid k-mers: {}", why),
+    }
+}
+
+fn create_file(path: &Path) -> File {
+    match File::create(path) {
+        Ok(f) => f,
+        Err(why) => panic!("Couldn't create {}: {}", path.display(), why),
+    }
+}
+
+fn open_file(path: &Path) -> File {
+    match File::open(path) {
+        Ok(f) => f,
+        Err(why) => panic!("Couldn't open {}: {}", path.display(), why),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::reverse_complement_of;
+
+    #[test]
+    fn finds_minimizers() {
+        // m-mers: AC(1) CG(6) GT(11) TA(12) AC(1)
+        let m = minimizers(b"ACGTAC", 3, 2, false);
+        assert_eq!(m, vec![1, 6, 11, 1]);
+    }
+
+    #[test]
+    fn finds_canonical_minimizers() {
+        let read = b"GGTTCAGA";
+        let rc = reverse_complement_of(read);
+        let mut forward = minimizers(read, 4, 3, true);
+        let reverse = minimizers(&rc, 4, 3, true);
+        forward.reverse();
+        assert_eq!(forward, reverse);
+    }
+
+    #[test]
+    fn encodes_weights() {
+        for &w in &[0, 1, 255, 256, 65_537, EdgeWeight::max_value()] {
+            assert_eq!(decode_weight(&encode_weight(w)), w);
+        }
+    }
+
+    #[test]
+    fn chooses_bucket_in_range() {
+        for m in 0..1000 {
+            assert!(bucket_of(m, 7) < 7);
+        }
+    }
+}
@@ -1,20 +1,16 @@
 //! Various algorithms for graph pruning - removing unnecessary vertices/edges.
 
+use algorithms::builder::Build;
 use asm::SEQUENCES;
 use collections::HmGIR;
 use collections::girs::edges::Edge;
-use collections::graphs::Graph;
-use collections::graphs::pt_graph::{EdgeIndex, Node, NodeIndex, PtGraph};
+use collections::graphs::{EdgeDirection, Graph, GraphMut, Id};
 use compress::{compress_node, encode_fasta_symbol};
-use prelude::{CDC, EdgeWeight, K1_SIZE, K_SIZE};
+use prelude::{CDC, K1_SIZE, K_SIZE, Weight};
 use slices::{BasicSlice, NodeSlice};
 
-use petgraph::EdgeDirection;
-
 use std::collections::hash_map::Entry;
-use std::iter;
 use std::mem;
-use std::slice;
 use std::vec::Drain;
 
 
@@ -22,27 +18,30 @@ use std::vec::Drain;
 pub trait Prunable: Clean {
     /// Remove all input and output dead paths
     fn remove_dead_paths(&mut self);
+    /// Remove edges with weight lower than `fraction` of the weight of the
+    /// strongest edge leaving (or entering) the same branching node, until
+    /// there are no such edges. Returns number of removed edges.
+    fn remove_erroneous_connections(&mut self, fraction: f64) -> usize;
 }
 
 /// A trait for keeping the graph clean.
 /// It keeps simple functions used for basic graph cleanups
-pub trait Clean {
+pub trait Clean: Build {
     /// Remove vertives without any edges.
     fn remove_single_vertices(&mut self);
     /// Remove edges with weight below threshold.
-    fn remove_weak_edges(&mut self, threshold: EdgeWeight);
+    fn remove_weak_edges(&mut self, threshold: Self::Weight);
 }
 
-impl Prunable for PtGraph {
+impl<G: GraphMut> Prunable for G {
     fn remove_dead_paths(&mut self) {
         info!("Starting graph pruning");
-        let mut to_remove: Vec<EdgeIndex> = vec![];
+        let mut to_remove: Vec<G::EdgeIdentifier> = vec![];
         loop {
-            trace!("Detected {} input/output vertices",
-                   Externals::new(self.raw_nodes().iter().enumerate()).count());
+            trace!("Detected {} input/output vertices", Externals::new(self).count());
             // analyze found input/output vertices
             let mut path_check_vec = vec![];
-            for v in Externals::new(self.raw_nodes().iter().enumerate()) {
+            for v in Externals::new(self) {
                 // sort into output and input paths
                 match v {
                     VertexType::Input(v_) => {
@@ -74,25 +73,69 @@ impl Prunable for PtGraph {
                 return;
             }
             // reverse sort edge indices such that removal won't cause any troubles with swapped
-            // edge indices (see `petgraph`'s explanation of `remove_edge`)
+            // edge indices (see `GraphMut`'s explanation of removal)
             to_remove.sort_by(|a, b| b.cmp(a));
             remove_paths(self, to_remove.drain(..));
         }
     }
+
+    fn remove_erroneous_connections(&mut self, fraction: f64) -> usize {
+        info!("Starting removal of erroneous connections");
+        let mut removed = 0;
+        loop {
+            let mut to_remove: Vec<G::EdgeIdentifier> = vec![];
+            let mut edges = vec![];
+            for i in 0..self.node_count() {
+                let node = G::NodeIdentifier::new(i);
+                for &direction in &[EdgeDirection::Outgoing, EdgeDirection::Incoming] {
+                    if self.degree(node, direction) > 1 {
+                        check_weak_connections(self,
+                                               node,
+                                               direction,
+                                               fraction,
+                                               &mut edges,
+                                               &mut to_remove);
+                    }
+                }
+            }
+            if to_remove.is_empty() {
+                info!("Removed {} erroneous connections", removed);
+                return removed;
+            }
+            // edge may be weak at both of its ends
+            to_remove.sort();
+            to_remove.dedup();
+            removed += to_remove.len();
+            self.remove_edges(to_remove);
+            self.remove_single_vertices();
+        }
+    }
 }
 
-impl Clean for PtGraph {
+impl<G: GraphMut> Clean for G {
     fn remove_single_vertices(&mut self) {
-        self.retain_nodes(|g, n| g.neighbors_undirected(n).next().is_some());
+        // iterate in the reverse order, so that the node moved in place of the
+        // removed one has already been checked
+        for i in (0..self.node_count()).rev() {
+            let n = G::NodeIdentifier::new(i);
+            if self.in_degree(n) == 0 && self.out_degree(n) == 0 {
+                self.remove_node(n);
+            }
+        }
     }
 
-    fn remove_weak_edges(&mut self, threshold: EdgeWeight) {
-        self.retain_edges(|g, e| unwrap!(g.edge_weight(e)).1 >= threshold);
+    fn remove_weak_edges(&mut self, threshold: G::Weight) {
+        for i in (0..self.edge_count()).rev() {
+            let e = G::EdgeIdentifier::new(i);
+            if unwrap!(self.edge_weight(e)).1 < threshold {
+                self.remove_edge(e);
+            }
+        }
         self.remove_single_vertices();
     }
 }
 
-impl Clean for HmGIR {
+impl<W: Weight> Clean for HmGIR<W> {
     fn remove_single_vertices(&mut self) {
         let mut keys_to_remove: Vec<NodeSlice> = self.iter()
             .filter(|&(_, val)| val.is_empty())
@@ -106,12 +149,12 @@ impl Clean for HmGIR {
         }
     }
 
-    fn remove_weak_edges(&mut self, threshold: EdgeWeight) {
+    fn remove_weak_edges(&mut self, threshold: Self::Weight) {
         for edges in self.values_mut() {
             *edges = edges.iter()
                 .cloned()
                 .filter(|&x| x.1 >= threshold)
-                .collect::<Vec<Edge>>()
+                .collect::<Vec<Edge<W>>>()
                 .into_boxed_slice();
         }
         self.remove_single_vertices();
@@ -124,7 +167,7 @@ impl Clean for HmGIR {
 /// common bytes for each sequence.
 /// WARNING: this may or may not be optimal if we follow the fasta standard
 /// but should be sufficiently faster for just 5 characters we use at the moment
-fn has_incoming_edges(gir: &mut HmGIR, node: &NodeSlice) -> bool {
+fn has_incoming_edges<W: Weight>(gir: &mut HmGIR<W>, node: &NodeSlice) -> bool {
     let mut output = false;
     // copy current sequence to register
     let mut vec = node.byte_name();
@@ -133,7 +176,7 @@ fn has_incoming_edges(gir: &mut HmGIR, node: &NodeSlice) -> bool {
     vec.insert(0, b'A');
     let mut v = Vec::new();
     compress_node(&vec, &mut v);
-    SEQUENCES.write()[0] = v.into_boxed_slice();
+    SEQUENCES.write().replace(0, &v);
     let shift_num = (mem::size_of::<CDC>() - 1) * 8 + 6;
     let mask = !((3) << shift_num);
     // try to bruteforce by inserting all possible characters: ACTGN
@@ -162,41 +205,40 @@ enum VertexType<T> {
 }
 
 /// Iterator yielding vertices which either have no incoming or outgoing edges.
-struct Externals<'a> {
-    iter: iter::Enumerate<slice::Iter<'a, Node>>,
+struct Externals<'a, G: 'a + Graph> {
+    graph: &'a G,
+    index: usize,
 }
 
-impl<'a> Externals<'a> {
-    fn new(iter_: iter::Enumerate<slice::Iter<'a, Node>>) -> Externals {
-        Externals { iter: iter_ }
+impl<'a, G: Graph> Externals<'a, G> {
+    fn new(graph: &'a G) -> Externals<'a, G> {
+        Externals {
+            graph: graph,
+            index: 0,
+        }
     }
 }
 
-impl<'a> Iterator for Externals<'a> {
-    type Item = VertexType<NodeIndex>;
-    fn next(&mut self) -> Option<VertexType<NodeIndex>> {
-        loop {
-            match self.iter.next() {
-                None => return None,
-                Some((index, node)) => {
-                    if node.next_edge(EdgeDirection::Incoming) == EdgeIndex::end() {
-                        return Some(VertexType::Input(NodeIndex::new(index)));
-                    }
-                    else if node.next_edge(EdgeDirection::Outgoing) == EdgeIndex::end() {
-                        return Some(VertexType::Output(NodeIndex::new(index)));
-                    }
-                    else {
-                        continue;
-                    }
-                }
+impl<'a, G: Graph> Iterator for Externals<'a, G> {
+    type Item = VertexType<G::NodeIdentifier>;
+    fn next(&mut self) -> Option<VertexType<G::NodeIdentifier>> {
+        while self.index < self.graph.node_count() {
+            let node = G::NodeIdentifier::new(self.index);
+            self.index += 1;
+            if self.graph.in_degree(node) == 0 {
+                return Some(VertexType::Input(node));
+            }
+            else if self.graph.out_degree(node) == 0 {
+                return Some(VertexType::Output(node));
             }
         }
+        None
     }
 }
 
 /// Remove dead input path.
 #[inline]
-fn remove_paths(graph: &mut PtGraph, to_remove: Drain<EdgeIndex>) {
+fn remove_paths<G: GraphMut>(graph: &mut G, to_remove: Drain<G::EdgeIdentifier>) {
     trace!("Removing {} dead paths", to_remove.len());
     for e in to_remove {
         let edgepoints = graph.edge_endpoints(e);
@@ -218,16 +260,30 @@ fn remove_paths(graph: &mut PtGraph, to_remove: Drain<EdgeIndex>) {
 
 /// Remove node if it's single.
 #[inline]
-fn remove_single_node(graph: &mut PtGraph, node: NodeIndex) {
+fn remove_single_node<G: GraphMut>(graph: &mut G, node: G::NodeIdentifier) {
     if graph.in_degree(node) == 0 && graph.out_degree(node) == 0 {
         graph.remove_node(node);
     }
 }
 
+/// Collect edges of the branching vertex in the given direction, which are
+/// weaker than `fraction` of the strongest one.
+#[inline]
+fn check_weak_connections<G: GraphMut>(graph: &G, vertex: G::NodeIdentifier,
+                                    direction: EdgeDirection, fraction: f64,
+                                    edges: &mut Vec<G::EdgeIdentifier>,
+                                    output_vec: &mut Vec<G::EdgeIdentifier>) {
+    graph.fill_edges(vertex, direction, edges);
+    let weight = |e: G::EdgeIdentifier| unwrap!(graph.edge_weight(e)).1.to_f64();
+    let strongest = edges.iter().map(|&e| weight(e)).fold(0.0, f64::max);
+    output_vec.extend(edges.iter().cloned().filter(|&e| weight(e) < fraction * strongest));
+}
+
 /// Check if vertex initializes a dead path.
 #[inline]
-fn check_dead_path(graph: &PtGraph, vertex: NodeIndex, first_direction: EdgeDirection,
-                   second_direction: EdgeDirection, output_vec: &mut Vec<EdgeIndex>) {
+fn check_dead_path<G: GraphMut>(graph: &G, vertex: G::NodeIdentifier, first_direction: EdgeDirection,
+                            second_direction: EdgeDirection,
+                            output_vec: &mut Vec<G::EdgeIdentifier>) {
     let mut current_vertex = vertex;
     let mut cnt = 0;
     loop {
@@ -250,7 +306,7 @@ fn check_dead_path(graph: &PtGraph, vertex: NodeIndex, first_direction: EdgeDire
             return;
         }
         // if out_degree(current_vertex) > 1
-        if graph.neighbors_directed(current_vertex, first_direction).nth(2).is_some() {
+        if graph.degree(current_vertex, first_direction) > 2 {
             return;
         }
     }
@@ -333,6 +389,52 @@ mod tests {
         }
     }
 
+    mod remove_erroneous_connections {
+        use super::*;
+        #[test]
+        fn removes_weak_branch() {
+            setup!(graph, x, y, z);
+            graph.add_edge(x, y, (EdgeSlice::default(), 100));
+            graph.add_edge(x, z, (EdgeSlice::default(), 5));
+            assert_eq!(graph.remove_erroneous_connections(0.1), 1);
+            assert_eq!(graph.node_count(), 2);
+            assert_eq!(graph.edge_count(), 1);
+            assert_eq!(graph.edge_weight(EdgeIndex::new(0)).unwrap().1, 100);
+        }
+
+        #[test]
+        fn keeps_low_coverage_branches() {
+            setup!(graph, x, y, z);
+            graph.add_edge(x, y, (EdgeSlice::default(), 3));
+            graph.add_edge(x, z, (EdgeSlice::default(), 2));
+            assert_eq!(graph.remove_erroneous_connections(0.5), 0);
+            assert_eq!(graph.node_count(), 3);
+            assert_eq!(graph.edge_count(), 2);
+        }
+
+        #[test]
+        fn removes_weak_incoming_edge() {
+            setup!(graph, x, y, z);
+            graph.add_edge(x, z, (EdgeSlice::default(), 100));
+            graph.add_edge(y, z, (EdgeSlice::default(), 5));
+            assert_eq!(graph.remove_erroneous_connections(0.1), 1);
+            assert_eq!(graph.node_count(), 2);
+            assert_eq!(graph.edge_count(), 1);
+        }
+
+        #[test]
+        fn removes_edge_weak_at_both_ends_once() {
+            setup!(graph, x, y, z);
+            let w = graph.add_node(());
+            graph.add_edge(x, y, (EdgeSlice::default(), 100));
+            graph.add_edge(x, z, (EdgeSlice::default(), 5));
+            graph.add_edge(w, z, (EdgeSlice::default(), 100));
+            assert_eq!(graph.remove_erroneous_connections(0.1), 1);
+            assert_eq!(graph.node_count(), 4);
+            assert_eq!(graph.edge_count(), 2);
+        }
+    }
+
     mod remove_single_vertices {
         use super::*;
         #[test]
@@ -0,0 +1,377 @@
+//! Paired-end scaffolding.
+//!
+//! Read pairs are mapped onto contigs by k-mers which occur in contigs only
+//! once. Mates are assumed to face each other (forward-reverse library), so
+//! pairs with both mates on the same contig give the insert size, while pairs
+//! with mates on different contigs link the contig ends faced by the mates.
+//! Each link estimates the gap between contigs as the insert size minus the
+//! distances of mates to the linked ends.
+//!
+//! Scaffolds are built greedily from the links supported by the most pairs.
+//! Each contig end is joined at most once and joins which would close a cycle
+//! are skipped, so scaffolds are paths of ordered and oriented contigs. Gaps
+//! are filled with runs of `N`s, at least `MIN_GAP_LENGTH` long.
+
+use algorithms::builder::for_each_read_pair;
+use algorithms::collapser::SerializedContigs;
+use algorithms::components::find_root;
+use config::InputFileType;
+use prelude::K_SIZE;
+use utils::reverse_complement_of;
+
+use std::cmp;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Length of the gap between contigs which seem to overlap, or which gap was
+/// estimated even shorter.
+pub const MIN_GAP_LENGTH: usize = 10;
+
+/// Part of the scaffold.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScaffoldPart {
+    /// Contig with the given index, reverse complemented if not forward.
+    Contig(usize, bool),
+    /// Run of `N`s of the given length.
+    Gap(usize),
+    /// Sequence which closed the gap.
+    Fill(String),
+}
+
+/// Ordered and oriented contigs separated by gaps.
+pub type Scaffold = Vec<ScaffoldPart>;
+
+/// Output from the scaffolder.
+pub struct Scaffolds {
+    /// Estimated insert size of the library, none if no pair was mapped onto
+    /// a single contig.
+    pub insert_size: Option<f64>,
+    /// Scaffolds, each contig occurs in exactly one of them.
+    pub scaffolds: Vec<Scaffold>,
+}
+
+// Position of the read on the contig.
+#[derive(Clone, Copy)]
+struct Mapping {
+    contig: usize,
+    // leftmost position of the read on the forward strand of the contig
+    start: isize,
+    forward: bool,
+    length: usize,
+}
+
+impl Mapping {
+    // Gets end of the contig faced by the read - its tail if the read is
+    // forward.
+    fn faced_end(&self) -> End {
+        (self.contig, self.forward)
+    }
+
+    // Gets distance from the 5' end of the read to the faced end of the contig.
+    fn distance_to_end(&self, contigs: &SerializedContigs) -> isize {
+        if self.forward {
+            contigs[self.contig].len() as isize - self.start
+        }
+        else {
+            self.start + self.length as isize
+        }
+    }
+}
+
+// End of the contig - its tail if the flag is set, its head otherwise.
+type End = (usize, bool);
+
+// Pairs supporting the link between two contig ends.
+#[derive(Default)]
+struct Link {
+    pairs: usize,
+    // sum of gaps estimated by pairs
+    gaps: isize,
+}
+
+/// Scaffolds contigs with read pairs from the two FASTA/FASTQ files, which
+/// hold the first and the second mates in the same order. Contig ends are
+/// joined if they are linked by at least `min_links` pairs.
+pub fn scaffold(contigs: &SerializedContigs, first: &Path, second: &Path, ft: InputFileType,
+                min_links: usize)
+                -> Scaffolds {
+    let index = index_contigs(contigs);
+    let mut inserts = vec![];
+    let mut spans = vec![];
+    for_each_read_pair(first, second, ft, |a, b| {
+        if let (Some(x), Some(y)) = (map_read(&index, a), map_read(&index, b)) {
+            if x.contig == y.contig {
+                inserts.extend(insert_of(x, y));
+            }
+            else {
+                spans.push((x, y));
+            }
+        }
+    });
+    info!("{} pairs mapped onto single contigs, {} pairs link contigs",
+          inserts.len(),
+          spans.len());
+    let insert_size = median(&mut inserts);
+    let insert = match insert_size {
+        Some(i) => i,
+        None => {
+            warn!("Insert size can't be estimated, contigs are left unscaffolded");
+            return Scaffolds {
+                insert_size: None,
+                scaffolds: (0..contigs.len())
+                    .map(|i| vec![ScaffoldPart::Contig(i, true)])
+                    .collect(),
+            };
+        }
+    };
+    info!("Estimated insert size: {}", insert);
+    let mut links: HashMap<(End, End), Link> = HashMap::new();
+    for (x, y) in spans {
+        let distances = x.distance_to_end(contigs) + y.distance_to_end(contigs);
+        // mates too far from the ends to come from a single fragment
+        if distances as f64 > 2.0 * insert {
+            continue;
+        }
+        let (a, b) = (x.faced_end(), y.faced_end());
+        let link = links.entry((cmp::min(a, b), cmp::max(a, b))).or_insert_with(Link::default);
+        link.pairs += 1;
+        link.gaps += insert.round() as isize - distances;
+    }
+    Scaffolds {
+        insert_size: insert_size,
+        scaffolds: join_contigs(contigs.len(), links, min_links),
+    }
+}
+
+// Maps k-mers occurring in contigs once to their contig and position. K-mers
+// occurring several times are mapped to none.
+fn index_contigs(contigs: &SerializedContigs) -> HashMap<Vec<u8>, Option<(usize, usize)>> {
+    let k = unsafe { K_SIZE };
+    let mut index = HashMap::new();
+    for (i, contig) in contigs.iter().enumerate() {
+        if contig.len() < k {
+            continue;
+        }
+        for (p, kmer) in contig.as_bytes().windows(k).enumerate() {
+            match index.entry(kmer.to_vec()) {
+                Entry::Occupied(mut e) => {
+                    e.insert(None);
+                }
+                Entry::Vacant(e) => {
+                    e.insert(Some((i, p)));
+                }
+            }
+        }
+    }
+    index
+}
+
+// Maps the read by its first unique k-mer, trying its reverse complement if
+// it doesn't map as it is.
+fn map_read(index: &HashMap<Vec<u8>, Option<(usize, usize)>>, read: &[u8]) -> Option<Mapping> {
+    let locate = |read: &[u8], forward: bool| {
+        read.windows(unsafe { K_SIZE })
+            .enumerate()
+            .filter_map(|(offset, kmer)| {
+                match index.get(kmer) {
+                    Some(&Some((contig, position))) => {
+                        Some(Mapping {
+                            contig: contig,
+                            start: position as isize - offset as isize,
+                            forward: forward,
+                            length: read.len(),
+                        })
+                    }
+                    _ => None,
+                }
+            })
+            .next()
+    };
+    locate(read, true).or_else(|| locate(&reverse_complement_of(read)[..], false))
+}
+
+// Gets insert size of the pair mapped onto the same contig, if mates face
+// each other.
+fn insert_of(x: Mapping, y: Mapping) -> Option<f64> {
+    let (forward, reverse) = match (x.forward, y.forward) {
+        (true, false) => (x, y),
+        (false, true) => (y, x),
+        _ => return None,
+    };
+    let insert = reverse.start + reverse.length as isize - forward.start;
+    if insert > 0 { Some(insert as f64) } else { None }
+}
+
+fn median(values: &mut [f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| unwrap!(a.partial_cmp(b)));
+    Some(values[values.len() / 2])
+}
+
+// Joins contig ends along the best supported links and walks the resulting
+// paths from their free ends.
+fn join_contigs(count: usize, links: HashMap<(End, End), Link>, min_links: usize)
+                -> Vec<Scaffold> {
+    let mut links = links.into_iter()
+        .filter(|&((a, b), ref l)| l.pairs >= min_links && a.0 != b.0)
+        .collect::<Vec<_>>();
+    // the most supported first, ties broken by ends to keep the order stable
+    links.sort_by(|x, y| {
+        match y.1.pairs.cmp(&x.1.pairs) {
+            cmp::Ordering::Equal => x.0.cmp(&y.0),
+            o => o,
+        }
+    });
+    let mut parents = (0..count).collect::<Vec<usize>>();
+    let mut partners: HashMap<End, (End, usize)> = HashMap::new();
+    for ((a, b), link) in links {
+        if partners.contains_key(&a) || partners.contains_key(&b) {
+            continue;
+        }
+        let (root_a, root_b) = (find_root(&mut parents, a.0), find_root(&mut parents, b.0));
+        if root_a == root_b {
+            continue;
+        }
+        parents[root_a] = root_b;
+        let gap = link.gaps / link.pairs as isize;
+        let length = cmp::max(gap, MIN_GAP_LENGTH as isize) as usize;
+        partners.insert(a, (b, length));
+        partners.insert(b, (a, length));
+    }
+    info!("Joined {} pairs of contig ends", partners.len() / 2);
+    let mut scaffolds = vec![];
+    let mut visited = vec![false; count];
+    for contig in 0..count {
+        let head_free = !partners.contains_key(&(contig, false));
+        // walks start only at free ends of paths, inner contigs are reached
+        // from them
+        if visited[contig] || (!head_free && partners.contains_key(&(contig, true))) {
+            continue;
+        }
+        let mut scaffold = vec![];
+        // enter by the free end, forward if it's the head
+        let (mut current, mut forward) = (contig, head_free);
+        loop {
+            visited[current] = true;
+            scaffold.push(ScaffoldPart::Contig(current, forward));
+            // leave by the opposite end
+            match partners.get(&(current, forward)) {
+                Some(&((next, tail), length)) => {
+                    scaffold.push(ScaffoldPart::Gap(length));
+                    current = next;
+                    forward = !tail;
+                }
+                None => break,
+            }
+        }
+        scaffolds.push(scaffold);
+    }
+    scaffolds
+}
+
+impl Scaffolds {
+    /// Gets sequences of scaffolds made of the given contigs.
+    pub fn sequences(&self, contigs: &SerializedContigs) -> Vec<String> {
+        self.scaffolds
+            .iter()
+            .map(|scaffold| {
+                let mut sequence = String::new();
+                for part in scaffold {
+                    match *part {
+                        ScaffoldPart::Contig(i, true) => sequence.push_str(&contigs[i]),
+                        ScaffoldPart::Contig(i, false) => {
+                            let reverse = reverse_complement_of(contigs[i].as_bytes());
+                            sequence.push_str(&unwrap!(String::from_utf8(reverse)));
+                        }
+                        ScaffoldPart::Gap(length) => {
+                            sequence.extend((0..length).map(|_| 'N'));
+                        }
+                        ScaffoldPart::Fill(ref fill) => sequence.push_str(fill),
+                    }
+                }
+                sequence
+            })
+            .collect()
+    }
+
+    /// Saves sequences of scaffolds to the FASTA file and their structure to
+    /// the AGP file. Contigs are referred to by their names in the contigs
+    /// output, sequences which closed gaps by their scaffold and position.
+    pub fn save_to_files<P: AsRef<Path>, Q: AsRef<Path>>(&self, contigs: &SerializedContigs,
+                                                         fasta: P, agp: Q) {
+        let mut writer = BufWriter::new(create_file(fasta));
+        for (i, s) in self.sequences(contigs).iter().enumerate() {
+            writeln!(&mut writer, ">katome_scaffold_{}", i).unwrap();
+            writeln!(&mut writer, "{}", s).unwrap();
+        }
+        let mut writer = BufWriter::new(create_file(agp));
+        writeln!(&mut writer, "##agp-version\t2.0").unwrap();
+        for (i, scaffold) in self.scaffolds.iter().enumerate() {
+            let mut position = 0;
+            let mut part_number = 0;
+            for (j, part) in scaffold.iter().enumerate() {
+                let object = format!("katome_scaffold_{}\t", i);
+                // contigs may abut, leaving nothing to describe
+                if let ScaffoldPart::Fill(ref fill) = *part {
+                    if fill.is_empty() {
+                        continue;
+                    }
+                }
+                part_number += 1;
+                match *part {
+                    ScaffoldPart::Contig(c, forward) => {
+                        let length = contigs[c].len();
+                        writeln!(&mut writer,
+                                 "{}{}\t{}\t{}\tW\tkatome_{}\t1\t{}\t{}",
+                                 object,
+                                 position + 1,
+                                 position + length,
+                                 part_number,
+                                 c,
+                                 length,
+                                 if forward { '+' } else { '-' })
+                            .unwrap();
+                        position += length;
+                    }
+                    ScaffoldPart::Gap(length) => {
+                        writeln!(&mut writer,
+                                 "{}{}\t{}\t{}\tN\t{}\tscaffold\tyes\tpaired-ends",
+                                 object,
+                                 position + 1,
+                                 position + length,
+                                 part_number,
+                                 length)
+                            .unwrap();
+                        position += length;
+                    }
+                    ScaffoldPart::Fill(ref fill) => {
+                        writeln!(&mut writer,
+                                 "{}{}\t{}\t{}\tO\tkatome_scaffold_{}_fill_{}\t1\t{}\t+",
+                                 object,
+                                 position + 1,
+                                 position + fill.len(),
+                                 part_number,
+                                 i,
+                                 j,
+                                 fill.len())
+                            .unwrap();
+                        position += fill.len();
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn create_file<P: AsRef<Path>>(path: P) -> File {
+    match File::create(&path) {
+        Err(why) => panic!("couldn't create {}: {}", path.as_ref().display(), why.description()),
+        Ok(file) => file,
+    }
+}
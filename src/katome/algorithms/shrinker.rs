@@ -1,12 +1,11 @@
 //! Shrink the given graph
 
 use algorithms::pruner::Clean;
-use collections::Graph;
-use collections::graphs::pt_graph::{PtGraph, NodeIndex, EdgeIndex};
+use collections::GraphMut;
+use collections::graphs::Id;
+use collections::graphs::EdgeDirection::{Incoming, Outgoing};
 
 use fixedbitset::FixedBitSet;
-use petgraph::Direction::{Incoming, Outgoing};
-use petgraph::visit::EdgeRef;
 
 /// Mark graph as shrinkable.
 pub trait Shrinkable {
@@ -35,31 +34,34 @@ pub trait Shrinkable {
     fn shrink_points(&mut self, possible_inc_points: &[Self::NodeIdx]);
 }
 
-struct ShrinkTraverse {
+struct ShrinkTraverse<G: GraphMut> {
     // bitset which holds information about visited nodes
     fb: FixedBitSet,
     // stack of nodes to visit
-    stack: Vec<NodeIndex>,
+    stack: Vec<G::NodeIdentifier>,
     // offset on the bitset, used during cycle-on-top shrink
     node_offset: usize,
+    // buffer for outgoing edges of the current node
+    edges: Vec<G::EdgeIdentifier>,
 }
 
-impl ShrinkTraverse {
-    pub fn new(graph: &PtGraph) -> ShrinkTraverse {
-        let mut v = Vec::new();
-        for n in graph.externals(Incoming) {
-            v.push(n);
-        }
+impl<G: GraphMut> ShrinkTraverse<G> {
+    pub fn new(graph: &G) -> ShrinkTraverse<G> {
         let len = graph.node_count();
+        let v = (0..len)
+            .map(G::NodeIdentifier::new)
+            .filter(|&n| graph.in_degree(n) == 0)
+            .collect();
         ShrinkTraverse {
             fb: FixedBitSet::with_capacity(len),
             stack: v,
             node_offset: 0,
+            edges: vec![],
         }
     }
 
     #[inline]
-    pub fn next(&mut self, graph: &PtGraph) -> Option<EdgeIndex> {
+    pub fn next(&mut self, graph: &G) -> Option<G::EdgeIdentifier> {
         let mut iter = false;
         let mut single_nodes = vec![];
         loop {
@@ -68,8 +70,10 @@ impl ShrinkTraverse {
                 let mut new_ancestor;
                 loop {
                     new_ancestor = false;
-                    for e in graph.edges_directed(current_node, Outgoing) {
-                        let n = e.target();
+                    graph.fill_edges(current_node, Outgoing, &mut self.edges);
+                    for i in 0..self.edges.len() {
+                        let e = self.edges[i];
+                        let n = unwrap!(graph.edge_endpoints(e)).1;
                         if self.is_visited(n) {
                             continue;
                         }
@@ -80,7 +84,7 @@ impl ShrinkTraverse {
                         else if graph.out_degree(n) == 1 && graph.in_degree(n) == 1 {
                             // current_node -> n -> x
                             // that's the only thing we need to start shrinking
-                            return Some(e.id());
+                            return Some(e);
                         }
                         else {
                             self.stack.push(n);
@@ -106,7 +110,7 @@ impl ShrinkTraverse {
 
             // find the next unvisited node in the graph and put it on the stack
             for (i, n) in self.fb.zeros().skip(self.node_offset).enumerate() {
-                let node = NodeIndex::new(n);
+                let node = G::NodeIdentifier::new(n);
                 // This if statement is technically not necessary, as algorithm
                 // works exactly the same without it, but it massively reduces
                 // the runtime. It filters all of the single nodes out --
@@ -115,7 +119,7 @@ impl ShrinkTraverse {
                     single_nodes.push(node);
                 }
                 else {
-                    self.stack.push(NodeIndex::new(n));
+                    self.stack.push(node);
                     // remember the current offset so we dont have to iterate
                     // over it again -- all nodes up to this point have been
                     // visited.
@@ -136,19 +140,19 @@ impl ShrinkTraverse {
     }
 
     #[inline]
-    fn is_visited(&self, node: NodeIndex) -> bool {
+    fn is_visited(&self, node: G::NodeIdentifier) -> bool {
         self.fb.contains(node.index())
     }
 
     #[inline]
-    fn mark_visited(&mut self, node: NodeIndex) {
+    fn mark_visited(&mut self, node: G::NodeIdentifier) {
         self.fb.insert(node.index());
     }
 }
 
-impl Shrinkable for PtGraph {
-    type EdgeIdx = EdgeIndex;
-    type NodeIdx = NodeIndex;
+impl<G: GraphMut> Shrinkable for G {
+    type EdgeIdx = G::EdgeIdentifier;
+    type NodeIdx = G::NodeIdentifier;
     #[inline]
     fn shrink_points(&mut self, possible_inc_points: &[Self::NodeIdx]) {
         for &n in possible_inc_points {
@@ -158,7 +162,7 @@ impl Shrinkable for PtGraph {
     #[inline]
     fn shrink_point(&mut self, n: Self::NodeIdx) {
         if self.out_degree(n) == 1 && self.in_degree(n) == 1 {
-            let edge_to_shrink = unwrap!(self.edges_directed(n, Incoming).next()).id();
+            let edge_to_shrink = unwrap!(self.first_edge(n, Incoming));
             self.shrink_single_path(edge_to_shrink);
         }
     }
@@ -166,7 +170,7 @@ impl Shrinkable for PtGraph {
         info!("Start shrinking the graph with {} nodes and {} edges",
               self.node_count(),
               self.edge_count());
-        let mut t = ShrinkTraverse::new(self);
+        let mut t = ShrinkTraverse::<G>::new(self);
         while let Some(base_edge) = t.next(self) {
             self.shrink_single_path(base_edge);
         }
@@ -177,7 +181,7 @@ impl Shrinkable for PtGraph {
     }
 
     #[inline]
-    fn shrink_single_path(&mut self, mut base_edge: EdgeIndex) -> EdgeIndex {
+    fn shrink_single_path(&mut self, mut base_edge: G::EdgeIdentifier) -> G::EdgeIdentifier {
         let (start_node, mut mid_node) = unwrap!(self.edge_endpoints(base_edge));
         loop {
             let next_edge = next_out_edge(self, mid_node);
@@ -211,12 +215,12 @@ impl Shrinkable for PtGraph {
 
 
 #[inline]
-fn edge_target(graph: &PtGraph, edge: EdgeIndex) -> NodeIndex {
+fn edge_target<G: GraphMut>(graph: &G, edge: G::EdgeIdentifier) -> G::NodeIdentifier {
     unwrap!(graph.edge_endpoints(edge)).1
 }
 
 #[inline]
-fn next_out_edge(graph: &PtGraph, node: NodeIndex) -> EdgeIndex {
+fn next_out_edge<G: GraphMut>(graph: &G, node: G::NodeIdentifier) -> G::EdgeIdentifier {
     unwrap!(graph.first_edge(node, Outgoing))
 }
 
@@ -255,13 +259,13 @@ mod tests {
     // lock here
             let _l = LOCK.lock().unwrap();
             SEQUENCES.write().clear();
-            SEQUENCES.write().push(vec![].into_boxed_slice());
-            SEQUENCES.write().push(c1.into_boxed_slice());
-            SEQUENCES.write().push(c2.into_boxed_slice());
-            SEQUENCES.write().push(c3.into_boxed_slice());
-            SEQUENCES.write().push(c4.into_boxed_slice());
-            SEQUENCES.write().push(c5.into_boxed_slice());
-            SEQUENCES.write().push(c6.into_boxed_slice());
+            SEQUENCES.write().push(&[]);
+            SEQUENCES.write().push(&c1);
+            SEQUENCES.write().push(&c2);
+            SEQUENCES.write().push(&c3);
+            SEQUENCES.write().push(&c4);
+            SEQUENCES.write().push(&c5);
+            SEQUENCES.write().push(&c6);
         )
     );
 
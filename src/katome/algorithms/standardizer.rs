@@ -17,63 +17,65 @@
 //! be removed from the graph.
 
 
+use algorithms::builder::Build;
 use algorithms::pruner::Clean;
-use collections::Graph;
-use collections::graphs::pt_graph::{EdgeIndex, NodeIndex, PtGraph};
-use prelude::EdgeWeight;
+use collections::{Graph, GraphMut};
+use collections::graphs::{EdgeDirection, Id};
+use prelude::Weight;
 
-use petgraph::EdgeDirection;
+use std::collections::HashSet;
 
 /// Contig representation.
-type Contig = Vec<EdgeIndex>;
+type Contig<G> = Vec<<G as Graph>::EdgeIdentifier>;
 /// Vector of `Contig`s.
-type GraphContigs = Vec<Contig>;
+type GraphContigs<G> = Vec<Contig<G>>;
 
 /// Trait describing standardization of the `Graph`.
-pub trait Standardizable {
+pub trait Standardizable: Build {
     /// Standardize edges of the `Graph`.
     fn standardize_edges(&mut self, original_genome_length: usize, k_size: usize,
-                         threshold: EdgeWeight);
+                         threshold: Self::Weight);
     /// Standardize each contig.
     fn standardize_contigs(&mut self);
 }
 
-impl Standardizable for PtGraph {
+impl<G: GraphMut> Standardizable for G {
     fn standardize_edges(&mut self, original_genome_length: usize, k_size: usize,
-                         threshold: EdgeWeight) {
+                         threshold: G::Weight) {
         // calculate sum of all weights of edges (s) and sum of weights lower than threshold (l)
-        let (s, l) = self.raw_edges()
-            .iter()
-            .fold((0_usize, 0_usize), |acc, e| {
-                if e.weight.1 < threshold {
-                    (acc.0 + e.weight.1 as usize, acc.1 + e.weight.1 as usize)
+        let (s, l) = (0..self.edge_count())
+            .map(|i| unwrap!(self.edge_weight(G::EdgeIdentifier::new(i))).1)
+            .fold((0.0, 0.0), |acc, w| {
+                if w < threshold {
+                    (acc.0 + w.to_f64(), acc.1 + w.to_f64())
                 }
                 else {
-                    (acc.0 + e.weight.1 as usize, acc.1)
+                    (acc.0 + w.to_f64(), acc.1)
                 }
             });
-        let p: f64 =
-            calculate_standardization_ratio(original_genome_length, k_size, s as usize, l as usize);
+        let p: f64 = calculate_standardization_ratio(original_genome_length, k_size, s, l);
         info!("Ratio: {} for g: {} k: {} s: {} l: {}", p, original_genome_length, k_size, s, l);
         // normalize edges across the graph
-        for weight in self.edge_weights_mut() {
-            let new_weight = (weight.1 as f64 * p).round() as EdgeWeight;
-            weight.1 = if new_weight == 0 && weight.1 >= threshold {
-                1
+        for i in 0..self.edge_count() {
+            let weight = unwrap!(self.edge_weight_mut(G::EdgeIdentifier::new(i)));
+            let new_weight = G::Weight::from_f64(weight.1.to_f64() * p);
+            weight.1 = if new_weight == G::Weight::zero() && weight.1 >= threshold {
+                G::Weight::one()
             }
             else {
                 new_weight
             };
         }
         // remove edges with weight 0
-        self.remove_weak_edges(1);
+        self.remove_weak_edges(G::Weight::one());
     }
 
     fn standardize_contigs(&mut self) {
         let ambiguous_nodes = self.get_ambiguous_nodes();
         info!("Found {} ambiguous nodes", ambiguous_nodes.len());
+        let mut edges = vec![];
         for node in &ambiguous_nodes {
-            let contigs = get_contigs_from_node(self, *node, &ambiguous_nodes);
+            let contigs = get_contigs_from_node(self, *node, &ambiguous_nodes, &mut edges);
             for contig in contigs {
                 standardize_contig(self, contig);
             }
@@ -82,14 +84,16 @@ impl Standardizable for PtGraph {
 }
 
 #[inline]
-fn get_contigs_from_node(graph: &PtGraph, starting_node: NodeIndex,
-                         ambiguous_nodes: &<PtGraph as Graph>::AmbiguousNodes)
-                         -> GraphContigs {
+fn get_contigs_from_node<G: GraphMut>(graph: &G, starting_node: G::NodeIdentifier,
+                                   ambiguous_nodes: &HashSet<G::NodeIdentifier>,
+                                   edges: &mut Vec<G::EdgeIdentifier>)
+                                   -> GraphContigs<G> {
     let mut contigs = vec![];
-    for node in graph.neighbors_directed(starting_node, EdgeDirection::Outgoing) {
+    graph.fill_edges(starting_node, EdgeDirection::Outgoing, edges);
+    for &edge in edges.iter() {
         let mut contig = vec![];
-        let mut current_node = node;
-        let mut current_edge = unwrap!(graph.find_edge(starting_node, node));
+        let mut current_node = unwrap!(graph.edge_endpoints(edge)).1;
+        let mut current_edge = unwrap!(graph.find_edge(starting_node, current_node));
         loop {
             let out_degree = graph.out_degree(current_node);
             contig.push(current_edge);
@@ -106,13 +110,13 @@ fn get_contigs_from_node(graph: &PtGraph, starting_node: NodeIndex,
 
 // Set weights of consecutive `Edge`s in the `Contig` to the mean value
 #[inline]
-fn standardize_contig(graph: &mut PtGraph, contig: Contig) {
+fn standardize_contig<G: GraphMut>(graph: &mut G, contig: Contig<G>) {
     // sum all weights in the contig
-    let sum: usize = contig.iter()
-        .map(|&e| unwrap!(graph.edge_weight(e)).1 as usize)
+    let sum: f64 = contig.iter()
+        .map(|&e| unwrap!(graph.edge_weight(e)).1.to_f64())
         .sum();
     // calculate new, standardizes weight
-    let standardized_weight = (sum as f64 / contig.len() as f64).round() as EdgeWeight;
+    let standardized_weight = G::Weight::from_f64(sum / contig.len() as f64);
     // modify all edges in the contig with new value
     for edge in contig {
         unwrap!(graph.edge_weight_mut(edge)).1 = standardized_weight;
@@ -121,9 +125,9 @@ fn standardize_contig(graph: &mut PtGraph, contig: Contig) {
 
 #[inline]
 fn calculate_standardization_ratio(original_genome_length: usize, k: usize,
-                                   sum_of_all_weights: usize, weights_lower_than_threshold: usize)
+                                   sum_of_all_weights: f64, weights_lower_than_threshold: f64)
                                    -> f64 {
-    (original_genome_length - k) as f64 / (sum_of_all_weights - weights_lower_than_threshold) as f64
+    (original_genome_length - k) as f64 / (sum_of_all_weights - weights_lower_than_threshold)
 }
 
 
@@ -136,7 +140,7 @@ mod tests {
 
     #[test]
     fn calculates_standardization_ratio() {
-        let p = calculate_standardization_ratio(10, 0, 10, 0);
+        let p = calculate_standardization_ratio(10, 0, 10.0, 0.0);
         assert_eq!(p, 1.0);
     }
 
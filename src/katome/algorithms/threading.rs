@@ -0,0 +1,311 @@
+//! Read threading.
+//!
+//! Once reads are split into k-mers, information about the longer sequences
+//! they span is lost, so even repeats shorter than a read break contigs.
+//! Threading maps reads back onto paths of the shrunk graph and counts, for
+//! each repeat, how many reads enter it by the given incoming edge and leave
+//! it by the given outgoing edge. Repeat is either the node with several
+//! incoming and several outgoing edges, or the single edge which source has
+//! several incoming edges and target several outgoing ones.
+//!
+//! As in EULER and Velvet, repeat is split into separate copies, one per pair
+//! of edges, only if reads pair its incoming and outgoing edges one-to-one -
+//! each pair supported by at least the given number of reads. Pairs with
+//! lower support are treated as noise. Repeats touching edges of the already
+//! split repeat are left for the collapser.
+
+use algorithms::builder::{Build, for_each_read};
+use algorithms::pruner::Clean;
+use algorithms::shrinker::Shrinkable;
+use asm::SEQUENCES;
+use collections::GraphMut;
+use collections::graphs::{EdgeDirection, SequencesToNodes, index_nodes};
+use compress::{compress_edge, compress_node};
+use config::InputFileType;
+use prelude::{CDC, K1_SIZE, Weight};
+use slices::{BasicSlice, EdgeSlice};
+use utils::reverse_complement_of;
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Describes structure which repeats can be resolved by threading reads.
+pub trait Threadable: Build {
+    /// Threads reads from the FASTA/FASTQ input files through the structure
+    /// and splits repeats which they consistently span, each pair of edges
+    /// supported by at least `min_support` reads. Returns number of split
+    /// repeats.
+    fn thread_reads<P: AsRef<Path>>(&mut self, input_files: &[P], ft: InputFileType,
+                                    reverse_complement: bool, min_support: usize)
+                                    -> usize;
+}
+
+// Numbers of reads spanning the repeat, for each pair of its incoming and
+// outgoing edges.
+type Spans<E> = HashMap<(E, E), usize>;
+
+// Repeat along with edges entering and leaving it.
+struct Repeat<G: GraphMut> {
+    incoming: Vec<G::EdgeIdentifier>,
+    // edge of the repeat, none if the repeat is a single node
+    edge: Option<G::EdgeIdentifier>,
+    outgoing: Vec<G::EdgeIdentifier>,
+}
+
+impl<G: GraphMut> Threadable for G {
+    fn thread_reads<P: AsRef<Path>>(&mut self, input_files: &[P], ft: InputFileType,
+                                    reverse_complement: bool, min_support: usize)
+                                    -> usize {
+        info!("Starting read threading");
+        // repeats have to be single nodes or edges
+        self.shrink();
+        let input_files = input_files.iter()
+            .map(|f| f.as_ref().to_path_buf())
+            .collect::<Vec<PathBuf>>();
+        let spans = count_spans(self, &input_files, ft, reverse_complement);
+        // split repeats in the fixed order, identifiers don't change until
+        // the removal
+        let mut starts = spans.keys().cloned().collect::<Vec<G::NodeIdentifier>>();
+        starts.sort();
+        let mut claimed = HashSet::new();
+        let mut to_remove = vec![];
+        let mut split = 0;
+        for start in starts {
+            let repeat = match repeat_at(self, start) {
+                Some(r) => r,
+                None => continue,
+            };
+            let edges = repeat.incoming
+                .iter()
+                .chain(repeat.edge.iter())
+                .chain(repeat.outgoing.iter())
+                .cloned()
+                .collect::<Vec<G::EdgeIdentifier>>();
+            if edges.iter().any(|e| claimed.contains(e)) {
+                continue;
+            }
+            if let Some(pairs) = consistent_pairs(&repeat, &spans[&start], min_support) {
+                split_repeat(self, &repeat, &pairs);
+                claimed.extend(edges.iter().cloned());
+                to_remove.extend(edges);
+                split += 1;
+            }
+        }
+        self.remove_edges(to_remove);
+        self.remove_single_vertices();
+        info!("Split {} repeats spanned by reads", split);
+        split
+    }
+}
+
+#[inline]
+fn endpoints<G: GraphMut>(graph: &G, edge: G::EdgeIdentifier)
+                       -> (G::NodeIdentifier, G::NodeIdentifier) {
+    unwrap!(graph.edge_endpoints(edge))
+}
+
+// Counts reads (and their reverse complements) spanning possible repeats,
+// indexed by the first node of the repeat. Reads are mapped onto paths of the
+// graph one at a time, only paths of at least two edges span anything.
+fn count_spans<G: GraphMut>(graph: &G, input_files: &[PathBuf], ft: InputFileType,
+                         reverse_complement: bool)
+                         -> HashMap<G::NodeIdentifier, Spans<G::EdgeIdentifier>> {
+    let nodes = index_nodes(graph);
+    let mut spans = HashMap::new();
+    let mut paths = 0;
+    let mut key = vec![];
+    {
+        let mut add = |path: &[G::EdgeIdentifier]| {
+            add_spans(graph, path, &mut spans);
+            paths += 1;
+        };
+        for_each_read(input_files, ft, |read| {
+            thread_read(graph, &nodes, &mut key, read, &mut add);
+            if reverse_complement {
+                thread_read(graph, &nodes, &mut key, &reverse_complement_of(read), &mut add);
+            }
+        });
+    }
+    info!("Threaded reads along {} paths", paths);
+    spans
+}
+
+// Maps the read onto paths of the graph, which may start and end in the
+// middle of the edge, and passes paths of at least two edges to `f`. Path is
+// broken wherever the read disagrees with the graph, e.g. due to the
+// sequencing error. `key` is the buffer for compressed nodes of the read.
+fn thread_read<G, F>(graph: &G, nodes: &SequencesToNodes<G::NodeIdentifier>,
+                     key: &mut Vec<CDC>, read: &[u8], f: &mut F)
+    where G: GraphMut,
+          F: FnMut(&[G::EdgeIdentifier]) {
+    let k1 = unsafe { K1_SIZE };
+    if read.len() <= k1 {
+        return;
+    }
+    let hits = read.windows(k1)
+        .enumerate()
+        .filter_map(|(p, w)| {
+            key.clear();
+            compress_node(w, key);
+            nodes.get(&key[..]).map(|&n| (p, n))
+        })
+        .collect::<Vec<(usize, G::NodeIdentifier)>>();
+    let mut path = vec![];
+    match hits.first() {
+        Some(&(p, n)) if p > 0 => {
+            let prefix = &read[..p + k1];
+            path.extend(edge_matching(graph, n, EdgeDirection::Incoming, |s| s.ends_with(prefix)));
+        }
+        _ => {}
+    }
+    for w in hits.windows(2) {
+        let ((a, source), (b, _)) = (w[0], w[1]);
+        let piece = &read[a..b + k1];
+        match edge_matching(graph, source, EdgeDirection::Outgoing, |s| s == piece) {
+            Some(e) => path.push(e),
+            None => {
+                if path.len() > 1 {
+                    f(&path);
+                }
+                path.clear();
+            }
+        }
+    }
+    match hits.last() {
+        Some(&(p, n)) if p + k1 < read.len() => {
+            let suffix = &read[p..];
+            path.extend(edge_matching(graph,
+                                      n,
+                                      EdgeDirection::Outgoing,
+                                      |s| s.starts_with(suffix)));
+        }
+        _ => {}
+    }
+    if path.len() > 1 {
+        f(&path);
+    }
+}
+
+// Finds edge of the node which sequence matches. Edges of the same node
+// differ next to it, so at most one edge matches the sequence longer than
+// the node.
+#[inline]
+fn edge_matching<G: GraphMut, F: Fn(&[u8]) -> bool>(graph: &G, node: G::NodeIdentifier,
+                                                 direction: EdgeDirection, matches: F)
+                                                 -> Option<G::EdgeIdentifier> {
+    graph.edges_of(node, direction)
+        .into_iter()
+        .find(|&e| matches(&unwrap!(graph.edge_weight(e)).0.byte_name()[..]))
+}
+
+// Adds the path to the reads spanning repeats which it passes through.
+fn add_spans<G: GraphMut>(graph: &G, path: &[G::EdgeIdentifier],
+                       spans: &mut HashMap<G::NodeIdentifier, Spans<G::EdgeIdentifier>>) {
+    for (i, &e) in path.iter().enumerate() {
+        let start = endpoints(graph, e).1;
+        if graph.in_degree(start) < 2 {
+            continue;
+        }
+        // skip the edge of the repeat
+        let next = if graph.out_degree(start) == 1 { i + 2 } else { i + 1 };
+        if next < path.len() {
+            *spans.entry(start)
+                .or_insert_with(HashMap::new)
+                .entry((e, path[next]))
+                .or_insert(0) += 1;
+        }
+    }
+}
+
+// Gets repeat starting at the node, unless it's not a repeat or it can't be
+// split, because some of its edges lead back into it.
+fn repeat_at<G: GraphMut>(graph: &G, start: G::NodeIdentifier) -> Option<Repeat<G>> {
+    let incoming = graph.edges_of(start, EdgeDirection::Incoming);
+    if incoming.len() < 2 {
+        return None;
+    }
+    let (edge, end) = if graph.out_degree(start) == 1 {
+        let e = unwrap!(graph.first_edge(start, EdgeDirection::Outgoing));
+        let end = endpoints(graph, e).1;
+        if end == start || graph.in_degree(end) != 1 {
+            return None;
+        }
+        (Some(e), end)
+    }
+    else {
+        (None, start)
+    };
+    let outgoing = graph.edges_of(end, EdgeDirection::Outgoing);
+    if outgoing.len() < 2 {
+        return None;
+    }
+    let inside = |n: G::NodeIdentifier| n == start || n == end;
+    if incoming.iter().any(|&e| inside(endpoints(graph, e).0)) ||
+       outgoing.iter().any(|&e| inside(endpoints(graph, e).1)) {
+        return None;
+    }
+    Some(Repeat {
+        incoming: incoming,
+        edge: edge,
+        outgoing: outgoing,
+    })
+}
+
+// Gets pairs of incoming and outgoing edges with their support, if reads
+// pair edges of the repeat one-to-one.
+fn consistent_pairs<G: GraphMut>(repeat: &Repeat<G>, spans: &Spans<G::EdgeIdentifier>,
+                              min_support: usize)
+                              -> Option<Vec<(G::EdgeIdentifier, G::EdgeIdentifier, usize)>> {
+    let n = repeat.incoming.len();
+    if repeat.outgoing.len() != n {
+        return None;
+    }
+    let mut pairs = spans.iter()
+        .filter(|&(_, &support)| support >= min_support)
+        .map(|(&(i, o), &support)| (i, o, support))
+        .collect::<Vec<_>>();
+    // spans contain only edges of the repeat, so it's enough to check that
+    // no edge occurs in two pairs
+    let incoming = pairs.iter().map(|p| p.0).collect::<HashSet<_>>();
+    let outgoing = pairs.iter().map(|p| p.1).collect::<HashSet<_>>();
+    if pairs.len() != n || incoming.len() != n || outgoing.len() != n {
+        return None;
+    }
+    pairs.sort();
+    Some(pairs)
+}
+
+// Adds separate copy of the repeat for each pair of edges. Weight of the
+// repeat edge is divided according to the support of pairs. Old edges have to
+// be removed afterwards.
+fn split_repeat<G: GraphMut>(graph: &mut G, repeat: &Repeat<G>,
+                          pairs: &[(G::EdgeIdentifier, G::EdgeIdentifier, usize)]) {
+    let total = pairs.iter().map(|p| p.2).sum::<usize>() as f64;
+    for (copy, &(i, o, support)) in pairs.iter().enumerate() {
+        let source = endpoints(graph, i).0;
+        let target = endpoints(graph, o).1;
+        let incoming = *unwrap!(graph.edge_weight(i));
+        let outgoing = *unwrap!(graph.edge_weight(o));
+        let start = graph.add_node();
+        graph.add_edge(source, start, incoming);
+        let end = match repeat.edge {
+            Some(r) => {
+                let (slice, weight) = *unwrap!(graph.edge_weight(r));
+                // shrinking extends sequences in place, so copies can't share them
+                let slice = if copy == 0 {
+                    slice
+                }
+                else {
+                    let sequence = compress_edge(&slice.byte_name());
+                    EdgeSlice::new(SEQUENCES.write().push(&sequence))
+                };
+                let share = (weight.to_f64() * support as f64 / total).round().max(1.0);
+                let end = graph.add_node();
+                graph.add_edge(start, end, (slice, G::Weight::from_f64(share)));
+                end
+            }
+            None => start,
+        };
+        graph.add_edge(end, target, outgoing);
+    }
+}
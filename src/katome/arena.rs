@@ -0,0 +1,470 @@
+//! Flat arena storage for compressed sequences.
+//!
+//! Storing every compressed sequence in its own `Box<[CDC]>` costs one heap
+//! allocation and a 16-byte fat pointer per node/edge, which for small k-mer
+//! sizes is several times the size of the 2-bit payload itself. `SequenceArena`
+//! keeps all sequences in one contiguous vector of `CDC`s and only remembers
+//! where each of them starts and how long it is.
+//!
+//! Sequences are still addressed by their index, so `NodeSlice`s and
+//! `EdgeSlice`s work on the arena exactly like they did on the vector of boxed
+//! slices. Sequences may change their length (e.g. edges after
+//! `EdgeSlice::merge`) - shorter values are written in place, longer ones are
+//! appended to the end of the arena and the old space becomes garbage, which
+//! can be reclaimed with `compact`.
+//!
+//! Arena can either keep its buffers on the heap or in memory-mapped scratch
+//! files (see `SequenceArena::mapped`), which allows the assembler to work on
+//! data sets exceeding physical memory at the cost of page faults.
+
+use mmap::MmapVec;
+use prelude::{CDC, Idx};
+
+use std::io;
+use std::mem;
+use std::ops::{Deref, DerefMut, Index, IndexMut};
+use std::path::Path;
+
+/// Underlying buffer of the arena.
+#[derive(Debug)]
+pub enum Buffer<T: Copy> {
+    /// Buffer allocated on the heap.
+    Memory(Vec<T>),
+    /// Buffer living in the memory-mapped file.
+    Mapped(MmapVec<T>),
+}
+
+impl<T: Copy> Default for Buffer<T> {
+    fn default() -> Buffer<T> {
+        Buffer::Memory(Vec::new())
+    }
+}
+
+impl<T: Copy> Buffer<T> {
+    /// Creates empty buffer of the same kind.
+    fn empty_like(&self) -> Buffer<T> {
+        match *self {
+            Buffer::Memory(_) => Buffer::Memory(Vec::new()),
+            Buffer::Mapped(ref v) => {
+                match v.empty_like() {
+                    Ok(m) => Buffer::Mapped(m),
+                    Err(why) => panic!("Couldn't create scratch file: {}", why),
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        match *self {
+            Buffer::Memory(ref v) => v.capacity(),
+            Buffer::Mapped(ref v) => v.capacity(),
+        }
+    }
+
+    #[inline]
+    fn push(&mut self, value: T) {
+        match *self {
+            Buffer::Memory(ref mut v) => v.push(value),
+            Buffer::Mapped(ref mut v) => v.push(value),
+        }
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<T> {
+        match *self {
+            Buffer::Memory(ref mut v) => v.pop(),
+            Buffer::Mapped(ref mut v) => v.pop(),
+        }
+    }
+
+    #[inline]
+    fn extend_from_slice(&mut self, values: &[T]) {
+        match *self {
+            Buffer::Memory(ref mut v) => v.extend_from_slice(values),
+            Buffer::Mapped(ref mut v) => v.extend_from_slice(values),
+        }
+    }
+
+    #[inline]
+    fn reserve(&mut self, additional: usize) {
+        match *self {
+            Buffer::Memory(ref mut v) => v.reserve(additional),
+            Buffer::Mapped(ref mut v) => v.reserve(additional),
+        }
+    }
+
+    #[inline]
+    fn truncate(&mut self, len: usize) {
+        match *self {
+            Buffer::Memory(ref mut v) => v.truncate(len),
+            Buffer::Mapped(ref mut v) => v.truncate(len),
+        }
+    }
+
+    fn clear(&mut self) {
+        match *self {
+            Buffer::Memory(ref mut v) => v.clear(),
+            Buffer::Mapped(ref mut v) => v.clear(),
+        }
+    }
+
+    fn shrink_to_fit(&mut self) {
+        match *self {
+            Buffer::Memory(ref mut v) => v.shrink_to_fit(),
+            Buffer::Mapped(ref mut v) => v.shrink_to_fit(),
+        }
+    }
+}
+
+impl<T: Copy> Deref for Buffer<T> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &[T] {
+        match *self {
+            Buffer::Memory(ref v) => &v[..],
+            Buffer::Mapped(ref v) => &v[..],
+        }
+    }
+}
+
+impl<T: Copy> DerefMut for Buffer<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [T] {
+        match *self {
+            Buffer::Memory(ref mut v) => &mut v[..],
+            Buffer::Mapped(ref mut v) => &mut v[..],
+        }
+    }
+}
+
+/// Contiguous storage of variable-length compressed sequences.
+#[derive(Debug, Default)]
+pub struct SequenceArena {
+    // compressed sequences, laid out one after another
+    data: Buffer<CDC>,
+    // position of each sequence in `data`
+    starts: Buffer<Idx>,
+    // length of each sequence
+    lengths: Buffer<u32>,
+    // number of bytes in `data` which are no longer referenced by any sequence
+    wasted: usize,
+}
+
+impl SequenceArena {
+    /// Creates empty arena.
+    pub fn new() -> SequenceArena {
+        SequenceArena::default()
+    }
+
+    /// Creates empty arena with space reserved for the given number of
+    /// sequences and bytes of compressed data.
+    pub fn with_capacity(sequences: usize, bytes: usize) -> SequenceArena {
+        SequenceArena {
+            data: Buffer::Memory(Vec::with_capacity(bytes)),
+            starts: Buffer::Memory(Vec::with_capacity(sequences)),
+            lengths: Buffer::Memory(Vec::with_capacity(sequences)),
+            wasted: 0,
+        }
+    }
+
+    /// Creates empty arena which keeps its buffers in memory-mapped files
+    /// inside the given scratch directory.
+    pub fn mapped<P: AsRef<Path>>(dir: P) -> io::Result<SequenceArena> {
+        Ok(SequenceArena {
+            data: Buffer::Mapped(MmapVec::new(&dir)?),
+            starts: Buffer::Mapped(MmapVec::new(&dir)?),
+            lengths: Buffer::Mapped(MmapVec::new(&dir)?),
+            wasted: 0,
+        })
+    }
+
+    /// Checks if the arena is backed by memory-mapped files.
+    pub fn is_mapped(&self) -> bool {
+        match self.data {
+            Buffer::Mapped(_) => true,
+            Buffer::Memory(_) => false,
+        }
+    }
+
+    /// Gets number of sequences stored in the arena.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Checks if there are no sequences in the arena.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.starts.is_empty()
+    }
+
+    /// Appends sequence to the end of the arena. Returns its index.
+    #[inline]
+    pub fn push(&mut self, sequence: &[CDC]) -> Idx {
+        let idx = self.starts.len();
+        self.starts.push(self.data.len());
+        self.lengths.push(sequence.len() as u32);
+        self.data.extend_from_slice(sequence);
+        idx
+    }
+
+    /// Appends copy of the sequence stored under the given index. Returns
+    /// index of the copy.
+    #[inline]
+    pub fn duplicate(&mut self, idx: Idx) -> Idx {
+        let start = self.starts[idx];
+        let length = self.lengths[idx];
+        let end = start + length as usize;
+        let new_idx = self.starts.len();
+        self.starts.push(self.data.len());
+        self.lengths.push(length);
+        self.data.reserve(end - start);
+        for i in start..end {
+            let chunk = self.data[i];
+            self.data.push(chunk);
+        }
+        new_idx
+    }
+
+    /// Removes the last sequence from the arena.
+    #[inline]
+    pub fn pop(&mut self) {
+        if let (Some(start), Some(length)) = (self.starts.pop(), self.lengths.pop()) {
+            if start + length as usize == self.data.len() {
+                self.data.truncate(start);
+            }
+            else {
+                self.wasted += length as usize;
+            }
+        }
+    }
+
+    /// Removes all sequences from the arena.
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.starts.clear();
+        self.lengths.clear();
+        self.wasted = 0;
+    }
+
+    /// Replaces the sequence under the given index.
+    ///
+    /// Sequences which are not longer than the original one are written in
+    /// place, longer sequences are moved to the end of the arena.
+    #[inline]
+    pub fn replace(&mut self, idx: Idx, sequence: &[CDC]) {
+        let start = self.starts[idx];
+        let old_length = self.lengths[idx] as usize;
+        if sequence.len() <= old_length {
+            self.data[start..start + sequence.len()].copy_from_slice(sequence);
+            self.wasted += old_length - sequence.len();
+        }
+        else if start + old_length == self.data.len() {
+            // last sequence in the arena can simply grow
+            self.data.truncate(start);
+            self.data.extend_from_slice(sequence);
+        }
+        else {
+            self.starts[idx] = self.data.len();
+            self.data.extend_from_slice(sequence);
+            self.wasted += old_length;
+        }
+        self.lengths[idx] = sequence.len() as u32;
+    }
+
+    /// Clears the sequence under the given index without changing indices of
+    /// other sequences.
+    #[inline]
+    pub fn clear_at(&mut self, idx: Idx) {
+        self.wasted += self.lengths[idx] as usize;
+        self.lengths[idx] = 0;
+    }
+
+    /// Iterates over all sequences in the arena.
+    pub fn iter(&self) -> Iter {
+        Iter {
+            arena: self,
+            idx: 0,
+        }
+    }
+
+    /// Moves all sequences next to each other, releasing space left by the
+    /// cleared and relocated sequences.
+    pub fn compact(&mut self) {
+        if self.wasted == 0 {
+            return;
+        }
+        let mut data = self.data.empty_like();
+        data.reserve(self.data.len() - self.wasted);
+        for (start, length) in self.starts.iter_mut().zip(self.lengths.iter()) {
+            let old_start = *start;
+            *start = data.len();
+            data.extend_from_slice(&self.data[old_start..old_start + *length as usize]);
+        }
+        self.data = data;
+        self.wasted = 0;
+    }
+
+    /// Releases unused capacity of the underlying vectors.
+    pub fn shrink_to_fit(&mut self) {
+        self.data.shrink_to_fit();
+        self.starts.shrink_to_fit();
+        self.lengths.shrink_to_fit();
+    }
+
+    /// Gets number of bytes of the compressed data referenced by sequences.
+    pub fn payload_size(&self) -> usize {
+        self.data.len() - self.wasted
+    }
+
+    /// Gets number of bytes of the compressed data which are no longer used.
+    pub fn wasted_size(&self) -> usize {
+        self.wasted
+    }
+
+    /// Gets number of bytes allocated by the arena, either on the heap or in
+    /// the scratch files.
+    pub fn heap_size(&self) -> usize {
+        self.data.capacity() * mem::size_of::<CDC>() +
+        self.starts.capacity() * mem::size_of::<Idx>() +
+        self.lengths.capacity() * mem::size_of::<u32>()
+    }
+
+    /// Gets number of bytes which would be needed to store the same sequences
+    /// as a vector of boxed slices.
+    pub fn boxed_size(&self) -> usize {
+        self.len() * mem::size_of::<Box<[CDC]>>() + self.payload_size() * mem::size_of::<CDC>()
+    }
+}
+
+impl Index<Idx> for SequenceArena {
+    type Output = [CDC];
+
+    #[inline]
+    fn index(&self, idx: Idx) -> &[CDC] {
+        let start = self.starts[idx];
+        &self.data[start..start + self.lengths[idx] as usize]
+    }
+}
+
+impl IndexMut<Idx> for SequenceArena {
+    #[inline]
+    fn index_mut(&mut self, idx: Idx) -> &mut [CDC] {
+        let start = self.starts[idx];
+        &mut self.data[start..start + self.lengths[idx] as usize]
+    }
+}
+
+/// Iterator over sequences stored in `SequenceArena`.
+pub struct Iter<'a> {
+    arena: &'a SequenceArena,
+    idx: Idx,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a [CDC];
+
+    fn next(&mut self) -> Option<&'a [CDC]> {
+        if self.idx >= self.arena.len() {
+            return None;
+        }
+        self.idx += 1;
+        Some(&self.arena[self.idx - 1])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.arena.len() - self.idx;
+        (remaining, Some(remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+    use super::*;
+
+    macro_rules! setup {
+        ($a:ident) => {
+            let mut $a = SequenceArena::new();
+            $a.push(&[]);
+            $a.push(&[1, 2, 3]);
+            $a.push(&[4, 5]);
+            $a.push(&[6, 7, 8, 9]);
+        }
+    }
+
+    #[test]
+    fn pushes_and_indexes() {
+        setup!(arena);
+        assert_eq!(arena.len(), 4);
+        assert!(arena[0].is_empty());
+        assert_eq!(&arena[1], &[1, 2, 3]);
+        assert_eq!(&arena[2], &[4, 5]);
+        assert_eq!(&arena[3], &[6, 7, 8, 9]);
+        assert_eq!(arena.payload_size(), 9);
+    }
+
+    #[test]
+    fn replaces_in_place() {
+        setup!(arena);
+        arena.replace(1, &[10, 11]);
+        assert_eq!(&arena[1], &[10, 11]);
+        assert_eq!(&arena[2], &[4, 5]);
+        assert_eq!(arena.wasted_size(), 1);
+    }
+
+    #[test]
+    fn replaces_with_longer_sequence() {
+        setup!(arena);
+        arena.replace(1, &[10, 11, 12, 13, 14]);
+        assert_eq!(&arena[1], &[10, 11, 12, 13, 14]);
+        assert_eq!(&arena[2], &[4, 5]);
+        assert_eq!(&arena[3], &[6, 7, 8, 9]);
+        assert_eq!(arena.wasted_size(), 3);
+        arena.replace(3, &[1, 2, 3, 4, 5, 6]);
+        assert_eq!(&arena[3], &[1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn duplicates_and_pops() {
+        setup!(arena);
+        let idx = arena.duplicate(2);
+        assert_eq!(idx, 4);
+        assert_eq!(&arena[4], &[4, 5]);
+        arena.pop();
+        assert_eq!(arena.len(), 4);
+        assert_eq!(arena.payload_size(), 9);
+    }
+
+    #[test]
+    fn compacts() {
+        setup!(arena);
+        arena.clear_at(1);
+        arena.replace(2, &[4, 5, 6, 7, 8]);
+        assert_eq!(arena.wasted_size(), 5);
+        arena.compact();
+        assert_eq!(arena.wasted_size(), 0);
+        assert_eq!(arena.payload_size(), 9);
+        assert!(arena[1].is_empty());
+        assert_eq!(&arena[2], &[4, 5, 6, 7, 8]);
+        assert_eq!(&arena[3], &[6, 7, 8, 9]);
+        assert_eq!(arena.iter().count(), 4);
+    }
+
+    #[test]
+    fn works_on_mapped_buffers() {
+        let mut arena = SequenceArena::mapped(temp_dir()).unwrap();
+        assert!(arena.is_mapped());
+        arena.push(&[]);
+        arena.push(&[1, 2, 3]);
+        arena.push(&[4, 5]);
+        arena.replace(1, &[1, 2, 3, 4]);
+        arena.compact();
+        assert!(arena.is_mapped());
+        assert_eq!(&arena[1], &[1, 2, 3, 4]);
+        assert_eq!(&arena[2], &[4, 5]);
+        assert_eq!(arena.payload_size(), 6);
+    }
+}
@@ -1,9 +1,22 @@
 //! Basic genome assembler.
 
-use asm::{Assemble, Contigs, SEQUENCES};
-use collections::{GIR, Graph, Convert};
-use config::Config;
-use prelude::{EdgeWeight, K_SIZE, set_global_k_sizes};
+use algorithms::bloom::create_filtered;
+use algorithms::builder::{AddReads, Build, check_files, create_builder};
+use algorithms::bubbles::Poppable;
+use algorithms::collapser::{Collapsable, SerializedContigs};
+use algorithms::components::remove_small_components;
+use algorithms::corrector::create_corrected;
+use algorithms::gap_closer::{close_gaps, copy_graph};
+use algorithms::partitioner::create_partitioned;
+use algorithms::pruner::{Clean, Prunable};
+use algorithms::scaffolder::scaffold;
+use algorithms::standardizer::Standardizable;
+use algorithms::threading::Threadable;
+use asm::{Assemble, Contigs, SEQUENCES, init_sequences};
+use collections::{GIR, GraphMut, Convert, ConvertCompacted};
+use collections::graphs::pt_graph::WeightedPtGraph;
+use config::{CollapseMode, Config, InputFileType, KmerCounting};
+use prelude::{K_SIZE, Weight, set_global_k_sizes};
 use stats::Stats;
 
 use std::path::Path;
@@ -13,31 +26,29 @@ use std::time::Instant;
 pub struct BasicAsm {}
 
 impl Assemble for BasicAsm {
-    fn assemble<P: AsRef<Path>, G: Graph>(config: Config<P>) {
+    fn assemble<P: AsRef<Path>, G: GraphMut>(config: Config<P>) {
         let start = Instant::now();
         info!("Starting assembler!");
+        config.validate();
         unsafe {
             set_global_k_sizes(config.k_mer_size);
         }
-        let (graph, number_of_read_bytes) =
-            G::create(&config.input_files,
-                      config.input_file_type,
-                      config.reverse_complement,
-                      config.minimal_weight_threshold as EdgeWeight);
+        init_sequences(&config);
+        let (graph, number_of_read_bytes) = create::<P, G>(&config);
         sequences_stats(number_of_read_bytes);
         assemble_with_graph(graph, config, start);
     }
 
-    fn assemble_with_gir<P: AsRef<Path>, G, T: GIR>(config: Config<P>) where G: Graph + Convert<T> {
+    fn assemble_with_gir<P: AsRef<Path>, G, T: GIR>(config: Config<P>)
+        where G: GraphMut + Convert<T> {
         let start = Instant::now();
         info!("Starting assembler!");
+        config.validate();
         unsafe {
             set_global_k_sizes(config.k_mer_size);
         }
-        let (gir, number_of_read_bytes) = T::create(&config.input_files,
-                                                    config.input_file_type,
-                                                    config.reverse_complement,
-                                                    config.minimal_weight_threshold as EdgeWeight);
+        init_sequences(&config);
+        let (gir, number_of_read_bytes) = create::<P, T>(&config);
         sequences_stats(number_of_read_bytes);
         gir.log_stats();
         let graph = G::create_from(gir);
@@ -45,39 +56,180 @@ impl Assemble for BasicAsm {
     }
 }
 
-fn sequences_stats(number_of_read_bytes: usize) {
-    let saved: usize = SEQUENCES.read().iter().map(|x| x.len()).sum();
-    let total: usize = SEQUENCES.read().len();
+/// Assembles given data using specified `GIR` and `Graph`, which is created
+/// already compacted, and writes results into the output file.
+///
+/// Creation of the compacted graph replaces all sequences in the global
+/// `SEQUENCES`.
+pub fn assemble_compacted<P: AsRef<Path>, G, T: GIR>(config: Config<P>)
+    where G: GraphMut + ConvertCompacted<T> {
+    let start = Instant::now();
+    info!("Starting assembler!");
+    config.validate();
+    unsafe {
+        set_global_k_sizes(config.k_mer_size);
+    }
+    init_sequences(&config);
+    let (gir, number_of_read_bytes) = create::<P, T>(&config);
+    sequences_stats(number_of_read_bytes);
+    gir.log_stats();
+    let graph = G::create_compacted_from(gir);
+    assemble_with_graph(graph, config, start);
+}
+
+/// Counts k-mers of the input in the `Builder` of the collection. K-mers are
+/// counted in partitions on the disk or in memory, with the error correction
+/// or the Bloom filter pre-pass if it is enabled in the config.
+pub fn count_kmers<P: AsRef<Path>, T: AddReads>(config: &Config<P>) -> (T, usize) {
+    let files = check_files(&config.input_files);
+    let ft = config.input_file_type;
+    let rc = config.reverse_complement;
+    let reads = ft != InputFileType::BFCounter;
+    match config.kmer_counting.unwrap_or(KmerCounting::InMemory) {
+        KmerCounting::Partitioned => create_partitioned(&files, ft, rc, &config.partition_params()),
+        KmerCounting::InMemory => {
+            match (config.error_correction_threshold, config.bloom_filter_fp_rate) {
+                (Some(threshold), None) if reads => create_corrected(&files, ft, rc, threshold),
+                (None, Some(rate)) if reads => create_filtered(&files, ft, rc, rate),
+                _ => {
+                    create_builder(&files,
+                                   ft,
+                                   rc,
+                                   T::Weight::from_f64(config.minimal_weight_threshold as f64))
+                }
+            }
+        }
+    }
+}
+
+/// Creates collection out of the input, as set in the config. Returns
+/// collection with information about total number of read bytes.
+pub fn create<P: AsRef<Path>, T: Build>(config: &Config<P>) -> (T, usize) {
+    let (builder, number_of_read_bytes) = count_kmers::<P, T::Builder>(config);
+    (T::from_builder(builder), number_of_read_bytes)
+}
+
+/// Logs statistics of the global `SEQUENCES`.
+pub fn sequences_stats(number_of_read_bytes: usize) {
+    let s = SEQUENCES.read();
+    let saved: usize = s.payload_size();
+    let total: usize = s.len();
     info!("Avg size of edge: {}", saved as f64 / total as f64);
     info!("Saved {} out of {} bytes bytes -- {:.2}%",
              saved,
              number_of_read_bytes,
              (saved * 100) as f64 / number_of_read_bytes as f64);
+    info!("{}", s.stats());
+}
+
+fn assemble_with_graph<P: AsRef<Path>, G: GraphMut>(graph: G, config: Config<P>, start: Instant) {
+    let (serialized_contigs, estimated_repeats, closing_graph) =
+        simplify_and_collapse(graph, &config);
+    info!("I created {} contigs", serialized_contigs.len());
+    let mut contigs = Contigs::new(config.original_genome_length, serialized_contigs);
+    contigs.estimated_repeats = estimated_repeats;
+    contigs.log_stats();
+    contigs.save_to_file(&config.output_file);
+    save_scaffolds(&contigs, &config, closing_graph.as_ref());
+    log_elapsed(start);
 }
 
-fn assemble_with_graph<P: AsRef<Path>, G: Graph>(mut graph: G, config: Config<P>, start: Instant) {
+/// Prunes and standardizes the graph, then collapses it into contigs.
+///
+/// Returns also indices of contigs with the estimated number of repeats and,
+/// if gaps in scaffolds are to be closed, copy of the graph from before
+/// collapsing.
+pub fn simplify_and_collapse<P: AsRef<Path>, G: GraphMut>(mut graph: G, config: &Config<P>)
+                                                       -> (SerializedContigs, Vec<usize>,
+                                                           Option<WeightedPtGraph<G::Weight>>) {
     graph.log_stats();
     info!("First pruning.");
     graph.remove_dead_paths();
     graph.log_stats();
+    if let Some(params) = config.bubble_params() {
+        info!("Popping bubbles.");
+        graph.pop_bubbles(&params);
+        graph.log_stats();
+    }
+    if let Some(fraction) = config.erroneous_connection_fraction {
+        info!("Removing erroneous connections.");
+        graph.remove_erroneous_connections(fraction);
+        graph.log_stats();
+    }
     info!("Standardizing contigs.");
     graph.standardize_contigs();
-    graph.remove_weak_edges(config.minimal_weight_threshold as EdgeWeight);
+    graph.remove_weak_edges(G::Weight::from_f64(config.minimal_weight_threshold as f64));
     graph.standardize_contigs();
     graph.log_stats();
     info!("Standardizing edges");
     graph.standardize_edges(config.original_genome_length,
                             unsafe { K_SIZE },
-                            config.minimal_weight_threshold as EdgeWeight);
+                            G::Weight::from_f64(config.minimal_weight_threshold as f64));
     graph.log_stats();
     info!("Second pruning");
     graph.remove_dead_paths();
     graph.log_stats();
-    let serialized_contigs = graph.collapse();
-    info!("I created {} contigs", serialized_contigs.len());
-    let contigs = Contigs::new(config.original_genome_length, serialized_contigs);
-    contigs.log_stats();
-    contigs.save_to_file(config.output_file);
+    match config.read_threading_min_support {
+        Some(support) if config.input_file_type != InputFileType::BFCounter => {
+            info!("Threading reads.");
+            graph.thread_reads(&config.input_files,
+                               config.input_file_type,
+                               config.reverse_complement,
+                               support);
+            graph.log_stats();
+        }
+        _ => {}
+    }
+    if config.min_component_length.is_some() || config.min_component_weight.is_some() {
+        info!("Removing small components.");
+        let stats = remove_small_components(&mut graph,
+                                            config.min_component_length.unwrap_or(0),
+                                            config.min_component_weight.unwrap_or(0) as f64);
+        info!("{}", stats);
+        graph.log_stats();
+    }
+    let closing_graph = match (&config.paired_input_files, config.gap_closing_tolerance) {
+        (&Some(_), Some(_)) => {
+            info!("Copying graph for gap closing.");
+            Some(copy_graph(&graph))
+        }
+        _ => None,
+    };
+    let (contigs, estimated_repeats) =
+        graph.collapse_in_mode(config.collapse_mode.unwrap_or(CollapseMode::Weights),
+                               config.dominant_branch_ratio,
+                               config.unroll_cycles.unwrap_or(false));
+    (contigs, estimated_repeats, closing_graph)
+}
+
+/// Scaffolds contigs with read pairs, if they are given, and saves scaffolds
+/// along with their AGP description next to the output file. Gaps are closed
+/// with paths of the given graph.
+pub fn save_scaffolds<P: AsRef<Path>, W: Weight>(contigs: &Contigs, config: &Config<P>,
+                                                 closing_graph: Option<&WeightedPtGraph<W>>) {
+    let files = match config.paired_input_files {
+        Some(ref f) => f,
+        None => return,
+    };
+    info!("Scaffolding contigs.");
+    let mut scaffolds = scaffold(&contigs.serialized_contigs,
+                                 files[0].as_ref(),
+                                 files[1].as_ref(),
+                                 config.input_file_type,
+                                 config.scaffold_min_links.unwrap_or(3));
+    info!("Created {} scaffolds", scaffolds.scaffolds.len());
+    if let (Some(graph), Some(tolerance)) = (closing_graph, config.gap_closing_tolerance) {
+        info!("Closing gaps.");
+        close_gaps(graph, &contigs.serialized_contigs, &mut scaffolds, tolerance);
+    }
+    let output = config.output_file.as_ref();
+    scaffolds.save_to_files(&contigs.serialized_contigs,
+                            output.with_extension("scaffolds.fa"),
+                            output.with_extension("agp"));
+}
+
+/// Logs time elapsed since the start of the assembly.
+pub fn log_elapsed(start: Instant) {
     let duration = start.elapsed();
     let secs = duration.as_secs();
     let hours = secs / 3600;
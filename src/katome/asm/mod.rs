@@ -1,9 +1,11 @@
 //! De novo genome assemblers.
 pub mod basic_assembler;
+pub mod multi_k_assembler;
 
 use algorithms::collapser::SerializedContigs;
-use collections::{GIR, Graph, Convert};
-use config::Config;
+use arena::SequenceArena;
+use collections::{GIR, GraphMut, Convert};
+use config::{Config, SequencesStorage};
 use prelude::LockedSequences;
 
 use std::error::Error;
@@ -20,11 +22,29 @@ lazy_static! {
     /// ReadSlice (it doesn't have to store `Arc` to the container).
     pub static ref SEQUENCES: LockedSequences = {
         let l = LockedSequences::default();
-        l.write().push(vec![].into_boxed_slice());
+        l.write().push(&[]);
         l
     };
 }
 
+/// Replace global `SEQUENCES` with an empty storage described by the config.
+pub fn init_sequences<P: AsRef<Path>>(config: &Config<P>) {
+    let arena = match config.sequences_storage.unwrap_or(SequencesStorage::Memory) {
+        SequencesStorage::Memory => SequenceArena::new(),
+        SequencesStorage::Mmap => {
+            let dir = config.scratch_directory();
+            info!("Storing sequences in memory-mapped files in {}", dir.display());
+            match SequenceArena::mapped(&dir) {
+                Ok(a) => a,
+                Err(why) => panic!("Couldn't create scratch files in {}: {}", dir.display(), why),
+            }
+        }
+    };
+    let mut s = SEQUENCES.write();
+    *s = arena;
+    s.push(&[]);
+}
+
 #[doc(hidden)]
 pub mod lock {
     use std::sync::Mutex;
@@ -43,6 +63,10 @@ pub struct Contigs {
 
     /// Serialized contigs.
     pub serialized_contigs: SerializedContigs,
+
+    /// Indices of contigs containing cycles, which number of repeats was
+    /// estimated from the coverage.
+    pub estimated_repeats: Vec<usize>,
 }
 
 impl Contigs {
@@ -51,6 +75,7 @@ impl Contigs {
         Contigs {
             original_genome_length: length_,
             serialized_contigs: serialized,
+            estimated_repeats: vec![],
         }
     }
 
@@ -66,7 +91,12 @@ impl Contigs {
 
         let mut writer = BufWriter::new(&file);
         for (i, c) in self.serialized_contigs.iter().enumerate() {
-            writeln!(&mut writer, ">katome_{}", i).unwrap();
+            if self.estimated_repeats.contains(&i) {
+                writeln!(&mut writer, ">katome_{} estimated_repeats", i).unwrap();
+            }
+            else {
+                writeln!(&mut writer, ">katome_{}", i).unwrap();
+            }
             writeln!(&mut writer, "{}", c).unwrap();
         }
     }
@@ -75,8 +105,9 @@ impl Contigs {
 /// Public API for assemblers.
 pub trait Assemble {
     /// Assembles given data using specified `Graph` and writes results into the output file.
-    fn assemble<P: AsRef<Path>, G: Graph>(config: Config<P>);
+    fn assemble<P: AsRef<Path>, G: GraphMut>(config: Config<P>);
 
     /// Assembles given data using specified `GIR` and `Graph, and writes results into the output file.
-    fn assemble_with_gir<P: AsRef<Path>, G, T: GIR>(config: Config<P>) where G: Graph + Convert<T>;
+    fn assemble_with_gir<P: AsRef<Path>, G, T: GIR>(config: Config<P>)
+        where G: GraphMut + Convert<T>;
 }
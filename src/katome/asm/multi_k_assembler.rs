@@ -0,0 +1,140 @@
+//! Multi-k iterative genome assembler.
+//!
+//! Graph built with a small k-mer bridges regions of low coverage, but can't
+//! resolve repeats longer than k-mer. Graph built with a big k-mer resolves
+//! them, but breaks in regions of low coverage. Similarly to IDBA and SPAdes
+//! this assembler runs rounds with increasing sizes of the k-mer. Contigs
+//! assembled in each round are fed into the graph of the next one as
+//! high-weight pseudo-reads, so that regions bridged with the smaller k-mer are
+//! not lost.
+
+use algorithms::builder::{Build, add_reads};
+use algorithms::collapser::SerializedContigs;
+use asm::{Assemble, Contigs, init_sequences};
+use asm::basic_assembler::{count_kmers, log_elapsed, save_scaffolds, sequences_stats,
+                           simplify_and_collapse};
+use collections::{GIR, GraphMut, Convert, ConvertCompacted};
+use config::{Config, InputFileType};
+use prelude::{Weight, set_global_k_sizes};
+use stats::Stats;
+
+use std::fs::{File, remove_file};
+use std::io::BufWriter;
+use std::io::prelude::*;
+use std::path::Path;
+use std::time::Instant;
+
+/// Multi-k iterative assembler.
+///
+/// Sizes of the k-mer are taken from `Config::k_mer_sizes`.
+pub struct MultiKAsm {}
+
+impl Assemble for MultiKAsm {
+    fn assemble<P: AsRef<Path>, G: GraphMut>(config: Config<P>) {
+        assemble_rounds(config, |config, pseudo_reads| create_round::<P, G>(config, pseudo_reads));
+    }
+
+    fn assemble_with_gir<P: AsRef<Path>, G, T: GIR>(config: Config<P>)
+        where G: GraphMut + Convert<T> {
+        assemble_rounds(config, |config, pseudo_reads| {
+            let (gir, number_of_read_bytes) = create_round::<P, T>(config, pseudo_reads);
+            gir.log_stats();
+            (G::create_from(gir), number_of_read_bytes)
+        });
+    }
+}
+
+/// Assembles given data in rounds using specified `GIR` and `Graph`, which is
+/// created already compacted in every round, and writes results into the
+/// output file.
+///
+/// Creation of the compacted graph replaces all sequences in the global
+/// `SEQUENCES`.
+pub fn assemble_compacted<P: AsRef<Path>, G, T: GIR>(config: Config<P>)
+    where G: GraphMut + ConvertCompacted<T> {
+    assemble_rounds(config, |config, pseudo_reads| {
+        let (gir, number_of_read_bytes) = create_round::<P, T>(config, pseudo_reads);
+        gir.log_stats();
+        (G::create_compacted_from(gir), number_of_read_bytes)
+    });
+}
+
+// Creates collection of the round out of the input and k-mers of contigs from
+// the previous round, if there are any. Contig k-mers are added to the
+// `Builder`, so that they are counted along with k-mers of the reads.
+fn create_round<P: AsRef<Path>, T: Build>(config: &Config<P>, pseudo_reads: Option<&Path>)
+                                          -> (T, usize) {
+    let (mut builder, number_of_read_bytes) = count_kmers::<P, T::Builder>(config);
+    if let Some(path) = pseudo_reads {
+        add_reads(&mut builder,
+                  &[path],
+                  InputFileType::BFCounter,
+                  config.reverse_complement,
+                  T::Weight::zero());
+    }
+    (T::from_builder(builder), number_of_read_bytes)
+}
+
+fn assemble_rounds<P, G, F>(config: Config<P>, mut create: F)
+    where P: AsRef<Path>,
+          G: GraphMut,
+          F: FnMut(&Config<P>, Option<&Path>) -> (G, usize) {
+    let start = Instant::now();
+    info!("Starting multi-k assembler!");
+    config.validate();
+    let schedule = config.k_schedule();
+    let weight = G::Weight::from_f64(config.contig_kmer_weight.unwrap_or(100) as f64);
+    let pseudo_reads = config.scratch_directory().join("katome_contig_kmers.bfc");
+    let mut serialized_contigs = SerializedContigs::new();
+    let mut estimated_repeats = vec![];
+    let mut closing_graph = None;
+    for (round, &k) in schedule.iter().enumerate() {
+        info!("Round {} out of {}, k-mer size {}", round + 1, schedule.len(), k);
+        unsafe {
+            set_global_k_sizes(k);
+        }
+        init_sequences(&config);
+        let (graph, number_of_read_bytes) = if round > 0 {
+            info!("Adding k-mers of {} contigs from the previous round",
+                  serialized_contigs.len());
+            write_contig_kmers(&serialized_contigs, k, weight, &pseudo_reads);
+            let created = create(&config, Some(&pseudo_reads));
+            if let Err(why) = remove_file(&pseudo_reads) {
+                panic!("Couldn't remove {}: {}", pseudo_reads.display(), why);
+            }
+            created
+        }
+        else {
+            create(&config, None)
+        };
+        sequences_stats(number_of_read_bytes);
+        let (serialized, estimated, closing) = simplify_and_collapse(graph, &config);
+        serialized_contigs = serialized;
+        estimated_repeats = estimated;
+        // sequences of the graph copied in the previous round are already gone
+        closing_graph = closing;
+        info!("Round {} created {} contigs", round + 1, serialized_contigs.len());
+    }
+    let mut contigs = Contigs::new(config.original_genome_length, serialized_contigs);
+    contigs.estimated_repeats = estimated_repeats;
+    contigs.log_stats();
+    contigs.save_to_file(&config.output_file);
+    save_scaffolds(&contigs, &config, closing_graph.as_ref());
+    log_elapsed(start);
+}
+
+// Writes k-mers of the contigs in the BFCounter format, each with the given
+// weight. Contigs shorter than k-mer are skipped.
+fn write_contig_kmers<W: Weight>(contigs: &SerializedContigs, k: usize, weight: W, path: &Path) {
+    let file = match File::create(path) {
+        Ok(f) => f,
+        Err(why) => panic!("Couldn't create {}: {}", path.display(), why),
+    };
+    let mut writer = BufWriter::new(file);
+    for contig in contigs.iter().filter(|c| c.len() >= k) {
+        for kmer in contig.as_bytes().windows(k) {
+            writer.write_all(kmer).unwrap();
+            writeln!(&mut writer, "\t{}", weight).unwrap();
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Direct construction of the compacted de Bruijn graph from the `GIR`.
+//!
+//! Instead of creating a node for every (k-1)-mer and shrinking straight paths
+//! afterwards, maximal non-branching paths (unitigs) are walked in the `GIR`
+//! and each of them becomes a single edge of the graph. Only branching nodes,
+//! sources, sinks and a single node of every isolated cycle are added to the
+//! graph, so simplification algorithms work on a fraction of the nodes.
+
+use asm::SEQUENCES;
+use collections::girs::edges::{Edge, Outgoing};
+use collections::graphs::pt_graph::{NodeIndex, WeightedPtGraph};
+use compress::compress_edge;
+use prelude::{CDC, EdgeWeight, Idx, Weight};
+use slices::{BasicSlice, EdgeSlice, NodeSlice};
+
+use fixedbitset::FixedBitSet;
+
+/// Nodes of the `GIR` with their outgoing edges. Targets of the edges are
+/// positions of the nodes in the vector.
+pub type GIRNodes<W = EdgeWeight> = Vec<(NodeSlice, Outgoing<W>)>;
+
+// Single maximal non-branching path.
+struct Unitig<W> {
+    source: Idx,
+    target: Idx,
+    sequence: Vec<CDC>,
+    weight: W,
+}
+
+/// Creates compacted graph out of the `GIR` nodes. Weight of each edge is the
+/// mean weight of k-mers in the unitig it represents.
+///
+/// All sequences in the global `SEQUENCES` are replaced with the sequences of
+/// unitigs, so neither `NodeSlice`s of the nodes nor slices of any other
+/// collection are valid after the call.
+pub fn compacted_graph<W: Weight>(nodes: GIRNodes<W>) -> WeightedPtGraph<W> {
+    let mut in_degrees = vec![0_usize; nodes.len()];
+    for &(_, ref edges) in &nodes {
+        for e in edges.iter() {
+            in_degrees[e.0] += 1;
+        }
+    }
+    let unitigs = {
+        // node lies inside of the unitig if it has exactly one incoming and
+        // one outgoing edge, which is not a loop
+        let is_inner = |v: Idx| {
+            in_degrees[v] == 1 && nodes[v].1.len() == 1 && nodes[v].1[0].0 != v
+        };
+        let mut visited = FixedBitSet::with_capacity(nodes.len());
+        let mut unitigs = vec![];
+        for v in 0..nodes.len() {
+            if is_inner(v) {
+                continue;
+            }
+            for &edge in nodes[v].1.iter() {
+                unitigs.push(walk(&nodes, v, edge, &is_inner, &mut visited));
+            }
+        }
+        // inner nodes which have not been visited form isolated cycles
+        for v in 0..nodes.len() {
+            if is_inner(v) && !visited.contains(v) {
+                visited.insert(v);
+                unitigs.push(walk(&nodes, v, nodes[v].1[0], &is_inner, &mut visited));
+            }
+        }
+        unitigs
+    };
+    let node_count = nodes.len();
+    drop(nodes);
+    let mut graph = WeightedPtGraph::with_capacity(0, unitigs.len());
+    let mut node_ids: Vec<Option<NodeIndex>> = vec![None; node_count];
+    // unitigs hold copies of everything the graph needs, sequences of the GIR
+    // are dropped to make room for them
+    let mut s = SEQUENCES.write();
+    s.clear();
+    s.push(&[]);
+    for u in unitigs {
+        let source = graph_node(&mut graph, &mut node_ids, u.source);
+        let target = graph_node(&mut graph, &mut node_ids, u.target);
+        let id = s.push(&u.sequence);
+        graph.add_edge(source, target, (EdgeSlice::new(id), u.weight));
+    }
+    s.shrink_to_fit();
+    info!("Compacted graph has {} nodes and {} edges",
+          graph.node_count(),
+          graph.edge_count());
+    graph
+}
+
+/// Follows the unitig starting with the given edge of the `source` node.
+fn walk<W: Weight, F: Fn(Idx) -> bool>(nodes: &GIRNodes<W>, source: Idx, first: Edge<W>,
+                                       is_inner: &F, visited: &mut FixedBitSet)
+                                       -> Unitig<W> {
+    let mut sequence = nodes[source].0.byte_name();
+    let mut total_weight = 0.0;
+    let mut length = 0_usize;
+    let mut edge = first;
+    loop {
+        sequence.push(edge.2);
+        total_weight += edge.1.to_f64();
+        length += 1;
+        let target = edge.0;
+        if target == source || !is_inner(target) {
+            return Unitig {
+                source: source,
+                target: target,
+                sequence: compress_edge(&sequence),
+                weight: W::from_f64(total_weight / length as f64),
+            };
+        }
+        visited.insert(target);
+        edge = nodes[target].1[0];
+    }
+}
+
+fn graph_node<W: Weight>(graph: &mut WeightedPtGraph<W>, node_ids: &mut [Option<NodeIndex>],
+                         v: Idx)
+                         -> NodeIndex {
+    match node_ids[v] {
+        Some(n) => n,
+        None => {
+            let n = graph.add_node(());
+            node_ids[v] = Some(n);
+            n
+        }
+    }
+}
@@ -1,38 +1,38 @@
 //! Edges representation in GIR.
 
-use prelude::{EdgeWeight, Idx};
+use prelude::{EdgeWeight, Idx, Weight};
 
 /// Single edge representation.
 ///
 /// `Idx` indicates unique id of the endpoint node of the edge, assigned based on the
 /// GIR creation order.
 /// Last byte denotes last character in the kmer.
-pub type Edge = (Idx, EdgeWeight, u8);
+pub type Edge<W = EdgeWeight> = (Idx, W, u8);
 /// Stores information about consecutive edges.
-pub type Outgoing = Box<[Edge]>;
+pub type Outgoing<W = EdgeWeight> = Box<[Edge<W>]>;
 
 /// Edges representation in GIR. It saves information about outgoing edges, in which tuples
 /// of id and weight indicate a single edge.
 #[allow(dead_code)]
 #[derive(Clone, Debug)]
-pub struct Edges {
+pub struct Edges<W = EdgeWeight> {
     /// Information about outgoing edges, storing targets and weights.
-    pub outgoing: Outgoing,
+    pub outgoing: Outgoing<W>,
     /// Index of source node for all edges in outgoing.
     pub idx: Idx,
 }
 
-impl Edges {
+impl<W: Weight> Edges<W> {
     /// Creates `Edges` with a single `Edge`.
-    pub fn new(to: Idx, idx_: Idx, last_char: u8) -> Edges {
+    pub fn new(to: Idx, idx_: Idx, last_char: u8) -> Edges<W> {
         Edges {
-            outgoing: (vec![(to, 1, last_char)]).into_boxed_slice(),
+            outgoing: (vec![(to, W::one(), last_char)]).into_boxed_slice(),
             idx: idx_,
         }
     }
 
     /// Creates empty `Edges` with given starting node.
-    pub fn empty(idx_: Idx) -> Edges {
+    pub fn empty(idx_: Idx) -> Edges<W> {
         Edges {
             outgoing: Box::new([]),
             idx: idx_,
@@ -43,17 +43,17 @@ impl Edges {
     pub fn add_edge(&mut self, to: Idx, last_char: u8) {
         let mut out_ = Vec::new();
         out_.extend_from_slice(&self.outgoing);
-        out_.push((to, 1, last_char));
+        out_.push((to, W::one(), last_char));
         self.outgoing = out_.into_boxed_slice();
     }
 
     /// Removes edges with weight below threshold.
-    pub fn remove_weak_edges(&mut self, threshold: EdgeWeight) {
+    pub fn remove_weak_edges(&mut self, threshold: W) {
         self.outgoing = self.outgoing
             .iter()
             .cloned()
             .filter(|&x| x.1 >= threshold)
-            .collect::<Vec<Edge>>()
+            .collect::<Vec<Edge<W>>>()
             .into_boxed_slice();
     }
 
@@ -66,8 +66,8 @@ impl Edges {
     }
 }
 
-impl Default for Edges {
-    fn default() -> Edges {
+impl<W> Default for Edges<W> {
+    fn default() -> Edges<W> {
         Edges {
             outgoing: Box::new([]),
             idx: 0,
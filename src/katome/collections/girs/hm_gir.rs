@@ -1,15 +1,18 @@
 //! `HashMap` based Graph's Intermediate Representation
 
-use algorithms::builder::{Build, Init};
+use algorithms::builder::{AddReads, Build, Init, Update, add_reads};
 use asm::SEQUENCES;
-use collections::{Convert, GIR};
+use collections::{Convert, ConvertCompacted, ConvertWithMemory, GIR};
+use collections::girs::conversion_chunks;
+use collections::girs::compacted::{GIRNodes, compacted_graph};
 use collections::girs::edges::{Edge, Outgoing};
-use collections::graphs::pt_graph::{NodeIndex, PtGraph};
-use compress::{change_last_char_in_edge, compress_kmer, kmer_to_edge, compress_kmer_with_rev_compl};
+use collections::graphs::pt_graph::{NodeIndex, WeightedPtGraph};
+use compress::{compress_kmer, compress_kmer_with_rev_compl};
 use config::InputFileType;
-use prelude::{CDC, Idx, K_SIZE, K1_SIZE};
-use slices::{BasicSlice, EdgeSlice, NodeSlice};
-use super::hs_gir::create_or_modify_edge;
+use prelude::{CDC, EdgeWeight, Idx, K_SIZE, K1_SIZE, Weight};
+use slices::{BasicSlice, NodeSlice};
+use stats::{HeapSize, MemoryStats, table_size};
+use super::hs_gir::{add_outgoing_edges, create_or_modify_edge};
 
 use metrohash::MetroHash;
 use fixedbitset::FixedBitSet;
@@ -17,14 +20,17 @@ use fixedbitset::FixedBitSet;
 use std::collections::HashMap as HM;
 use std::collections::hash_map::Entry;
 use std::hash::BuildHasherDefault as BuildHash;
+use std::mem::size_of;
+use std::path::Path;
 
 /// `HashMap` GIR
-pub type HmGIR = HM<NodeSlice, Outgoing, BuildHash<MetroHash>>;
+pub type HmGIR<W = EdgeWeight> = HM<NodeSlice, Outgoing<W>, BuildHash<MetroHash>>;
 
-impl GIR for HmGIR {}
+impl<W: Weight> GIR for HmGIR<W> {}
 
-impl Init for HmGIR {
-    fn init(_edge_count: Option<usize>, node_count: Option<usize>, _ft: InputFileType) -> HmGIR {
+impl<W: Weight> Init for HmGIR<W> {
+    fn init(_edge_count: Option<usize>, node_count: Option<usize>, _ft: InputFileType)
+            -> HmGIR<W> {
         if let Some(nodes) = node_count {
             HmGIR::with_capacity_and_hasher(nodes, BuildHash::<MetroHash>::default())
         }
@@ -34,66 +40,107 @@ impl Init for HmGIR {
     }
 }
 
-impl Build for HmGIR {
+impl<W: Weight> AddReads for HmGIR<W> {
+    type Weight = W;
+
     /// Add new reads to `HmGIR`, modify weights of existing edges.
     fn add_read_fastaq(&mut self, read: &[u8], reverse_complement: bool) {
-        assert!(read.len() as Idx >= unsafe { K_SIZE }, "Read is too short!");
-        let mut s = NodeSlice::default();
-        let mut t = NodeSlice::default();
-        if reverse_complement {
-            // because underlying algorithm which adds nodes/edges to the graph
-            // relies on the fact that each time we slide the window the old
-            // target node becomes the new souce node, we cant insert reverse
-            // complement after kmer is compressed, but rather we need to store
-            // all reverse complements and add them after original read has been
-            // added. What is essentially happening in here is that first all
-            // read kmers are added and then all reverse complements are added.
-            // Generation of reverse complements is mangled together with
-            // compression of read kmers for performance reasons.
-            let mut reversed = Vec::with_capacity(read.len() - unsafe { K1_SIZE });
-            // let remainder = unsafe{ K1_SIZE } % 4;
-            for (cnt, window) in read.windows(unsafe { K_SIZE } as usize).enumerate() {
-                // compress k_mer, generate compressed reverse complement of the
-                // kmer and store it to add after all kmers for the read are
-                // generated
-                let (compressed_kmer, rev_compl_compr) = compress_kmer_with_rev_compl(window);
-                reversed.push((rev_compl_compr, window[window.len() - 1]));
-                add_single_edge(self,
-                                cnt == 0,
-                                compressed_kmer,
-                                &mut s,
-                                &mut t,
-                                window[window.len() - 1]);
-            }
-            // add reverse complements
-            let rev = reversed.len() - 1;
-            let last = reversed.remove(rev);
-            add_single_edge(self, true, last.0, &mut s, &mut t, last.1);
-            for r in reversed.drain(..).rev() {
-                add_single_edge(self, false, r.0, &mut s, &mut t, r.1);
-            }
+        add_read(self, read, W::one(), reverse_complement);
+    }
+
+    /// Add k-mers counted by BFCounter to `HmGIR`, modify weights of existing
+    /// edges.
+    fn add_read_bfc(&mut self, read: &[u8], weight: W, reverse_complement: bool) {
+        add_read(self, read, weight, reverse_complement);
+    }
+
+    fn kmer_count(&self) -> Option<usize> {
+        Some(self.values().map(|o| o.len()).sum())
+    }
+}
+
+impl<W: Weight> Build for HmGIR<W> {
+    type Weight = W;
+    type Builder = HmGIR<W>;
+
+    fn from_builder(gir: HmGIR<W>) -> HmGIR<W> {
+        gir
+    }
+}
+
+impl<W: Weight> Update for HmGIR<W> {
+    fn update<P: AsRef<Path>>(mut self, input_files: &[P], ft: InputFileType,
+                              reverse_complement: bool, minimal_weight_threshold: W)
+                              -> (HmGIR<W>, usize) {
+        let total = add_reads(&mut self,
+                              input_files,
+                              ft,
+                              reverse_complement,
+                              minimal_weight_threshold);
+        (self, total)
+    }
+}
+
+fn add_read<W: Weight>(gir: &mut HmGIR<W>, read: &[u8], weight: W, reverse_complement: bool) {
+    assert!(read.len() as Idx >= unsafe { K_SIZE }, "Read is too short!");
+    let mut s = NodeSlice::default();
+    let mut t = NodeSlice::default();
+    if reverse_complement {
+        // because underlying algorithm which adds nodes/edges to the graph
+        // relies on the fact that each time we slide the window the old
+        // target node becomes the new souce node, we cant insert reverse
+        // complement after kmer is compressed, but rather we need to store
+        // all reverse complements and add them after original read has been
+        // added. What is essentially happening in here is that first all
+        // read kmers are added and then all reverse complements are added.
+        // Generation of reverse complements is mangled together with
+        // compression of read kmers for performance reasons.
+        let mut reversed = Vec::with_capacity(read.len() - unsafe { K1_SIZE });
+        // let remainder = unsafe{ K1_SIZE } % 4;
+        for (cnt, window) in read.windows(unsafe { K_SIZE } as usize).enumerate() {
+            // compress k_mer, generate compressed reverse complement of the
+            // kmer and store it to add after all kmers for the read are
+            // generated
+            let (compressed_kmer, rev_compl_compr) = compress_kmer_with_rev_compl(window);
+            reversed.push((rev_compl_compr, window[window.len() - 1]));
+            add_single_edge(gir,
+                            cnt == 0,
+                            compressed_kmer,
+                            &mut s,
+                            &mut t,
+                            window[window.len() - 1],
+                            weight);
         }
-        else {
-            for (cnt, window) in read.windows(unsafe { K_SIZE } as usize).enumerate() {
-                let compressed_kmer = compress_kmer(window);
-                add_single_edge(self,
-                                cnt == 0,
-                                compressed_kmer,
-                                &mut s,
-                                &mut t,
-                                window[window.len() - 1]);
-            }
+        // add reverse complements
+        let rev = reversed.len() - 1;
+        let last = reversed.remove(rev);
+        add_single_edge(gir, true, last.0, &mut s, &mut t, last.1, weight);
+        for r in reversed.drain(..).rev() {
+            add_single_edge(gir, false, r.0, &mut s, &mut t, r.1, weight);
+        }
+    }
+    else {
+        for (cnt, window) in read.windows(unsafe { K_SIZE } as usize).enumerate() {
+            let compressed_kmer = compress_kmer(window);
+            add_single_edge(gir,
+                            cnt == 0,
+                            compressed_kmer,
+                            &mut s,
+                            &mut t,
+                            window[window.len() - 1],
+                            weight);
         }
     }
 }
 
 #[inline]
-fn add_single_edge(gir: &mut HmGIR, first_node: bool, compressed: Vec<CDC>,
-                   source_node: &mut NodeSlice, target_node: &mut NodeSlice, last_char: u8) {
+fn add_single_edge<W: Weight>(gir: &mut HmGIR<W>, first_node: bool, compressed: Vec<CDC>,
+                              source_node: &mut NodeSlice, target_node: &mut NodeSlice,
+                              last_char: u8, weight: W) {
     let mut insert = false;
     {
         let mut s = SEQUENCES.write();
-        s[0] = compressed.into_boxed_slice();
+        s.replace(0, &compressed);
     }
     // insert source on the first pass of the loop
     if first_node {
@@ -104,10 +151,7 @@ fn add_single_edge(gir: &mut HmGIR, first_node: bool, compressed: Vec<CDC>,
                 Entry::Occupied(oe) => *oe.key(),
                 Entry::Vacant(_) => {
                     // push to vector
-                    let mut s = SEQUENCES.write();
-                    let tmp = s[0].clone();
-                    let offset = s.len();
-                    s.push(tmp);
+                    let offset = SEQUENCES.write().duplicate(0);
                     insert = true;
                     NodeSlice::new(2 * offset)
                 }
@@ -131,8 +175,7 @@ fn add_single_edge(gir: &mut HmGIR, first_node: bool, compressed: Vec<CDC>,
                 // push to vector
                 let offset = if !insert {
                     let mut s = SEQUENCES.write();
-                    let tmp = s[0].clone();
-                    s.push(tmp);
+                    s.duplicate(0);
                     2 * s.len() - 1
                 }
                 else {
@@ -147,80 +190,91 @@ fn add_single_edge(gir: &mut HmGIR, first_node: bool, compressed: Vec<CDC>,
     if insert {
         gir.insert(*target_node, Box::new([]));
     }
-    let e: &mut Outgoing = unwrap!(gir.get_mut(source_node), "Node disappeared");
-    create_or_modify_edge(e, target_node.offset(), last_char);
+    let e: &mut Outgoing<W> = unwrap!(gir.get_mut(source_node), "Node disappeared");
+    create_or_modify_edge(e, target_node.offset(), last_char, weight);
     *source_node = *target_node;
 }
 
 
-impl Convert<HmGIR> for PtGraph {
-    fn create_from(mut gir: HmGIR) -> Self {
+impl<W: Weight> Convert<HmGIR<W>> for WeightedPtGraph<W> {
+    fn create_from(gir: HmGIR<W>) -> Self {
+        WeightedPtGraph::create_with_memory_from(gir).0
+    }
+}
+
+impl<W: Weight> ConvertWithMemory<HmGIR<W>> for WeightedPtGraph<W> {
+    fn create_with_memory_from(mut gir: HmGIR<W>) -> (Self, MemoryStats) {
         info!("Starting conversion from GIR to graph");
-        {
-            let mut idx_set: HM<Idx, Idx, BuildHash<MetroHash>> =
-                HM::with_capacity_and_hasher(gir.len(), BuildHash::<MetroHash>::default());
-            for (cnt, ns) in gir.keys().enumerate() {
-                idx_set.insert(ns.offset(), cnt);
-            }
-            for edges in gir.values_mut() {
-                *edges = edges.iter()
-                    .cloned()
-                    .map(|x| (*unwrap!(idx_set.get(&x.0)), x.1, x.2))
-                    .collect::<Vec<Edge>>()
-                    .into_boxed_slice();
-            }
+        let mut memory = MemoryStats::new(gir.heap_size() + SEQUENCES.read().heap_size());
+        // node indices follow the order of offsets, so that targets of the
+        // edges can be found with a binary search
+        let mut nodes = gir.keys().cloned().collect::<Vec<NodeSlice>>();
+        nodes.sort_by_key(|ns| ns.offset());
+        let mut remaining_edges = gir.values().map(|o| o.len()).sum::<usize>();
+        // edges are allocated as the GIR gives back its memory
+        let mut graph = WeightedPtGraph::with_capacity(nodes.len(), 0);
+        for _ in 0..nodes.len() {
+            graph.add_node(());
         }
-        let mut graph = PtGraph::default();
-        let mut s = SEQUENCES.write();
-        let mut fb = FixedBitSet::with_capacity(s.len());
-        for (idx, (ns, mut _edges)) in gir.drain().enumerate() {
-            let source = NodeIndex::new(idx);
-            while source.index() >= graph.node_count() {
-                graph.add_node(());
+        let mut fb = FixedBitSet::with_capacity(SEQUENCES.read().len());
+        let mut source = 0;
+        for chunk in conversion_chunks(&nodes) {
+            // hashing a node reads SEQUENCES, so the chunk has to be taken out
+            // of the GIR before the write lock is acquired
+            let mut outgoing = Vec::with_capacity(chunk.len());
+            for ns in chunk {
+                let mut edges = unwrap!(gir.remove(ns));
+                for edge in edges.iter_mut() {
+                    edge.0 = unwrap!(nodes.binary_search_by_key(&edge.0, |n| n.offset()).ok());
+                }
+                outgoing.push(edges);
             }
-            let id = ns.idx();
-            if _edges.is_empty() {
-                // clear the underlying box as it will no longer be used. We
-                // can't pop it out of the global vector cause it would ruin our
-                // existing indices that are already in the graph.
-                s[id] = Box::new([]);
-                continue;
+            // give back memory of the already converted nodes
+            if gir.len() <= gir.capacity() / 2 {
+                gir.shrink_to_fit();
             }
-            // at least one edge going out
-            let (target, weight, last_char) = _edges[0];
-            let prev = fb.put(id);
-            let (slice, tmp) = if prev {
-                // this slice uses already taken slot with compressed edge - we
-                // can't link them both to the same id
-                let new_compressed = change_last_char_in_edge(&s[id], last_char);
-                s.push(new_compressed.clone().into_boxed_slice());
-                (EdgeSlice::new(s.len() - 1), new_compressed)
+            graph.reserve_edges(outgoing.iter().map(|o| o.len()).sum::<usize>());
+            let mut s = SEQUENCES.write();
+            for (ns, edges) in chunk.iter().zip(outgoing.into_iter()) {
+                remaining_edges -= edges.len();
+                add_outgoing_edges(&mut graph,
+                                   &mut s,
+                                   &mut fb,
+                                   NodeIndex::new(source),
+                                   ns.idx(),
+                                   &edges);
+                source += 1;
             }
-            else {
-                // first edge slice will be pointing at the original place of source
-                // node, next edges will be appended to the global SEQUENCEs after
-                // having their last symbol changed
-                let tmp = kmer_to_edge(&s[id]);
-                s[id] = tmp.clone().into_boxed_slice();
-                (EdgeSlice::new(id), tmp)
-            };
-            while target >= graph.node_count() {
-                graph.add_node(());
+            memory.update(table_size::<(NodeSlice, Outgoing<W>)>(gir.capacity()) +
+                          remaining_edges * size_of::<Edge<W>>() +
+                          nodes.capacity() * size_of::<NodeSlice>() +
+                          graph.heap_size() + s.heap_size());
+        }
+        // release space taken by the cleared nodes
+        let mut s = SEQUENCES.write();
+        s.compact();
+        memory.update(graph.heap_size() + s.heap_size());
+        info!("{}", memory);
+        (graph, memory)
+    }
+}
+
+impl<W: Weight> ConvertCompacted<HmGIR<W>> for WeightedPtGraph<W> {
+    fn create_compacted_from(gir: HmGIR<W>) -> Self {
+        info!("Starting compacted conversion from GIR to graph");
+        let mut nodes = gir.into_iter().collect::<GIRNodes<W>>();
+        {
+            let mut idx_set: HM<Idx, Idx, BuildHash<MetroHash>> =
+                HM::with_capacity_and_hasher(nodes.len(), BuildHash::<MetroHash>::default());
+            for (cnt, &(ns, _)) in nodes.iter().enumerate() {
+                idx_set.insert(ns.offset(), cnt);
             }
-            graph.add_edge(source, NodeIndex::new(target), (slice, weight));
-            for edge in _edges.into_iter().skip(1) {
-                while edge.0 >= graph.node_count() {
-                    graph.add_node(());
+            for node in &mut nodes {
+                for edge in node.1.iter_mut() {
+                    edge.0 = *unwrap!(idx_set.get(&edge.0));
                 }
-                let new_compressed = change_last_char_in_edge(&tmp, edge.2);
-                s.push(new_compressed.into_boxed_slice());
-                graph.add_edge(source,
-                               NodeIndex::new(edge.0),
-                               (EdgeSlice::new(s.len() - 1), edge.1));
             }
-            // force drop of the original box in hashmap
-            _edges = Box::new([]);
         }
-        graph
+        compacted_graph(nodes)
     }
 }
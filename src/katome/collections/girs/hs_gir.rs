@@ -1,14 +1,18 @@
 //! `HashSet` based Graph's Intermediate Representation
 extern crate itertools;
 
-use algorithms::builder::{Build, Init};
+use algorithms::builder::{AddReads, Build, Init, Update, add_reads};
 use asm::SEQUENCES;
-use collections::{Convert, GIR};
-use collections::girs::edges::{Edges, Outgoing};
-use collections::graphs::pt_graph::{NodeIndex, PtGraph};
+use collections::{Convert, ConvertCompacted, ConvertWithMemory, GIR};
+use collections::girs::conversion_chunks;
+use collections::girs::compacted::{GIRNodes, compacted_graph};
+use collections::girs::edges::{Edge, Edges, Outgoing};
+use collections::graphs::pt_graph::{NodeIndex, WeightedPtGraph};
 use compress::{change_last_char_in_edge, compress_kmer, kmer_to_edge, compress_kmer_with_rev_compl};
-use prelude::{CDC, Idx, K_SIZE, K1_SIZE};
+use config::InputFileType;
+use prelude::{CDC, EdgeWeight, Idx, K_SIZE, K1_SIZE, Sequences, Weight};
 use slices::{BasicSlice, EdgeSlice, NodeSlice};
+use stats::{HeapSize, MemoryStats, table_size};
 
 use metrohash::MetroHash;
 use self::itertools::Itertools;
@@ -20,21 +24,22 @@ use std::fmt;
 use std::hash;
 use std::hash::BuildHasherDefault;
 use std::mem;
+use std::path::Path;
 
 /// Single node and its outgoing edges.
 ///
 /// Used for serialization/deserialization during `GIR` -> `Graph` conversion
 #[derive(Clone, Default)]
-pub struct Vertex {
+pub struct Vertex<W = EdgeWeight> {
     /// Node's `ReadSlice` representing k-mer.
     pub ns: NodeSlice,
     /// Outgoing edges.
-    pub edges: Edges,
+    pub edges: Edges<W>,
 }
 
-impl Vertex {
+impl<W> Vertex<W> {
     /// Creates new `Vertex`.
-    pub fn new(ns_: NodeSlice, edges_: Edges) -> Vertex {
+    pub fn new(ns_: NodeSlice, edges_: Edges<W>) -> Vertex<W> {
         Vertex {
             ns: ns_,
             edges: edges_,
@@ -42,104 +47,155 @@ impl Vertex {
     }
 }
 
-impl hash::Hash for Vertex {
+impl<W> hash::Hash for Vertex<W> {
     fn hash<H>(&self, state: &mut H) where H: hash::Hasher {
         self.ns.hash(state)
     }
 }
 
-impl cmp::Eq for Vertex {}
+impl<W> cmp::Eq for Vertex<W> {}
 
-impl cmp::PartialEq for Vertex {
-    fn eq(&self, other: &Vertex) -> bool {
+impl<W> cmp::PartialEq for Vertex<W> {
+    fn eq(&self, other: &Vertex<W>) -> bool {
         self.ns == other.ns
     }
 }
 
-impl cmp::PartialOrd for Vertex {
-    fn partial_cmp(&self, other: &Vertex) -> Option<cmp::Ordering> {
+impl<W> cmp::PartialOrd for Vertex<W> {
+    fn partial_cmp(&self, other: &Vertex<W>) -> Option<cmp::Ordering> {
         self.ns.partial_cmp(&other.ns)
     }
 }
 
-impl cmp::Ord for Vertex {
-    fn cmp(&self, other: &Vertex) -> cmp::Ordering {
+impl<W> cmp::Ord for Vertex<W> {
+    fn cmp(&self, other: &Vertex<W>) -> cmp::Ordering {
         self.ns.cmp(&other.ns)
     }
 }
 
 /// `HashSet` GIR
-pub type HsGIR = HS<Box<Vertex>, BuildHasherDefault<MetroHash>>;
+pub type HsGIR<W = EdgeWeight> = HS<Box<Vertex<W>>, BuildHasherDefault<MetroHash>>;
 
-impl GIR for HsGIR {}
+impl<W: Weight> GIR for HsGIR<W> {}
 
-impl Init for HsGIR {}
+impl<W: Weight> Init for HsGIR<W> {
+    fn init(_edge_count: Option<usize>, node_count: Option<usize>, _ft: InputFileType)
+            -> HsGIR<W> {
+        if let Some(nodes) = node_count {
+            HsGIR::with_capacity_and_hasher(nodes, BuildHasherDefault::<MetroHash>::default())
+        }
+        else {
+            HsGIR::default()
+        }
+    }
+}
+
+impl<W: Weight> AddReads for HsGIR<W> {
+    type Weight = W;
 
-impl Build for HsGIR {
-    /// Add new reads to `HmGIR`, modify weights of existing edges.
+    /// Add new reads to `HsGIR`, modify weights of existing edges.
     #[inline]
     fn add_read_fastaq(&mut self, read: &[u8], reverse_complement: bool) {
-        assert!(read.len() as Idx >= unsafe { K_SIZE }, "Read is too short!");
-        let mut s: Box<Vertex> = Box::new(Vertex::default());
-        let mut t: Box<Vertex> = Box::new(Vertex::default());
-        let mut idx = self.len();
-
-        if reverse_complement {
-            // because underlying algorithm which adds nodes/edges to the graph
-            // relies on the fact that each time we slide the window the old
-            // target node becomes the new souce node, we cant insert reverse
-            // complement after kmer is compressed, but rather we need to store
-            // all reverse complements and add them after original read has been
-            // added. What is essentially happening in here is that first all
-            // read kmers are added and then all reverse complements are added.
-            // Generation of reverse complements is mangled together with
-            // compression of read kmers for performance reasons.
-            let mut reversed = Vec::with_capacity(read.len() - unsafe { K1_SIZE });
-            // let remainder = unsafe{ K1_SIZE } % 4;
-            for (cnt, window) in read.windows(unsafe { K_SIZE } as usize).enumerate() {
-                // compress k_mer, generate compressed reverse complement of the
-                // kmer and store it to add after all kmers for the read are
-                // generated
-                let (compressed_kmer, rev_compl_compr) = compress_kmer_with_rev_compl(window);
-                reversed.push((rev_compl_compr, window[window.len() - 1]));
-                add_single_edge(self,
-                                cnt == 0,
-                                compressed_kmer,
-                                &mut idx,
-                                &mut s,
-                                &mut t,
-                                window[window.len() - 1]);
-            }
-            // add reverse complements
-            let rev = reversed.len() - 1;
-            let last = reversed.remove(rev);
-            add_single_edge(self, true, last.0, &mut idx, &mut s, &mut t, last.1);
-            for r in reversed.drain(..).rev() {
-                add_single_edge(self, false, r.0, &mut idx, &mut s, &mut t, r.1);
-            }
+        add_read(self, read, W::one(), reverse_complement);
+    }
+
+    /// Add k-mers counted by BFCounter to `HsGIR`, modify weights of existing
+    /// edges.
+    fn add_read_bfc(&mut self, read: &[u8], weight: W, reverse_complement: bool) {
+        add_read(self, read, weight, reverse_complement);
+    }
+
+    fn kmer_count(&self) -> Option<usize> {
+        Some(self.iter().map(|v| v.edges.outgoing.len()).sum())
+    }
+}
+
+impl<W: Weight> Build for HsGIR<W> {
+    type Weight = W;
+    type Builder = HsGIR<W>;
+
+    fn from_builder(gir: HsGIR<W>) -> HsGIR<W> {
+        gir
+    }
+}
+
+impl<W: Weight> Update for HsGIR<W> {
+    fn update<P: AsRef<Path>>(mut self, input_files: &[P], ft: InputFileType,
+                              reverse_complement: bool, minimal_weight_threshold: W)
+                              -> (HsGIR<W>, usize) {
+        let total = add_reads(&mut self,
+                              input_files,
+                              ft,
+                              reverse_complement,
+                              minimal_weight_threshold);
+        (self, total)
+    }
+}
+
+fn add_read<W: Weight>(gir: &mut HsGIR<W>, read: &[u8], weight: W, reverse_complement: bool) {
+    assert!(read.len() as Idx >= unsafe { K_SIZE }, "Read is too short!");
+    let mut s: Box<Vertex<W>> = Box::new(Vertex::default());
+    let mut t: Box<Vertex<W>> = Box::new(Vertex::default());
+    let mut idx = gir.len();
+
+    if reverse_complement {
+        // because underlying algorithm which adds nodes/edges to the graph
+        // relies on the fact that each time we slide the window the old
+        // target node becomes the new souce node, we cant insert reverse
+        // complement after kmer is compressed, but rather we need to store
+        // all reverse complements and add them after original read has been
+        // added. What is essentially happening in here is that first all
+        // read kmers are added and then all reverse complements are added.
+        // Generation of reverse complements is mangled together with
+        // compression of read kmers for performance reasons.
+        let mut reversed = Vec::with_capacity(read.len() - unsafe { K1_SIZE });
+        // let remainder = unsafe{ K1_SIZE } % 4;
+        for (cnt, window) in read.windows(unsafe { K_SIZE } as usize).enumerate() {
+            // compress k_mer, generate compressed reverse complement of the
+            // kmer and store it to add after all kmers for the read are
+            // generated
+            let (compressed_kmer, rev_compl_compr) = compress_kmer_with_rev_compl(window);
+            reversed.push((rev_compl_compr, window[window.len() - 1]));
+            add_single_edge(gir,
+                            cnt == 0,
+                            compressed_kmer,
+                            &mut idx,
+                            &mut s,
+                            &mut t,
+                            window[window.len() - 1],
+                            weight);
         }
-        else {
-            for (cnt, window) in read.windows(unsafe { K_SIZE } as usize).enumerate() {
-                let compressed_kmer = compress_kmer(window);
-                add_single_edge(self,
-                                cnt == 0,
-                                compressed_kmer,
-                                &mut idx,
-                                &mut s,
-                                &mut t,
-                                window[window.len() - 1]);
-            }
+        // add reverse complements
+        let rev = reversed.len() - 1;
+        let last = reversed.remove(rev);
+        add_single_edge(gir, true, last.0, &mut idx, &mut s, &mut t, last.1, weight);
+        for r in reversed.drain(..).rev() {
+            add_single_edge(gir, false, r.0, &mut idx, &mut s, &mut t, r.1, weight);
+        }
+    }
+    else {
+        for (cnt, window) in read.windows(unsafe { K_SIZE } as usize).enumerate() {
+            let compressed_kmer = compress_kmer(window);
+            add_single_edge(gir,
+                            cnt == 0,
+                            compressed_kmer,
+                            &mut idx,
+                            &mut s,
+                            &mut t,
+                            window[window.len() - 1],
+                            weight);
         }
     }
 }
 
 #[inline]
-fn add_single_edge(gir: &mut HsGIR, first_node: bool, compressed: Vec<CDC>, idx: &mut usize,
-                   source_vert: &mut Box<Vertex>, target_vert: &mut Box<Vertex>, last_char: u8) {
+fn add_single_edge<W: Weight>(gir: &mut HsGIR<W>, first_node: bool, compressed: Vec<CDC>,
+                              idx: &mut usize, source_vert: &mut Box<Vertex<W>>,
+                              target_vert: &mut Box<Vertex<W>>, last_char: u8, weight: W) {
     let mut insert = false;
     {
         let mut s = SEQUENCES.write();
-        s[0] = compressed.into_boxed_slice();
+        s.replace(0, &compressed);
     }
     // insert source on the first pass
     if first_node {
@@ -148,10 +204,8 @@ fn add_single_edge(gir: &mut HsGIR, first_node: bool, compressed: Vec<CDC>, idx:
             *source_vert = v.clone();
         }
         else {
-            let mut s = SEQUENCES.write();
-            let tmp = s[0].clone();
-            source_vert.ns = NodeSlice::new(2 * s.len());
-            s.push(tmp);
+            let offset = SEQUENCES.write().duplicate(0);
+            source_vert.ns = NodeSlice::new(2 * offset);
             insert = true;
             *idx += 1;
         }
@@ -168,8 +222,7 @@ fn add_single_edge(gir: &mut HsGIR, first_node: bool, compressed: Vec<CDC>, idx:
     else {
         let offset = if !insert {
             let mut s = SEQUENCES.write();
-            let tmp = s[0].clone();
-            s.push(tmp);
+            s.duplicate(0);
             2 * s.len() - 1
         }
         else {
@@ -183,100 +236,148 @@ fn add_single_edge(gir: &mut HsGIR, first_node: bool, compressed: Vec<CDC>, idx:
     if insert {
         gir.insert(target_vert.clone());
     }
-    create_or_modify_edge(&mut source_vert.edges.outgoing, target_vert.edges.idx, last_char);
+    create_or_modify_edge(&mut source_vert.edges.outgoing,
+                          target_vert.edges.idx,
+                          last_char,
+                          weight);
     gir.replace(source_vert.clone());
     *source_vert = target_vert.clone();
 }
 
-/// Create edge if it previously haven't existed, otherwise increase it's weight.
-pub fn create_or_modify_edge(edges: &mut Outgoing, to: Idx, last_char: u8) {
+/// Create edge with the given weight if it previously haven't existed,
+/// otherwise increase it's weight by the given weight.
+pub fn create_or_modify_edge<W: Weight>(edges: &mut Outgoing<W>, to: Idx, last_char: u8,
+                                        weight: W) {
     for i in edges.iter_mut() {
         if i.0 == to {
-            i.1 += 1;
+            i.1 = i.1.saturating_add(weight);
             return;
         }
     }
     let mut out_ = Vec::new();
     out_.extend_from_slice(edges);
-    out_.push((to, 1, last_char));
+    out_.push((to, weight, last_char));
     *edges = out_.into_boxed_slice();
 }
 
-impl Convert<HsGIR> for PtGraph {
-    fn create_from(mut h: HsGIR) -> Self {
-        let mut graph = PtGraph::default();
-        let mut s = SEQUENCES.write();
-        let mut fb = FixedBitSet::with_capacity(s.len());
-        for vertex in h.drain() {
-            let source = NodeIndex::new(vertex.edges.idx);
-            while source.index() >= graph.node_count() {
-                graph.add_node(());
-            }
-            let id = vertex.ns.idx();
-            if vertex.edges.outgoing.is_empty() {
-                // clear the underlying box as it will no longer be used. We
-                // can't pop it out of the global vector cause it would ruin our
-                // existing indices that are already in the graph.
-                s[id] = Box::new([]);
-                continue;
-            }
-            // at least one edge going out
-            let (target, weight, last_char) = vertex.edges.outgoing[0];
-            // if previous slice has different offset by 1, that means we need
-            // to push the current edge to the end of the SEQUENCES
-            let prev = fb.put(id);
-            let (slice, tmp) = if prev {
-                // this slice uses already taken slot with compressed edge - we
-                // can't link them both to the same id
-                let new_compressed = change_last_char_in_edge(&s[id], last_char);
-                s.push(new_compressed.clone().into_boxed_slice());
-                (EdgeSlice::new(s.len() - 1), new_compressed)
-            }
-            else {
-                // first edge slice will be pointing at the original place of source
-                // node, next edges will be appended to the global SEQUENCEs after
-                // having their last symbol changed
-                let tmp = kmer_to_edge(&s[id]);
-                s[id] = tmp.clone().into_boxed_slice();
-                (EdgeSlice::new(id), tmp)
-            };
-            while target >= graph.node_count() {
-                graph.add_node(());
+impl<W: Weight> Convert<HsGIR<W>> for WeightedPtGraph<W> {
+    fn create_from(h: HsGIR<W>) -> Self {
+        WeightedPtGraph::create_with_memory_from(h).0
+    }
+}
+
+impl<W: Weight> ConvertWithMemory<HsGIR<W>> for WeightedPtGraph<W> {
+    fn create_with_memory_from(mut h: HsGIR<W>) -> (Self, MemoryStats) {
+        info!("Starting conversion from GIR to graph");
+        let mut memory = MemoryStats::new(h.heap_size() + SEQUENCES.read().heap_size());
+        let mut nodes = h.iter().map(|v| v.ns).collect::<Vec<NodeSlice>>();
+        nodes.sort_by_key(|ns| ns.offset());
+        let mut remaining_edges = h.iter().map(|v| v.edges.outgoing.len()).sum::<usize>();
+        // edges are allocated as the GIR gives back its memory
+        let mut graph = WeightedPtGraph::with_capacity(h.len(), 0);
+        for _ in 0..h.len() {
+            graph.add_node(());
+        }
+        let mut fb = FixedBitSet::with_capacity(SEQUENCES.read().len());
+        for chunk in conversion_chunks(&nodes) {
+            // hashing a vertex reads SEQUENCES, so the chunk has to be taken
+            // out of the GIR before the write lock is acquired
+            let vertices = chunk.iter()
+                .map(|&ns| unwrap!(h.take(&Vertex::new(ns, Edges::empty(0)))))
+                .collect::<Vec<Box<Vertex<W>>>>();
+            // give back memory of the already converted vertices
+            if h.len() <= h.capacity() / 2 {
+                h.shrink_to_fit();
             }
-            graph.add_edge(source, NodeIndex::new(target), (slice, weight));
-            for edge in vertex.edges.outgoing.iter().skip(1) {
-                while edge.0 >= graph.node_count() {
-                    graph.add_node(());
-                }
-                let new_compressed = change_last_char_in_edge(&tmp, edge.2);
-                s.push(new_compressed.into_boxed_slice());
-                let slice = EdgeSlice::new(s.len() - 1);
-                graph.add_edge(source, NodeIndex::new(edge.0), (slice, edge.1));
+            graph.reserve_edges(vertices.iter().map(|v| v.edges.outgoing.len()).sum::<usize>());
+            let mut s = SEQUENCES.write();
+            for vertex in vertices {
+                remaining_edges -= vertex.edges.outgoing.len();
+                add_outgoing_edges(&mut graph,
+                                   &mut s,
+                                   &mut fb,
+                                   NodeIndex::new(vertex.edges.idx),
+                                   vertex.ns.idx(),
+                                   &vertex.edges.outgoing);
             }
-            // deallocate box such that it does not occupy memory
-            let raw = Box::into_raw(vertex);
-            deallocate(raw);
+            memory.update(table_size::<Box<Vertex<W>>>(h.capacity()) +
+                          h.len() * mem::size_of::<Vertex<W>>() +
+                          remaining_edges * mem::size_of::<Edge<W>>() +
+                          nodes.capacity() * mem::size_of::<NodeSlice>() +
+                          graph.heap_size() + s.heap_size());
         }
-        graph
+        // release space taken by the cleared nodes
+        let mut s = SEQUENCES.write();
+        s.compact();
+        memory.update(graph.heap_size() + s.heap_size());
+        info!("{}", memory);
+        (graph, memory)
+    }
+}
+
+/// Adds outgoing edges of the `GIR` node to the graph. Slot of the node in
+/// `SEQUENCES` is reused by the first edge, unless it was already taken.
+/// Nodes without outgoing edges are cleared.
+pub fn add_outgoing_edges<W: Weight>(graph: &mut WeightedPtGraph<W>, s: &mut Sequences,
+                                     fb: &mut FixedBitSet, source: NodeIndex, id: Idx,
+                                     outgoing: &[Edge<W>]) {
+    if outgoing.is_empty() {
+        // clear the underlying box as it will no longer be used. We
+        // can't pop it out of the global vector cause it would ruin our
+        // existing indices that are already in the graph.
+        s.clear_at(id);
+        return;
+    }
+    // at least one edge going out
+    let (target, weight, last_char) = outgoing[0];
+    // if previous slice has different offset by 1, that means we need
+    // to push the current edge to the end of the SEQUENCES
+    let prev = fb.put(id);
+    let (slice, tmp) = if prev {
+        // this slice uses already taken slot with compressed edge - we
+        // can't link them both to the same id
+        let new_compressed = change_last_char_in_edge(&s[id], last_char);
+        s.push(&new_compressed);
+        (EdgeSlice::new(s.len() - 1), new_compressed)
+    }
+    else {
+        // first edge slice will be pointing at the original place of source
+        // node, next edges will be appended to the global SEQUENCEs after
+        // having their last symbol changed
+        let tmp = kmer_to_edge(&s[id]);
+        s.replace(id, &tmp);
+        (EdgeSlice::new(id), tmp)
+    };
+    graph.add_edge(source, NodeIndex::new(target), (slice, weight));
+    for edge in outgoing.iter().skip(1) {
+        let new_compressed = change_last_char_in_edge(&tmp, edge.2);
+        s.push(&new_compressed);
+        let slice = EdgeSlice::new(s.len() - 1);
+        graph.add_edge(source, NodeIndex::new(edge.0), (slice, edge.1));
     }
 }
 
-// hack to deallocate `Vertex` (avoids unstable `heap` api)
-// for more information see https://github.com/rust-lang/rust/issues/27700
-fn deallocate<T>(ptr: *mut T) {
-    unsafe {
-        mem::drop(Vec::from_raw_parts(ptr, 0, 1));
+impl<W: Weight> ConvertCompacted<HsGIR<W>> for WeightedPtGraph<W> {
+    fn create_compacted_from(mut h: HsGIR<W>) -> Self {
+        info!("Starting compacted conversion from GIR to graph");
+        let mut nodes: GIRNodes<W> =
+            vec![(NodeSlice::default(), Box::new([]) as Outgoing<W>); h.len()];
+        for vertex in h.drain() {
+            let v = *vertex;
+            nodes[v.edges.idx] = (v.ns, v.edges.outgoing);
+        }
+        compacted_graph(nodes)
     }
 }
 
 /// Convenience wrapper around `HsGIR`, allows for a custom Debug trait implementation
-pub struct DebugHsGIR(pub HsGIR);
+pub struct DebugHsGIR<W = EdgeWeight>(pub HsGIR<W>);
 
 fn id<T>(x: T, _: T) -> T {
     x
 }
 
-impl fmt::Debug for DebugHsGIR {
+impl<W: Weight> fmt::Debug for DebugHsGIR<W> {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         self.0
             .iter()
@@ -6,14 +6,23 @@
 //! algorithmic efficiency
 pub mod hs_gir;
 pub mod hm_gir;
+pub mod vec_gir;
 pub mod edges;
+pub mod compacted;
 
 use algorithms::builder::Build;
 use collections::Graph;
-use stats::{Stats, CollectionStats};
+use slices::{BasicSlice, NodeSlice};
+use stats::{Stats, CollectionStats, MemoryStats};
+
+use std::cmp;
+
+/// Number of `GIR` nodes converted to the `Graph` before memory of the `GIR` is
+/// given back.
+pub const CONVERSION_CHUNK: usize = 4096;
 
 /// Graph's Intermediate Representation (GIR) interface.
-pub trait GIR: Build + Stats<CollectionStats> {}
+pub trait GIR: Build + Stats<CollectionStats<<Self as Build>::Weight>> {}
 
 /// Convert `GIR` to `Graph`.
 ///
@@ -27,3 +36,41 @@ pub trait Convert<T: GIR> {
     /// Create `Graph` from `GIR`.
     fn create_from(T) -> Self where Self: Graph;
 }
+
+/// Convert `GIR` to the compacted `Graph`.
+///
+/// Every maximal non-branching path of the `GIR` becomes a single edge of the
+/// `Graph`, so that there is no need to shrink it before the collapse. The
+/// conversion replaces all sequences in the global `SEQUENCES`, so no other
+/// collection may use them at the time.
+pub trait ConvertCompacted<T: GIR> {
+    /// Create compacted `Graph` from `GIR`.
+    fn create_compacted_from(T) -> Self where Self: Graph;
+}
+
+/// Convert `GIR` to `Graph`, tracking memory used by both collections.
+pub trait ConvertWithMemory<T: GIR>: Convert<T> {
+    /// Create `Graph` from `GIR` along with memory usage of the conversion.
+    fn create_with_memory_from(T) -> (Self, MemoryStats) where Self: Graph + Sized;
+}
+
+/// Splits nodes sorted by offset into chunks of about `CONVERSION_CHUNK`
+/// nodes.
+///
+/// Nodes sharing a sequence in `SEQUENCES` always land in the same chunk.
+/// Converting a chunk rewrites its sequences, so `GIR` lookups of the nodes
+/// that are still waiting for conversion have to keep hashing the original
+/// k-mers.
+pub fn conversion_chunks(nodes: &[NodeSlice]) -> Vec<&[NodeSlice]> {
+    let mut chunks = vec![];
+    let mut start = 0;
+    while start < nodes.len() {
+        let mut end = cmp::min(start + CONVERSION_CHUNK, nodes.len());
+        while end < nodes.len() && nodes[end].idx() == nodes[end - 1].idx() {
+            end += 1;
+        }
+        chunks.push(&nodes[start..end]);
+        start = end;
+    }
+    chunks
+}
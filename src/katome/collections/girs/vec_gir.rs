@@ -0,0 +1,363 @@
+//! Sort based Graph's Intermediate Representation
+//!
+//! `HmGIR` and `HsGIR` pay the overhead of the hash table for each node.
+//! `VecGIR` instead appends every compressed k-mer to a flat vector and, once
+//! the input is read, radix sorts and run-length counts them. Memory usage is
+//! then predictable - it depends only on the number of k-mers in the input,
+//! not on the load factor or the number of distinct k-mers.
+
+use algorithms::builder::{AddReads, Build, Init};
+use asm::SEQUENCES;
+use collections::{Convert, ConvertCompacted, GIR};
+use collections::girs::compacted::{GIRNodes, compacted_graph};
+use collections::girs::edges::Edge;
+use collections::graphs::pt_graph::{NodeIndex, WeightedPtGraph};
+use compress::{compress_kmer, decompress_node, kmer_to_edge};
+use config::InputFileType;
+use prelude::{CDC, EdgeWeight, K_SIZE, COMPRESSED_K1_SIZE, Weight};
+use slices::{BasicSlice, EdgeSlice, NodeSlice};
+use utils::reverse_complement_of;
+
+use std::cmp::Ordering;
+use std::mem;
+
+/// Sort based GIR.
+///
+/// Stores sorted, unique k-mers along with their weights and sorted, unique
+/// nodes ((k-1)-mers). Both are kept in the compressed form used for k-mers
+/// in `SEQUENCES`, i.e. as compressed source node followed by compressed
+/// target node.
+#[derive(Default, Clone, Debug)]
+pub struct VecGIR<W = EdgeWeight> {
+    kmers: Vec<CDC>,
+    weights: Vec<W>,
+    nodes: Vec<CDC>,
+}
+
+impl<W: Weight> GIR for VecGIR<W> {}
+
+impl<W: Weight> VecGIR<W> {
+    /// Gets number of nodes.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len() / node_size()
+    }
+
+    /// Gets number of edges.
+    pub fn edge_count(&self) -> usize {
+        self.weights.len()
+    }
+
+    /// Gets capacity of the underlying vectors, in records.
+    pub fn capacity(&self) -> usize {
+        self.kmers.capacity() / kmer_size()
+    }
+
+    /// Creates GIR out of collected k-mers.
+    fn from_kmers(collector: KmerCollector<W>) -> VecGIR<W> {
+        let (kmers, weights) = collector.count();
+        let size = kmer_size();
+        // sources are already sorted, targets need to be sorted separately
+        let nsize = node_size();
+        let mut targets = Vec::with_capacity(weights.len() * nsize);
+        for kmer in kmers.chunks(size) {
+            targets.extend_from_slice(&kmer[nsize..]);
+        }
+        let mut no_weights: Vec<W> = vec![];
+        radix_sort(&mut targets, nsize, &mut no_weights);
+        let mut nodes = Vec::with_capacity(targets.len());
+        {
+            let mut sources = kmers.chunks(size).map(|k| &k[..nsize]).peekable();
+            let mut targets = targets.chunks(nsize).peekable();
+            // merge sorted sources and targets, skipping duplicates
+            loop {
+                let next = match (sources.peek().cloned(), targets.peek().cloned()) {
+                    (Some(s), Some(t)) => {
+                        if s <= t {
+                            sources.next();
+                        }
+                        if t <= s {
+                            targets.next();
+                        }
+                        if s <= t { s } else { t }
+                    }
+                    (Some(s), None) => {
+                        sources.next();
+                        s
+                    }
+                    (None, Some(t)) => {
+                        targets.next();
+                        t
+                    }
+                    (None, None) => break,
+                };
+                if nodes.len() < nsize || &nodes[nodes.len() - nsize..] != next {
+                    nodes.extend_from_slice(next);
+                }
+            }
+        }
+        nodes.shrink_to_fit();
+        info!("GIR has {} unique k-mers and {} nodes",
+              weights.len(),
+              nodes.len() / nsize);
+        VecGIR {
+            kmers: kmers,
+            weights: weights,
+            nodes: nodes,
+        }
+    }
+
+    // Gets position of the node in the sorted nodes.
+    fn node_position(&self, node: &[CDC]) -> usize {
+        let nsize = node_size();
+        let (mut low, mut high) = (0, self.node_count());
+        while low < high {
+            let mid = (low + high) / 2;
+            match self.nodes[mid * nsize..(mid + 1) * nsize].cmp(node) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return mid,
+            }
+        }
+        panic!("Node is missing in the GIR");
+    }
+
+    // Gets source, target and the last symbol of every edge, sorted by source.
+    fn edges(&self) -> Vec<(usize, usize, W, u8)> {
+        let (size, nsize) = (kmer_size(), node_size());
+        self.kmers
+            .chunks(size)
+            .zip(self.weights.iter())
+            .map(|(kmer, &weight)| {
+                let target = &kmer[nsize..];
+                let last_char = *unwrap!(decompress_node(target).last());
+                (self.node_position(&kmer[..nsize]), self.node_position(target), weight, last_char)
+            })
+            .collect()
+    }
+}
+
+#[inline]
+fn kmer_size() -> usize {
+    2 * node_size()
+}
+
+#[inline]
+fn node_size() -> usize {
+    unsafe { COMPRESSED_K1_SIZE }
+}
+
+/// LSD radix sort of the fixed size records. `weights` are permuted along with
+/// records, unless they are empty.
+pub fn radix_sort<W: Weight>(records: &mut Vec<CDC>, size: usize, weights: &mut Vec<W>) {
+    if size == 0 {
+        return;
+    }
+    let n = records.len() / size;
+    let weighted = !weights.is_empty();
+    let mut buffer = vec![0; records.len()];
+    let mut weights_buffer = if weighted { vec![W::zero(); n] } else { vec![] };
+    for byte in (0..size).rev() {
+        let mut counts = [0_usize; 257];
+        for r in 0..n {
+            counts[records[r * size + byte] as usize + 1] += 1;
+        }
+        // all records have the same byte at this position
+        if counts.iter().any(|&c| c == n) {
+            continue;
+        }
+        for i in 0..256 {
+            counts[i + 1] += counts[i];
+        }
+        for r in 0..n {
+            let b = records[r * size + byte] as usize;
+            let position = counts[b];
+            counts[b] += 1;
+            buffer[position * size..(position + 1) * size]
+                .copy_from_slice(&records[r * size..(r + 1) * size]);
+            if weighted {
+                weights_buffer[position] = weights[r];
+            }
+        }
+        mem::swap(records, &mut buffer);
+        if weighted {
+            mem::swap(weights, &mut weights_buffer);
+        }
+    }
+}
+
+/// Merges consecutive equal records of the sorted vector. Weights of the
+/// records are summed, if there are no weights each record weights 1.
+fn run_length_count<W: Weight>(records: Vec<CDC>, size: usize, weights: Vec<W>)
+                               -> (Vec<CDC>, Vec<W>) {
+    let mut unique = Vec::with_capacity(records.len());
+    let mut counts: Vec<W> = Vec::with_capacity(records.len() / size);
+    for (r, record) in records.chunks(size).enumerate() {
+        let weight = if weights.is_empty() { W::one() } else { weights[r] };
+        if !unique.is_empty() && &unique[unique.len() - size..] == record {
+            let last = unwrap!(counts.last_mut());
+            *last = last.saturating_add(weight);
+        }
+        else {
+            unique.extend_from_slice(record);
+            counts.push(weight);
+        }
+    }
+    unique.shrink_to_fit();
+    counts.shrink_to_fit();
+    (unique, counts)
+}
+
+/// Gathers compressed k-mers during the reading of the input, `Builder` of
+/// `VecGIR`.
+#[derive(Default)]
+pub struct KmerCollector<W = EdgeWeight> {
+    kmers: Vec<CDC>,
+    // weights of the k-mers, empty for the FASTA/FASTQ input
+    weights: Vec<W>,
+}
+
+impl<W: Weight> KmerCollector<W> {
+    /// Sorts collected k-mers and merges the duplicates, summing their
+    /// weights. Returns sorted, unique compressed k-mers along with their
+    /// weights.
+    pub fn count(self) -> (Vec<CDC>, Vec<W>) {
+        let KmerCollector { mut kmers, mut weights } = self;
+        let size = kmer_size();
+        info!("Sorting {} k-mers", kmers.len() / size);
+        radix_sort(&mut kmers, size, &mut weights);
+        run_length_count(kmers, size, weights)
+    }
+}
+
+impl<W: Weight> Init for KmerCollector<W> {
+    fn init(edges_count: Option<usize>, _nodes_count: Option<usize>, ft: InputFileType)
+            -> KmerCollector<W> {
+        let edges = edges_count.unwrap_or(0);
+        KmerCollector {
+            kmers: Vec::with_capacity(edges * kmer_size()),
+            weights: match ft {
+                InputFileType::BFCounter => Vec::with_capacity(edges),
+                _ => vec![],
+            },
+        }
+    }
+}
+
+impl<W: Weight> AddReads for KmerCollector<W> {
+    type Weight = W;
+
+    fn add_read_fastaq(&mut self, read: &[u8], reverse_complement: bool) {
+        assert!(read.len() >= unsafe { K_SIZE }, "Read is too short!");
+        for window in read.windows(unsafe { K_SIZE }) {
+            self.kmers.extend(compress_kmer(window));
+        }
+        if reverse_complement {
+            let rc = reverse_complement_of(read);
+            for window in rc.windows(unsafe { K_SIZE }) {
+                self.kmers.extend(compress_kmer(window));
+            }
+        }
+        if !self.weights.is_empty() {
+            let count = self.kmers.len() / kmer_size();
+            self.weights.resize(count, W::one());
+        }
+    }
+
+    fn add_read_bfc(&mut self, read: &[u8], weight: W, reverse_complement: bool) {
+        assert!(read.len() >= unsafe { K_SIZE }, "Read is too short!");
+        // k-mers of reads added before weigh 1
+        let count = self.kmers.len() / kmer_size();
+        self.weights.resize(count, W::one());
+        self.kmers.extend(compress_kmer(read));
+        self.weights.push(weight);
+        if reverse_complement {
+            self.kmers.extend(compress_kmer(&reverse_complement_of(read)));
+            self.weights.push(weight);
+        }
+    }
+}
+
+impl<W: Weight> Build for VecGIR<W> {
+    type Weight = W;
+    type Builder = KmerCollector<W>;
+
+    fn from_builder(collector: KmerCollector<W>) -> VecGIR<W> {
+        VecGIR::from_kmers(collector)
+    }
+}
+
+impl<W: Weight> Convert<VecGIR<W>> for WeightedPtGraph<W> {
+    fn create_from(gir: VecGIR<W>) -> Self {
+        info!("Starting conversion from GIR to graph");
+        let edges = gir.edges();
+        let mut graph = WeightedPtGraph::with_capacity(gir.node_count(), edges.len());
+        for _ in 0..gir.node_count() {
+            graph.add_node(());
+        }
+        let mut s = SEQUENCES.write();
+        s.clear();
+        s.push(&[]);
+        for (kmer, &(source, target, weight, _)) in gir.kmers.chunks(kmer_size()).zip(&edges) {
+            let id = s.push(&kmer_to_edge(kmer));
+            graph.add_edge(NodeIndex::new(source),
+                           NodeIndex::new(target),
+                           (EdgeSlice::new(id), weight));
+        }
+        s.shrink_to_fit();
+        graph
+    }
+}
+
+impl<W: Weight> ConvertCompacted<VecGIR<W>> for WeightedPtGraph<W> {
+    fn create_compacted_from(gir: VecGIR<W>) -> Self {
+        info!("Starting compacted conversion from GIR to graph");
+        let mut nodes: GIRNodes<W> = vec![(NodeSlice::default(), Box::new([]) as Box<[Edge<W>]>);
+                                          gir.node_count()];
+        let mut outgoing: Vec<Vec<Edge<W>>> = vec![vec![]; gir.node_count()];
+        {
+            let mut s = SEQUENCES.write();
+            s.clear();
+            s.push(&[]);
+            // k-mers are pushed to the SEQUENCES, so that nodes can refer to
+            // them with `NodeSlice`s
+            let nsize = node_size();
+            for (n, node) in gir.nodes.chunks(nsize).enumerate() {
+                let mut kmer = Vec::with_capacity(2 * nsize);
+                kmer.extend_from_slice(node);
+                kmer.extend_from_slice(node);
+                nodes[n].0 = NodeSlice::new(2 * s.push(&kmer));
+            }
+        }
+        for (source, target, weight, last_char) in gir.edges() {
+            outgoing[source].push((target, weight, last_char));
+        }
+        drop(gir);
+        for (node, edges) in nodes.iter_mut().zip(outgoing.into_iter()) {
+            node.1 = edges.into_boxed_slice();
+        }
+        compacted_graph(nodes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{radix_sort, run_length_count};
+    use prelude::EdgeWeight;
+
+    #[test]
+    fn sorts_records() {
+        let mut records = vec![3, 1, 0, 2, 3, 0, 0, 2];
+        let mut weights: Vec<EdgeWeight> = vec![1, 2, 3, 4];
+        radix_sort(&mut records, 2, &mut weights);
+        assert_eq!(records, vec![0, 2, 0, 2, 3, 0, 3, 1]);
+        assert_eq!(weights, vec![2, 4, 3, 1]);
+    }
+
+    #[test]
+    fn counts_equal_records() {
+        let (records, weights) =
+            run_length_count::<EdgeWeight>(vec![0, 2, 0, 2, 3, 0], 2, vec![]);
+        assert_eq!(records, vec![0, 2, 3, 0]);
+        assert_eq!(weights, vec![2, 1]);
+    }
+}
@@ -0,0 +1,693 @@
+//! Succinct (BOSS-style) de Bruijn graph.
+//!
+//! Representation follows Bowe, Onodera, Sadakane and Shibuya "Succinct de
+//! Bruijn Graphs". Nodes ((k-1)-mers) are sorted in the colexicographic order,
+//! i.e. by their reversed labels, and edges are sorted by their sources and
+//! then by their labels (last symbols of the k-mers). Neither nodes nor the
+//! adjacency are stored explicitly:
+//!
+//! * outgoing degrees of consecutive nodes are written in unary into a single
+//!   bit vector (`0` per edge, `1` closing each node),
+//! * labels of the edges are kept as one bit vector per symbol,
+//! * edges which are the first (in the edge order) ones with the given label
+//!   entering their target are marked in one bit vector per symbol,
+//! * nodes with at least one incoming edge are marked in a bit vector.
+//!
+//! Targets of the edges labelled `c` appear in the node order exactly in the
+//! order of the first edges labelled `c`, among the nodes ending with `c`, so
+//! both outgoing and incoming neighbours are found with rank and select
+//! queries on these vectors. Topology takes roughly 10 bits per edge, instead
+//! of several machine words per edge and node of the `PtGraph` or `GIR`s.
+//!
+//! Labels of the nodes aren't stored either, they are recovered symbol by
+//! symbol by following incoming edges backwards. Only nodes without incoming
+//! edges keep their labels, compressed, so `BossGraph` doesn't use
+//! `SEQUENCES` at all. Just like `CsrGraph`, `BossGraph` is read-only and
+//! implements only `Graph`.
+
+use algorithms::builder::Build;
+use collections::girs::vec_gir::{KmerCollector, radix_sort};
+use collections::graphs::{EdgeDirection, Graph, Id};
+use compress::{CHARS_PER_CARRIER, compress_node, decompress_kmer, decompress_node};
+use prelude::{CDC, COMPRESSED_K1_SIZE, EdgeWeight, K_SIZE, Weight};
+
+use fixedbitset::FixedBitSet;
+
+use std::cmp;
+use std::cmp::Ordering;
+use std::u32;
+
+/// Size of the alphabet.
+const SIGMA: usize = 4;
+// Symbols of the alphabet, in the order of their ranks.
+const SYMBOLS: &'static [u8; SIGMA] = b"ACGT";
+// Number of bits covered by a single precomputed rank.
+const BITS_PER_RANK: usize = 512;
+
+/// Type denoting index of node in `BossGraph`.
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BossNodeIndex(u32);
+
+/// Type denoting index of edge in `BossGraph`.
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BossEdgeIndex(u32);
+
+impl Id for BossNodeIndex {
+    #[inline]
+    fn new(index: usize) -> BossNodeIndex {
+        BossNodeIndex(index as u32)
+    }
+
+    #[inline]
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Id for BossEdgeIndex {
+    #[inline]
+    fn new(index: usize) -> BossEdgeIndex {
+        BossEdgeIndex(index as u32)
+    }
+
+    #[inline]
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Bit vector supporting rank and select queries.
+///
+/// Ranks are precomputed for every `BITS_PER_RANK` bits, the remainder is
+/// counted with `count_ones`, exactly like regions in the `PtGraph` builder.
+#[derive(Clone)]
+struct RankSelect {
+    bits: FixedBitSet,
+    ranks: Vec<u32>,
+}
+
+impl RankSelect {
+    fn new(bits: FixedBitSet) -> RankSelect {
+        let mut ranks = vec![0];
+        let mut total = 0;
+        let mut start = 0;
+        while start < bits.len() {
+            let end = cmp::min(start + BITS_PER_RANK, bits.len());
+            total += bits.count_ones(start..end) as u32;
+            ranks.push(total);
+            start = end;
+        }
+        RankSelect {
+            bits: bits,
+            ranks: ranks,
+        }
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    #[inline]
+    fn get(&self, i: usize) -> bool {
+        self.bits.contains(i)
+    }
+
+    /// Number of ones in range `0..i`.
+    fn rank1(&self, i: usize) -> usize {
+        let block = i / BITS_PER_RANK;
+        let start = block * BITS_PER_RANK;
+        let mut rank = self.ranks[block] as usize;
+        if start < i {
+            rank += self.bits.count_ones(start..i);
+        }
+        rank
+    }
+
+    /// Position of the one with the given rank (counting from 0).
+    fn select1(&self, rank: usize) -> Option<usize> {
+        self.select(rank, |block| self.ranks[block] as usize, true)
+    }
+
+    /// Position of the zero with the given rank (counting from 0).
+    fn select0(&self, rank: usize) -> Option<usize> {
+        let zeros = |b: usize| cmp::min(b * BITS_PER_RANK, self.len()) - self.ranks[b] as usize;
+        self.select(rank, zeros, false)
+    }
+
+    fn select<F: Fn(usize) -> usize>(&self, rank: usize, count_before: F, bit: bool)
+                                     -> Option<usize> {
+        let blocks = self.ranks.len() - 1;
+        if count_before(blocks) <= rank {
+            return None;
+        }
+        // find the last block with less than `rank + 1` matching bits before it
+        let (mut low, mut high) = (0, blocks);
+        while high - low > 1 {
+            let mid = (low + high) / 2;
+            if count_before(mid) <= rank {
+                low = mid;
+            }
+            else {
+                high = mid;
+            }
+        }
+        let mut remaining = rank - count_before(low);
+        for i in low * BITS_PER_RANK..self.len() {
+            if self.get(i) == bit {
+                if remaining == 0 {
+                    return Some(i);
+                }
+                remaining -= 1;
+            }
+        }
+        unreachable!()
+    }
+}
+
+/// Succinct de Bruijn graph.
+#[derive(Clone)]
+pub struct BossGraph<W = EdgeWeight> {
+    // unary encoded outgoing degrees of the nodes
+    degrees: RankSelect,
+    // edges labelled with the given symbol
+    labels: Vec<RankSelect>,
+    // first edges labelled with the given symbol which enter their targets
+    firsts: Vec<RankSelect>,
+    // nodes with at least one incoming edge
+    has_incoming: RankSelect,
+    // index of the first node ending with the given symbol
+    counts: [usize; SIGMA + 1],
+    // compressed labels of the nodes without incoming edges, in the node order
+    tips: Vec<CDC>,
+    weights: Vec<W>,
+}
+
+impl<W> Default for BossGraph<W> {
+    fn default() -> BossGraph<W> {
+        BossGraph {
+            degrees: RankSelect::new(FixedBitSet::with_capacity(0)),
+            labels: vec![RankSelect::new(FixedBitSet::with_capacity(0)); SIGMA],
+            firsts: vec![RankSelect::new(FixedBitSet::with_capacity(0)); SIGMA],
+            has_incoming: RankSelect::new(FixedBitSet::with_capacity(0)),
+            counts: [0; SIGMA + 1],
+            tips: vec![],
+            weights: vec![],
+        }
+    }
+}
+
+impl<W: Weight> BossGraph<W> {
+    /// Creates graph out of the collected k-mers. K-mers don't have to be
+    /// unique, weights of the duplicates are summed.
+    fn from_kmers(collector: KmerCollector<W>) -> BossGraph<W> {
+        let (mut keys, mut weights) = collector.count();
+        let edge_count = weights.len();
+        assert!(edge_count < u32::MAX as usize, "Graph is too big to be stored as BossGraph");
+        let k = unsafe { K_SIZE };
+        let record_size = 2 * unsafe { COMPRESSED_K1_SIZE };
+        let key_size = (k + CHARS_PER_CARRIER - 1) / CHARS_PER_CARRIER;
+        // rewrite k-mers in place into the keys: reversed label of the source
+        // followed by the last symbol, so that sorting them gives the edge order
+        let mut key = Vec::with_capacity(k);
+        let mut compressed = Vec::with_capacity(key_size);
+        for i in 0..edge_count {
+            let kmer = decompress_kmer(&keys[i * record_size..(i + 1) * record_size]);
+            key.clear();
+            key.extend(kmer[..k - 1].iter().rev());
+            key.push(kmer[k - 1]);
+            compressed.clear();
+            compress_node(&key, &mut compressed);
+            keys[i * key_size..(i + 1) * key_size].copy_from_slice(&compressed);
+        }
+        keys.truncate(edge_count * key_size);
+        keys.shrink_to_fit();
+        radix_sort(&mut keys, key_size, &mut weights);
+        let keys = Keys {
+            keys: keys,
+            size: key_size,
+            k: k,
+        };
+        let mut degrees = FixedBitSet::with_capacity(edge_count);
+        let mut labels = vec![FixedBitSet::with_capacity(edge_count); SIGMA];
+        let mut firsts = vec![FixedBitSet::with_capacity(edge_count); SIGMA];
+        let mut has_incoming = FixedBitSet::with_capacity(0);
+        let mut counts = [0; SIGMA + 1];
+        let mut tips = vec![];
+        // Nodes are the sources and the targets of the edges. Sources are
+        // sorted along with the edges, targets of the edges with the same
+        // label are sorted in the edge order, so both are merged in a single
+        // pass.
+        let mut edge = 0;
+        let mut c = 0;
+        let mut target = keys.next_target(&mut c, 0);
+        if let Some(t) = target {
+            firsts[c].insert(t);
+        }
+        let mut node = 0;
+        loop {
+            let order = match target {
+                Some(t) if edge < edge_count => keys.cmp_source_target(edge, t),
+                None if edge < edge_count => Ordering::Less,
+                Some(_) => Ordering::Greater,
+                None => break,
+            };
+            let first_symbol = if order == Ordering::Greater {
+                c
+            }
+            else {
+                keys.symbol(edge, 0)
+            };
+            counts[first_symbol + 1] += 1;
+            has_incoming.grow(node + 1);
+            if order == Ordering::Less {
+                // label of the node can't be recovered from its predecessors
+                let label = (0..k - 1)
+                    .rev()
+                    .map(|i| SYMBOLS[keys.symbol(edge, i)])
+                    .collect::<Vec<u8>>();
+                compress_node(&label, &mut tips);
+            }
+            else {
+                has_incoming.insert(node);
+                // skip edges entering the same node
+                let t = unwrap!(target);
+                target = keys.next_target(&mut c, t + 1);
+                while let Some(e) = target {
+                    if keys.cmp_targets(e, t) != Ordering::Equal {
+                        firsts[c].insert(e);
+                        break;
+                    }
+                    target = keys.next_target(&mut c, e + 1);
+                }
+            }
+            if order != Ordering::Greater {
+                // outgoing edges of the node are consecutive
+                let source = edge;
+                while edge < edge_count && keys.cmp_sources(edge, source) == Ordering::Equal {
+                    labels[keys.symbol(edge, k - 1)].insert(edge);
+                    edge += 1;
+                }
+            }
+            // close the node
+            degrees.grow(node + edge + 1);
+            degrees.insert(node + edge);
+            node += 1;
+        }
+        drop(keys);
+        assert!(node < u32::MAX as usize, "Graph is too big to be stored as BossGraph");
+        for c in 0..SIGMA {
+            counts[c + 1] += counts[c];
+        }
+        tips.shrink_to_fit();
+        info!("Succinct graph has {} nodes and {} edges", node, edge_count);
+        BossGraph {
+            degrees: RankSelect::new(degrees),
+            labels: labels.into_iter().map(RankSelect::new).collect(),
+            firsts: firsts.into_iter().map(RankSelect::new).collect(),
+            has_incoming: RankSelect::new(has_incoming),
+            counts: counts,
+            tips: tips,
+            weights: weights,
+        }
+    }
+
+    /// Finds edge representing the given k-mer.
+    pub fn find_kmer(&self, kmer: &[u8]) -> Option<BossEdgeIndex> {
+        let k = unsafe { K_SIZE };
+        assert_eq!(kmer.len(), k, "Only k-mers can be searched for");
+        let source = &kmer[..k - 1];
+        // binary search over nodes in the colexicographic order
+        let (mut low, mut high) = (0, self.node_count());
+        while low < high {
+            let mid = (low + high) / 2;
+            match colex_cmp(&self.node_label(mid), source) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => {
+                    let c = symbol_rank(kmer[k - 1]);
+                    return self.out_edges(mid)
+                        .find(|&e| self.labels[c].get(e))
+                        .map(BossEdgeIndex::new);
+                }
+            }
+        }
+        None
+    }
+
+    /// Checks if the graph contains given k-mer.
+    pub fn contains(&self, kmer: &[u8]) -> bool {
+        self.find_kmer(kmer).is_some()
+    }
+
+    /// Gets number of bits used by the topology of the graph.
+    pub fn topology_size(&self) -> usize {
+        self.degrees.len() + self.has_incoming.len() +
+        self.labels.iter().chain(self.firsts.iter()).map(|l| l.len()).sum::<usize>()
+    }
+
+    #[inline]
+    fn out_edges(&self, node: usize) -> ::std::ops::Range<usize> {
+        let start = if node == 0 { 0 } else { unwrap!(self.degrees.select1(node - 1)) + 1 - node };
+        start..unwrap!(self.degrees.select1(node)) - node
+    }
+
+    // Edges entering the node are consecutive among the edges with its last
+    // symbol, the first of them is marked in `firsts`.
+    fn first_in_edge(&self, node: usize) -> Option<usize> {
+        if !self.has_incoming.get(node) {
+            return None;
+        }
+        let c = self.last_symbol(node);
+        let j = self.has_incoming.rank1(node) - self.has_incoming.rank1(self.counts[c]);
+        self.firsts[c].select1(j)
+    }
+
+    fn in_edges(&self, node: usize) -> Vec<usize> {
+        let first = match self.first_in_edge(node) {
+            Some(e) => e,
+            None => return vec![],
+        };
+        let c = self.last_symbol(node);
+        let mut edges = vec![first];
+        // remaining edges with the same label, up to the next first one
+        let mut rank = self.labels[c].rank1(first) + 1;
+        while let Some(e) = self.labels[c].select1(rank) {
+            if self.firsts[c].get(e) {
+                break;
+            }
+            edges.push(e);
+            rank += 1;
+        }
+        edges
+    }
+
+    fn source(&self, edge: usize) -> usize {
+        self.degrees.rank1(unwrap!(self.degrees.select0(edge)))
+    }
+
+    fn target(&self, edge: usize) -> usize {
+        let c = self.label(edge);
+        let j = self.firsts[c].rank1(edge + 1) - 1;
+        unwrap!(self.has_incoming.select1(self.has_incoming.rank1(self.counts[c]) + j))
+    }
+
+    // Rank of the symbol labelling the edge.
+    fn label(&self, edge: usize) -> usize {
+        unwrap!((0..SIGMA).find(|&c| self.labels[c].get(edge)))
+    }
+
+    // Symbol which ends labels of the node.
+    fn last_symbol(&self, node: usize) -> usize {
+        unwrap!((0..SIGMA).rev().find(|&c| self.counts[c] <= node))
+    }
+
+    // Label is read backwards, each node preceding the current one on the
+    // incoming edge adds the symbol which ends its own label. Walk stops at
+    // the node without incoming edges, which label is stored.
+    fn node_label(&self, node: usize) -> Vec<u8> {
+        let k1 = unsafe { K_SIZE } - 1;
+        let mut label = vec![0; k1];
+        let mut node = node;
+        for i in (0..k1).rev() {
+            match self.first_in_edge(node) {
+                Some(e) => {
+                    label[i] = SYMBOLS[self.last_symbol(node)];
+                    node = self.source(e);
+                }
+                None => {
+                    // labels of the nodes share suffix and prefix of this length
+                    let tip = node - self.has_incoming.rank1(node);
+                    let size = unsafe { COMPRESSED_K1_SIZE };
+                    let stored = decompress_node(&self.tips[tip * size..(tip + 1) * size]);
+                    label[..i + 1].copy_from_slice(&stored[k1 - 1 - i..]);
+                    break;
+                }
+            }
+        }
+        label
+    }
+}
+
+#[inline]
+fn symbol_rank(symbol: u8) -> usize {
+    match symbol {
+        b'A' => 0,
+        b'C' => 1,
+        b'G' => 2,
+        b'T' => 3,
+        s => panic!("Unsupported symbol {}", s as char),
+    }
+}
+
+/// Compares sequences by their reversed forms.
+fn colex_cmp(a: &[u8], b: &[u8]) -> Ordering {
+    a.iter().rev().cmp(b.iter().rev())
+}
+
+// Sorted keys of the edges used during the creation of the graph. Key is the
+// reversed label of the source followed by the label of the edge, so the
+// reversed label of the target is the label of the edge followed by the
+// key without its last two symbols.
+struct Keys {
+    keys: Vec<CDC>,
+    size: usize,
+    k: usize,
+}
+
+impl Keys {
+    fn len(&self) -> usize {
+        self.keys.len() / self.size
+    }
+
+    // Rank of the i-th symbol of the key of the edge.
+    #[inline]
+    fn symbol(&self, edge: usize, i: usize) -> usize {
+        let carrier = self.keys[edge * self.size + i / CHARS_PER_CARRIER];
+        let shift = 2 * (CHARS_PER_CARRIER - 1 - i % CHARS_PER_CARRIER);
+        ((carrier >> shift) & 3) as usize
+    }
+
+    #[inline]
+    fn target_symbol(&self, edge: usize, i: usize) -> usize {
+        if i == 0 {
+            self.symbol(edge, self.k - 1)
+        }
+        else {
+            self.symbol(edge, i - 1)
+        }
+    }
+
+    fn cmp_sources(&self, a: usize, b: usize) -> Ordering {
+        let k1 = self.k - 1;
+        (0..k1).map(|i| self.symbol(a, i)).cmp((0..k1).map(|i| self.symbol(b, i)))
+    }
+
+    fn cmp_targets(&self, a: usize, b: usize) -> Ordering {
+        let k1 = self.k - 1;
+        (0..k1).map(|i| self.target_symbol(a, i)).cmp((0..k1).map(|i| self.target_symbol(b, i)))
+    }
+
+    fn cmp_source_target(&self, source: usize, target: usize) -> Ordering {
+        let k1 = self.k - 1;
+        (0..k1)
+            .map(|i| self.symbol(source, i))
+            .cmp((0..k1).map(|i| self.target_symbol(target, i)))
+    }
+
+    // Finds the first edge, starting from `from`, labelled `c`. If there is
+    // none, the following labels are searched from the first edge.
+    fn next_target(&self, c: &mut usize, from: usize) -> Option<usize> {
+        let mut from = from;
+        while *c < SIGMA {
+            let label = *c;
+            if let Some(e) = (from..self.len()).find(|&e| self.symbol(e, self.k - 1) == label) {
+                return Some(e);
+            }
+            *c += 1;
+            from = 0;
+        }
+        None
+    }
+}
+
+impl<W: Weight> Graph for BossGraph<W> {
+    type NodeIdentifier = BossNodeIndex;
+    type EdgeIdentifier = BossEdgeIndex;
+
+    #[inline]
+    fn node_count(&self) -> usize {
+        self.has_incoming.len()
+    }
+
+    #[inline]
+    fn edge_count(&self) -> usize {
+        self.weights.len()
+    }
+
+    fn out_degree(&self, node: BossNodeIndex) -> usize {
+        self.out_edges(node.index()).len()
+    }
+
+    fn in_degree(&self, node: BossNodeIndex) -> usize {
+        self.in_edges(node.index()).len()
+    }
+
+    fn fill_edges(&self, node: BossNodeIndex, direction: EdgeDirection,
+                  edges: &mut Vec<BossEdgeIndex>) {
+        edges.clear();
+        match direction {
+            EdgeDirection::Outgoing => {
+                edges.extend(self.out_edges(node.index()).map(BossEdgeIndex::new))
+            }
+            EdgeDirection::Incoming => {
+                edges.extend(self.in_edges(node.index()).into_iter().map(BossEdgeIndex::new))
+            }
+        }
+    }
+
+    fn first_edge(&self, node: BossNodeIndex, direction: EdgeDirection) -> Option<BossEdgeIndex> {
+        self.edges_of(node, direction).first().cloned()
+    }
+
+    fn find_edge(&self, source: BossNodeIndex, target: BossNodeIndex) -> Option<BossEdgeIndex> {
+        self.out_edges(source.index())
+            .find(|&e| self.target(e) == target.index())
+            .map(BossEdgeIndex::new)
+    }
+
+    fn edge_endpoints(&self, edge: BossEdgeIndex) -> Option<(BossNodeIndex, BossNodeIndex)> {
+        if edge.index() >= self.edge_count() {
+            return None;
+        }
+        Some((BossNodeIndex::new(self.source(edge.index())),
+              BossNodeIndex::new(self.target(edge.index()))))
+    }
+
+    #[inline]
+    fn weight(&self, edge: BossEdgeIndex) -> Option<W> {
+        self.weights.get(edge.index()).cloned()
+    }
+
+    #[inline]
+    fn edge_sequence(&self, edge: BossEdgeIndex) -> Option<Vec<u8>> {
+        if edge.index() >= self.edge_count() {
+            return None;
+        }
+        let mut sequence = self.node_label(self.source(edge.index()));
+        sequence.push(SYMBOLS[self.label(edge.index())]);
+        Some(sequence)
+    }
+}
+
+impl<W: Weight> Build for BossGraph<W> {
+    type Weight = W;
+    type Builder = KmerCollector<W>;
+
+    fn from_builder(collector: KmerCollector<W>) -> BossGraph<W> {
+        BossGraph::from_kmers(collector)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::RankSelect;
+    use algorithms::builder::AddReads;
+    use asm::SEQUENCES;
+    use asm::lock::LOCK;
+
+    // deterministic pseudo-random read
+    fn read(length: usize, seed: u64) -> Vec<u8> {
+        let mut x = seed;
+        (0..length)
+            .map(|_| {
+                x = x.wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                b"ACGT"[(x >> 62) as usize]
+            })
+            .collect()
+    }
+
+    fn graph(reads: &[Vec<u8>]) -> BossGraph {
+        let mut collector = KmerCollector::<EdgeWeight>::default();
+        for r in reads {
+            collector.add_read_fastaq(r, false);
+        }
+        BossGraph::from_builder(collector)
+    }
+
+    #[test]
+    fn ranks_and_selects() {
+        let mut bits = FixedBitSet::with_capacity(2000);
+        for i in (0..2000).filter(|i| i % 3 == 0) {
+            bits.insert(i);
+        }
+        let rs = RankSelect::new(bits);
+        assert_eq!(rs.rank1(0), 0);
+        assert_eq!(rs.rank1(1000), 334);
+        assert_eq!(rs.select1(334), Some(1002));
+        assert_eq!(rs.select0(1), Some(2));
+        assert_eq!(rs.select0(700), Some(1051));
+        assert_eq!(rs.select1(667), None);
+    }
+
+    #[test]
+    fn finds_kmers() {
+        let _l = LOCK.lock().unwrap();
+        let k = unsafe { K_SIZE };
+        let reads = vec![read(100, 1), read(80, 2)];
+        let g = graph(&reads);
+        for r in &reads {
+            for w in r.windows(k) {
+                assert!(g.contains(w));
+            }
+        }
+        let mut missing = reads[0][..k].to_vec();
+        missing[k - 1] = if missing[k - 1] == b'A' { b'C' } else { b'A' };
+        if !reads.iter().any(|r| r.windows(k).any(|w| w == &missing[..])) {
+            assert!(!g.contains(&missing));
+        }
+    }
+
+    #[test]
+    fn navigates_edges() {
+        let _l = LOCK.lock().unwrap();
+        let k = unsafe { K_SIZE };
+        // second read shares the middle of the first one, creating branches
+        let first = read(120, 3);
+        let mut second = read(20, 4);
+        second.extend_from_slice(&first[30..90]);
+        second.extend(read(20, 5));
+        let g = graph(&[first.clone(), second]);
+        for e in 0..g.edge_count() {
+            let edge = BossEdgeIndex::new(e);
+            let name = unwrap!(g.edge_sequence(edge));
+            let (source, target) = unwrap!(g.edge_endpoints(edge));
+            assert_eq!(g.node_label(source.index()), name[..k - 1].to_vec());
+            assert_eq!(g.node_label(target.index()), name[1..].to_vec());
+            assert!(g.edges_of(target, EdgeDirection::Incoming).contains(&edge));
+            assert_eq!(g.find_edge(source, target), Some(edge));
+        }
+        let shared = unwrap!(g.find_kmer(&first[50..50 + k]));
+        assert_eq!(unwrap!(g.weight(shared)), 2);
+    }
+
+    #[test]
+    fn recovers_sequences_without_sequences() {
+        let _l = LOCK.lock().unwrap();
+        let k = unsafe { K_SIZE };
+        {
+            let mut s = SEQUENCES.write();
+            s.clear();
+            s.push(&[]);
+        }
+        let reads = vec![read(150, 6), read(90, 7)];
+        let g = graph(&reads);
+        assert_eq!(SEQUENCES.read().len(), 1);
+        for e in 0..g.edge_count() {
+            let name = unwrap!(g.edge_sequence(BossEdgeIndex::new(e)));
+            assert!(reads.iter().any(|r| r.windows(k).any(|w| w == &name[..])));
+        }
+    }
+}
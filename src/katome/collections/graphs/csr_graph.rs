@@ -0,0 +1,423 @@
+//! Compressed sparse row (CSR) based `Graph`.
+//!
+//! `PtGraph` keeps two links per node and two links per edge, so that nodes
+//! and edges can be added and removed at any time. Once the graph is
+//! simplified it is only read, and adjacency can be stored far more compactly:
+//! outgoing edges of every node occupy a contiguous range of the edge arrays
+//! and incoming edges are stored as a second, index-only row structure.
+//!
+//! `CsrGraph` is read-only - it implements `Graph`, but not `GraphMut`, so
+//! algorithms which modify the topology (pruning, shrinking, collapsing) have
+//! to be run on `PtGraph` before the conversion.
+
+use algorithms::builder::Build;
+use algorithms::collapser::{SerializedContig, SerializedContigs};
+use collections::graphs::{EdgeDirection, Graph, Id};
+use collections::graphs::pt_graph::{PtGraphBuilder, WeightedPtGraph};
+use prelude::{EdgeWeight, K1_SIZE, Weight};
+use slices::{BasicSlice, EdgeSlice};
+
+use fixedbitset::FixedBitSet;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::prelude::*;
+use std::path::Path;
+use std::u32;
+
+/// Type denoting index of node in `CsrGraph`.
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CsrNodeIndex(u32);
+
+/// Type denoting index of edge in `CsrGraph`.
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CsrEdgeIndex(u32);
+
+impl Id for CsrNodeIndex {
+    #[inline]
+    fn new(index: usize) -> CsrNodeIndex {
+        CsrNodeIndex(index as u32)
+    }
+
+    #[inline]
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Id for CsrEdgeIndex {
+    #[inline]
+    fn new(index: usize) -> CsrEdgeIndex {
+        CsrEdgeIndex(index as u32)
+    }
+
+    #[inline]
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Read-only `Graph` stored in the compressed sparse row format.
+///
+/// Edges are sorted by their sources, so outgoing edges of the node `n` are
+/// `out_offsets[n]..out_offsets[n + 1]`.
+#[derive(Clone, Debug)]
+pub struct CsrGraph<W = EdgeWeight> {
+    out_offsets: Vec<u32>,
+    targets: Vec<u32>,
+    weights: Vec<(EdgeSlice, W)>,
+    // incoming edges of the node `n` are `in_edges[in_offsets[n]..in_offsets[n + 1]]`
+    in_offsets: Vec<u32>,
+    in_edges: Vec<u32>,
+}
+
+impl<W> Default for CsrGraph<W> {
+    fn default() -> CsrGraph<W> {
+        CsrGraph {
+            out_offsets: vec![0],
+            targets: vec![],
+            weights: vec![],
+            in_offsets: vec![0],
+            in_edges: vec![],
+        }
+    }
+}
+
+impl<W: Weight> CsrGraph<W> {
+    /// Gets capacities of the node and edge arrays.
+    pub fn capacity(&self) -> (usize, usize) {
+        (self.out_offsets.capacity() - 1, self.weights.capacity())
+    }
+
+    /// Walks all maximal non-branching paths of the `Graph` and serializes
+    /// them into contigs.
+    ///
+    /// Unlike collapsing, walking doesn't modify the `Graph` and every edge
+    /// is spelled exactly once, so repeats are not resolved.
+    pub fn contigs(&self) -> SerializedContigs {
+        let is_inner = |n: usize| {
+            self.in_degree_of(n) == 1 && self.out_degree_of(n) == 1 &&
+            self.targets[self.out_offsets[n] as usize] as usize != n
+        };
+        let mut visited = FixedBitSet::with_capacity(self.edge_count());
+        let mut contigs = vec![];
+        for n in 0..self.node_count() {
+            if is_inner(n) {
+                continue;
+            }
+            for e in self.out_offsets[n] as usize..self.out_offsets[n + 1] as usize {
+                contigs.push(self.walk(e, &is_inner, &mut visited));
+            }
+        }
+        // edges left unvisited form isolated cycles
+        for e in 0..self.edge_count() {
+            if !visited.contains(e) {
+                contigs.push(self.walk(e, &is_inner, &mut visited));
+            }
+        }
+        info!("Walked {} contigs which have {} nucleotides",
+              contigs.len(),
+              contigs.iter().map(|x| x.len()).sum::<usize>());
+        contigs
+    }
+
+    // Follows the path starting with the given edge while it goes through
+    // inner nodes which haven't been visited yet.
+    fn walk<F: Fn(usize) -> bool>(&self, first: usize, is_inner: &F, visited: &mut FixedBitSet)
+                                  -> SerializedContig {
+        let mut contig = self.weights[first].0.name();
+        visited.insert(first);
+        let mut target = self.targets[first] as usize;
+        while is_inner(target) {
+            let e = self.out_offsets[target] as usize;
+            if visited.put(e) {
+                break;
+            }
+            contig.push_str(&self.weights[e].0.remainder());
+            target = self.targets[e] as usize;
+        }
+        contig
+    }
+
+    #[inline]
+    fn out_degree_of(&self, node: usize) -> usize {
+        (self.out_offsets[node + 1] - self.out_offsets[node]) as usize
+    }
+
+    #[inline]
+    fn in_degree_of(&self, node: usize) -> usize {
+        (self.in_offsets[node + 1] - self.in_offsets[node]) as usize
+    }
+
+    // Finds the last node whose outgoing edges start at or before the edge.
+    fn source_of(&self, edge: u32) -> usize {
+        let (mut low, mut high) = (0, self.node_count());
+        while high - low > 1 {
+            let mid = (low + high) / 2;
+            if self.out_offsets[mid] <= edge {
+                low = mid;
+            }
+            else {
+                high = mid;
+            }
+        }
+        low
+    }
+}
+
+impl<W: Weight> From<WeightedPtGraph<W>> for CsrGraph<W> {
+    fn from(graph: WeightedPtGraph<W>) -> CsrGraph<W> {
+        let node_count = graph.node_count();
+        let edge_count = graph.edge_count();
+        assert!(node_count < u32::MAX as usize && edge_count < u32::MAX as usize,
+                "Graph is too big to be stored as CsrGraph");
+        // counting sort of edges by their sources
+        let mut out_offsets = vec![0_u32; node_count + 1];
+        let mut in_offsets = vec![0_u32; node_count + 1];
+        for e in graph.raw_edges() {
+            out_offsets[e.source().index() + 1] += 1;
+            in_offsets[e.target().index() + 1] += 1;
+        }
+        for n in 0..node_count {
+            out_offsets[n + 1] += out_offsets[n];
+            in_offsets[n + 1] += in_offsets[n];
+        }
+        let mut next = out_offsets.clone();
+        let mut targets = vec![0_u32; edge_count];
+        let mut weights = vec![(EdgeSlice::default(), W::zero()); edge_count];
+        for e in graph.raw_edges() {
+            let source = e.source().index();
+            let position = next[source] as usize;
+            next[source] += 1;
+            targets[position] = e.target().index() as u32;
+            weights[position] = e.weight;
+        }
+        drop(graph);
+        next.clear();
+        next.extend_from_slice(&in_offsets);
+        let mut in_edges = vec![0_u32; edge_count];
+        for (e, &target) in targets.iter().enumerate() {
+            in_edges[next[target as usize] as usize] = e as u32;
+            next[target as usize] += 1;
+        }
+        CsrGraph {
+            out_offsets: out_offsets,
+            targets: targets,
+            weights: weights,
+            in_offsets: in_offsets,
+            in_edges: in_edges,
+        }
+    }
+}
+
+impl<W: Weight> Graph for CsrGraph<W> {
+    type NodeIdentifier = CsrNodeIndex;
+    type EdgeIdentifier = CsrEdgeIndex;
+
+    #[inline]
+    fn node_count(&self) -> usize {
+        self.out_offsets.len() - 1
+    }
+
+    #[inline]
+    fn edge_count(&self) -> usize {
+        self.targets.len()
+    }
+
+    #[inline]
+    fn out_degree(&self, node: CsrNodeIndex) -> usize {
+        self.out_degree_of(node.index())
+    }
+
+    #[inline]
+    fn in_degree(&self, node: CsrNodeIndex) -> usize {
+        self.in_degree_of(node.index())
+    }
+
+    fn fill_edges(&self, node: CsrNodeIndex, direction: EdgeDirection,
+                  edges: &mut Vec<CsrEdgeIndex>) {
+        let n = node.index();
+        edges.clear();
+        match direction {
+            EdgeDirection::Outgoing => {
+                edges.extend((self.out_offsets[n]..self.out_offsets[n + 1]).map(CsrEdgeIndex))
+            }
+            EdgeDirection::Incoming => {
+                edges.extend(self.in_edges[self.in_offsets[n] as usize..
+                                           self.in_offsets[n + 1] as usize]
+                    .iter()
+                    .map(|&e| CsrEdgeIndex(e)))
+            }
+        }
+    }
+
+    fn first_edge(&self, node: CsrNodeIndex, direction: EdgeDirection) -> Option<CsrEdgeIndex> {
+        let n = node.index();
+        match direction {
+            EdgeDirection::Outgoing if self.out_degree_of(n) > 0 => {
+                Some(CsrEdgeIndex(self.out_offsets[n]))
+            }
+            EdgeDirection::Incoming if self.in_degree_of(n) > 0 => {
+                Some(CsrEdgeIndex(self.in_edges[self.in_offsets[n] as usize]))
+            }
+            _ => None,
+        }
+    }
+
+    fn find_edge(&self, source: CsrNodeIndex, target: CsrNodeIndex) -> Option<CsrEdgeIndex> {
+        let n = source.index();
+        (self.out_offsets[n]..self.out_offsets[n + 1])
+            .find(|&e| self.targets[e as usize] == target.0)
+            .map(CsrEdgeIndex)
+    }
+
+    fn edge_endpoints(&self, edge: CsrEdgeIndex) -> Option<(CsrNodeIndex, CsrNodeIndex)> {
+        if edge.index() >= self.edge_count() {
+            return None;
+        }
+        Some((CsrNodeIndex::new(self.source_of(edge.0)), CsrNodeIndex(self.targets[edge.index()])))
+    }
+
+    #[inline]
+    fn weight(&self, edge: CsrEdgeIndex) -> Option<W> {
+        self.weights.get(edge.index()).map(|w| w.1)
+    }
+
+    #[inline]
+    fn edge_sequence(&self, edge: CsrEdgeIndex) -> Option<Vec<u8>> {
+        self.weights.get(edge.index()).map(|w| w.0.byte_name())
+    }
+}
+
+impl<W: Weight> Build for CsrGraph<W> {
+    type Weight = W;
+    type Builder = PtGraphBuilder<W>;
+
+    fn from_builder(builder: PtGraphBuilder<W>) -> CsrGraph<W> {
+        CsrGraph::from(WeightedPtGraph::from_builder(builder))
+    }
+}
+
+/// Serialize graph into .dot file.
+pub fn write_to_dot<W: Weight>(graph: &CsrGraph<W>, path_: &str) {
+    write_with(graph, path_, |file, graph| {
+        writeln!(file, "digraph {{")?;
+        for n in 0..graph.node_count() {
+            writeln!(file, "    {} [label=\"{}\"]", n, n)?;
+        }
+        for e in 0..graph.edge_count() {
+            writeln!(file,
+                     "    {} -> {} [label=\"{}\"]",
+                     graph.source_of(e as u32),
+                     graph.targets[e],
+                     graph.weights[e].1)?;
+        }
+        writeln!(file, "}}")
+    });
+}
+
+/// Serialize graph into .gfa file (GFA 1.0).
+///
+/// Every edge becomes a segment with its total k-mer count (weight times the
+/// number of k-mers in the edge) stored in the `KC` tag. Edges
+/// meeting in a node overlap by the node's (k-1)-mer, so each pair of the
+/// incoming and outgoing edge of the node becomes a link.
+pub fn write_to_gfa<W: Weight>(graph: &CsrGraph<W>, path_: &str) {
+    write_with(graph, path_, |file, graph| {
+        let overlap = unsafe { K1_SIZE };
+        writeln!(file, "H\tVN:Z:1.0")?;
+        for (e, &(ref slice, weight)) in graph.weights.iter().enumerate() {
+            let name = slice.name();
+            // k-mer count has to be an integer, even for the float weights
+            let count = (weight.to_f64() * (name.len() - overlap) as f64).round() as u64;
+            writeln!(file, "S\t{}\t{}\tKC:i:{}", e, name, count)?;
+        }
+        for n in 0..graph.node_count() {
+            let outgoing = graph.out_offsets[n]..graph.out_offsets[n + 1];
+            let incoming = graph.in_offsets[n] as usize..graph.in_offsets[n + 1] as usize;
+            for &from in &graph.in_edges[incoming] {
+                for to in outgoing.clone() {
+                    writeln!(file, "L\t{}\t+\t{}\t+\t{}M", from, to, overlap)?;
+                }
+            }
+        }
+        Ok(())
+    });
+}
+
+fn write_with<W, F>(graph: &CsrGraph<W>, path_: &str, write: F)
+    where F: Fn(&mut BufWriter<File>, &CsrGraph<W>) -> ::std::io::Result<()> {
+    let path = Path::new(path_);
+    let display = path.display();
+
+    let mut file = match File::create(&path) {
+        Err(why) => panic!("couldn't create {}: {}", display, why.description()),
+        Ok(file) => BufWriter::new(file),
+    };
+    match write(&mut file, graph).and_then(|_| file.flush()) {
+        Err(why) => panic!("couldn't write to {}: {}", display, why.description()),
+        Ok(_) => info!("successfully wrote to {}", display),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asm::SEQUENCES;
+    use collections::PtGraph;
+    use asm::lock::LOCK;
+    use compress::compress_edge;
+    use stats::Stats;
+    use std::iter::repeat;
+
+    // edge sequences differ only in the last character, which is enough for
+    // walking as remainders of the edges are compared
+    fn edge(last: char) -> String {
+        let mut name = repeat('A').take(unsafe { K1_SIZE }).collect::<String>();
+        name.push(last);
+        name
+    }
+
+    fn graph() -> CsrGraph {
+        // 0 -> 1 -> 2 -> 3, 1 -> 4, 4 -> 4
+        let edges: [(usize, usize, char); 5] =
+            [(0, 1, 'C'), (1, 2, 'G'), (2, 3, 'T'), (1, 4, 'A'), (4, 4, 'T')];
+        let mut s = SEQUENCES.write();
+        s.clear();
+        s.push(&[]);
+        let pt = PtGraph::from_edges(edges.iter().map(|&(a, b, last)| {
+            let id = s.push(&compress_edge(edge(last).as_bytes()));
+            (a, b, (EdgeSlice::new(id), 1))
+        }));
+        CsrGraph::from(pt)
+    }
+
+    #[test]
+    fn converts_from_pt_graph() {
+        let _l = LOCK.lock().unwrap();
+        let g = graph();
+        assert_eq!(g.node_count(), 5);
+        assert_eq!(g.edge_count(), 5);
+        assert_eq!(g.out_degree(CsrNodeIndex(1)), 2);
+        assert_eq!(g.in_degree(CsrNodeIndex(4)), 2);
+        assert_eq!(g.first_edge(CsrNodeIndex(0), EdgeDirection::Incoming), None);
+        let e = unwrap!(g.find_edge(CsrNodeIndex(2), CsrNodeIndex(3)));
+        assert_eq!(g.edge_endpoints(e), Some((CsrNodeIndex(2), CsrNodeIndex(3))));
+        for n in 0..g.node_count() {
+            for e in g.edges_of(CsrNodeIndex::new(n), EdgeDirection::Incoming) {
+                assert_eq!(unwrap!(g.edge_endpoints(e)).1.index(), n);
+            }
+        }
+        assert_eq!(g.stats().counts.edge_count, 5);
+    }
+
+    #[test]
+    fn walks_contigs() {
+        let _l = LOCK.lock().unwrap();
+        let mut contigs = graph().contigs();
+        contigs.sort();
+        let mut expected = vec![edge('A'), edge('C'), edge('G') + "T", edge('T')];
+        expected.sort();
+        assert_eq!(contigs, expected);
+    }
+}
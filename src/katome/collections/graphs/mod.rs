@@ -3,30 +3,155 @@
 //! `Graph`s support various algorithms for efficient genome assembly. They can
 //! be build from the input file or from the `GIR` if it supports convertion
 //! into the specified `Graph`.
+//!
+//! Algorithms (pruning, standardization, shrinking and collapsing) are written
+//! once against the primitives of the `GraphMut` trait and are available for
+//! every type which implements it. Read-only `Graph`s support only the
+//! algorithms which leave the structure of the graph intact.
+pub mod boss;
+pub mod csr_graph;
 pub mod pt_graph;
 
 use algorithms::builder::Build;
-use algorithms::collapser::Collapsable;
-use algorithms::pruner::Prunable;
-use algorithms::standardizer::Standardizable;
+use compress::compress_node;
+use prelude::{CDC, K1_SIZE};
+use slices::EdgeSlice;
 use stats::{Stats, CollectionStats};
 
-/// Graph's interface.
-pub trait Graph
-    : Build + Prunable + Standardizable + Collapsable + Stats<CollectionStats> {
+pub use petgraph::EdgeDirection;
+
+use metrohash::MetroHash;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::hash::BuildHasherDefault as BuildHash;
+use std::hash::Hash;
+
+/// Identifier of the node or the edge in the `Graph`.
+///
+/// Identifiers are dense - nodes of the `Graph` are identified by all indices
+/// in range `0..node_count()` and edges by all indices in range
+/// `0..edge_count()`.
+pub trait Id: Copy + Eq + Ord + Hash + Debug {
+    /// Creates identifier with the given index.
+    fn new(usize) -> Self;
+    /// Gets index of the identifier.
+    fn index(self) -> usize;
+}
+
+/// Read-only graph's interface.
+pub trait Graph: Build + Stats<CollectionStats<<Self as Build>::Weight>> {
     /// Node identifier.
-    type NodeIdentifier;
-    /// Collection storing nodes which are ambiguous nodes.
+    type NodeIdentifier: Id;
+    /// Edge identifier.
+    type EdgeIdentifier: Id;
+
+    /// Finds all ambiguous in the `Graph`.
     ///
     /// Node is considered ambiguous if this condition holds:
     /// ```(in_degree > 1 || out_degree > 1) || (in_degree == 0 && out_degree >= 1)```
     /// where `in_degree` and `out_degree` are counts of incoming and outgoing
     /// edges.
-    type AmbiguousNodes;
-    /// Finds all ambiguous in the `Graph`.
-    fn get_ambiguous_nodes(&self) -> Self::AmbiguousNodes;
+    fn get_ambiguous_nodes(&self) -> HashSet<Self::NodeIdentifier> {
+        (0..self.node_count())
+            .map(<Self::NodeIdentifier as Id>::new)
+            .filter(|n| {
+                let in_degree = self.in_degree(*n);
+                let out_degree = self.out_degree(*n);
+                (in_degree > 1 || out_degree > 1) || (in_degree == 0 && out_degree >= 1)
+            })
+            .collect()
+    }
+    /// Gets number of nodes.
+    fn node_count(&self) -> usize;
+    /// Gets number of edges.
+    fn edge_count(&self) -> usize;
     /// Gets number of outgoing edges for the given node.
     fn out_degree(&self, Self::NodeIdentifier) -> usize;
     /// Gets number of incoming edges for the given node.
     fn in_degree(&self, Self::NodeIdentifier) -> usize;
+    /// Gets number of edges of the given node in the specified direction.
+    fn degree(&self, node: Self::NodeIdentifier, direction: EdgeDirection) -> usize {
+        match direction {
+            EdgeDirection::Outgoing => self.out_degree(node),
+            EdgeDirection::Incoming => self.in_degree(node),
+        }
+    }
+    /// Gets all edges of the given node in the specified direction.
+    fn edges_of(&self, node: Self::NodeIdentifier, direction: EdgeDirection)
+                -> Vec<Self::EdgeIdentifier> {
+        let mut edges = vec![];
+        self.fill_edges(node, direction, &mut edges);
+        edges
+    }
+    /// Replaces content of the buffer with all edges of the given node in the
+    /// specified direction. Unlike `edges_of` it doesn't allocate once the
+    /// buffer is big enough, so it is used by loops over many nodes.
+    fn fill_edges(&self, Self::NodeIdentifier, EdgeDirection, &mut Vec<Self::EdgeIdentifier>);
+    /// Gets any edge of the given node in the specified direction.
+    fn first_edge(&self, Self::NodeIdentifier, EdgeDirection) -> Option<Self::EdgeIdentifier>;
+    /// Finds edge going from the first node to the second one.
+    fn find_edge(&self, Self::NodeIdentifier, Self::NodeIdentifier)
+                 -> Option<Self::EdgeIdentifier>;
+    /// Gets source and target of the edge.
+    fn edge_endpoints(&self, Self::EdgeIdentifier)
+                      -> Option<(Self::NodeIdentifier, Self::NodeIdentifier)>;
+    /// Gets weight of the edge.
+    fn weight(&self, Self::EdgeIdentifier) -> Option<Self::Weight>;
+    /// Gets decompressed sequence of the edge.
+    fn edge_sequence(&self, Self::EdgeIdentifier) -> Option<Vec<u8>>;
+}
+
+/// Mutable graph's interface.
+///
+/// Removal of the node or the edge may invalidate only the identifier of the
+/// last node or edge respectively, which takes the place of the removed one.
+/// Algorithms which remove several items at once should remove them in the
+/// descending order of identifiers.
+pub trait GraphMut: Graph {
+    /// Gets sequence and weight of the edge.
+    fn edge_weight(&self, Self::EdgeIdentifier) -> Option<&(EdgeSlice, Self::Weight)>;
+    /// Gets mutable sequence and weight of the edge.
+    fn edge_weight_mut(&mut self, Self::EdgeIdentifier) -> Option<&mut (EdgeSlice, Self::Weight)>;
+    /// Adds node without any edges.
+    fn add_node(&mut self) -> Self::NodeIdentifier;
+    /// Adds edge between the given nodes.
+    fn add_edge(&mut self, Self::NodeIdentifier, Self::NodeIdentifier, (EdgeSlice, Self::Weight))
+                -> Self::EdgeIdentifier;
+    /// Removes edge, returns its sequence and weight.
+    fn remove_edge(&mut self, Self::EdgeIdentifier) -> Option<(EdgeSlice, Self::Weight)>;
+    /// Removes node along with all of its edges.
+    fn remove_node(&mut self, Self::NodeIdentifier);
+    /// Removes all of the given edges, in the descending order of identifiers,
+    /// so that none of them is invalidated.
+    fn remove_edges(&mut self, mut edges: Vec<Self::EdgeIdentifier>) {
+        edges.sort_by(|a, b| b.cmp(a));
+        edges.dedup();
+        for e in edges {
+            self.remove_edge(e);
+        }
+    }
+}
+
+/// Map from compressed sequences of nodes to nodes of the graph.
+pub type SequencesToNodes<N> = HashMap<Vec<CDC>, N, BuildHash<MetroHash>>;
+
+/// Maps compressed sequences of nodes to nodes of the graph, so that nodes can
+/// be found by (k-1)-mers of reads or contigs compressed with `compress_node`.
+pub fn index_nodes<G: Graph>(graph: &G) -> SequencesToNodes<G::NodeIdentifier> {
+    let k1 = unsafe { K1_SIZE };
+    let mut nodes = SequencesToNodes::with_capacity_and_hasher(graph.node_count(),
+                                                               BuildHash::<MetroHash>::default());
+    for i in 0..graph.edge_count() {
+        let e = G::EdgeIdentifier::new(i);
+        let name = unwrap!(graph.edge_sequence(e));
+        let (source, target) = unwrap!(graph.edge_endpoints(e));
+        let mut key = vec![];
+        compress_node(&name[..k1], &mut key);
+        nodes.insert(key, source);
+        let mut key = vec![];
+        compress_node(&name[name.len() - k1..], &mut key);
+        nodes.insert(key, target);
+    }
+    nodes
 }
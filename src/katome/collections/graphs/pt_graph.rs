@@ -1,24 +1,30 @@
 //! `petgraph` based `Graph`.
 
-use algorithms::builder::{Build, Init};
+use algorithms::builder::{AddReads, Build, Init, Update, add_reads};
 use asm::SEQUENCES;
-use collections::graphs::Graph;
-use compress::{compress_kmer, kmer_to_edge, compress_kmer_with_rev_compl};
+use collections::graphs::{EdgeDirection, Graph, GraphMut, Id, SequencesToNodes};
+use compress::{compress_edge, compress_kmer, compress_node, decompress_edge, kmer_to_edge,
+               compress_kmer_with_rev_compl};
 use config::InputFileType;
-use prelude::{CDC, EdgeWeight, Idx, K_SIZE, K1_SIZE};
+use prelude::{CDC, EdgeWeight, Idx, K_SIZE, K1_SIZE, Sequences, Weight};
 use slices::{BasicSlice, EdgeSlice, NodeSlice};
+use stats::{HeapSize, table_size};
+use utils::reverse_complement_of;
 
 use fixedbitset::FixedBitSet;
 use metrohash::MetroHash;
 use petgraph;
 use petgraph::dot::{Config, Dot};
+use petgraph::visit::EdgeRef;
 
+use std::cmp;
 use std::collections::HashSet;
 use std::collections::hash_map::{Entry, HashMap};
 use std::error::Error;
 use std::fs::File;
 use std::hash::BuildHasherDefault as BuildHash;
 use std::io::prelude::*;
+use std::mem::size_of;
 use std::path::Path;
 
 
@@ -29,11 +35,14 @@ pub type NodeIndex = petgraph::graph::NodeIndex<Idx>;
 /// `Node` type in `PtGraph`.
 pub type Node = petgraph::graph::Node<(), Idx>;
 
+/// `petgraph` based `Graph` with the given type of edge weights.
+pub type WeightedPtGraph<W> = petgraph::Graph<(), (EdgeSlice, W), petgraph::Directed, Idx>;
+
 /// `petgraph` based `Graph`.
-pub type PtGraph = petgraph::Graph<(), (EdgeSlice, EdgeWeight), petgraph::Directed, Idx>;
+pub type PtGraph = WeightedPtGraph<EdgeWeight>;
 
 /// Serialize graph into .dot file.
-pub fn write_to_dot(graph: &PtGraph, path_: &str) {
+pub fn write_to_dot<W: Weight>(graph: &WeightedPtGraph<W>, path_: &str) {
     let path = Path::new(path_);
     let display = path.display();
 
@@ -48,30 +57,119 @@ pub fn write_to_dot(graph: &PtGraph, path_: &str) {
     }
 }
 
-impl Graph for PtGraph {
+impl Id for NodeIndex {
+    #[inline]
+    fn new(index: usize) -> NodeIndex {
+        NodeIndex::new(index)
+    }
+
+    #[inline]
+    fn index(self) -> usize {
+        NodeIndex::index(self)
+    }
+}
+
+impl Id for EdgeIndex {
+    #[inline]
+    fn new(index: usize) -> EdgeIndex {
+        EdgeIndex::new(index)
+    }
+
+    #[inline]
+    fn index(self) -> usize {
+        EdgeIndex::index(self)
+    }
+}
+
+impl<W: Weight> Graph for WeightedPtGraph<W> {
     type NodeIdentifier = NodeIndex;
-    type AmbiguousNodes = HashSet<NodeIndex>;
-    fn get_ambiguous_nodes(&self) -> Self::AmbiguousNodes {
-        self.node_indices()
-            .filter(|n| {
-                let in_degree = self.in_degree(*n);
-                let out_degree = self.out_degree(*n);
-                (in_degree > 1 || out_degree > 1) || (in_degree == 0 && out_degree >= 1)
-            })
-            .collect::<Self::AmbiguousNodes>()
+    type EdgeIdentifier = EdgeIndex;
+
+    #[inline]
+    fn node_count(&self) -> usize {
+        petgraph::Graph::node_count(self)
+    }
+
+    #[inline]
+    fn edge_count(&self) -> usize {
+        petgraph::Graph::edge_count(self)
     }
 
     #[inline]
     fn out_degree(&self, node: Self::NodeIdentifier) -> usize {
-        self.neighbors_directed(node, petgraph::EdgeDirection::Outgoing)
+        self.neighbors_directed(node, EdgeDirection::Outgoing)
             .count()
     }
 
     #[inline]
     fn in_degree(&self, node: Self::NodeIdentifier) -> usize {
-        self.neighbors_directed(node, petgraph::EdgeDirection::Incoming)
+        self.neighbors_directed(node, EdgeDirection::Incoming)
             .count()
     }
+
+    #[inline]
+    fn fill_edges(&self, node: NodeIndex, direction: EdgeDirection, edges: &mut Vec<EdgeIndex>) {
+        edges.clear();
+        edges.extend(self.edges_directed(node, direction).map(|e| e.id()));
+    }
+
+    #[inline]
+    fn first_edge(&self, node: NodeIndex, direction: EdgeDirection) -> Option<EdgeIndex> {
+        petgraph::Graph::first_edge(self, node, direction)
+    }
+
+    #[inline]
+    fn find_edge(&self, source: NodeIndex, target: NodeIndex) -> Option<EdgeIndex> {
+        petgraph::Graph::find_edge(self, source, target)
+    }
+
+    #[inline]
+    fn edge_endpoints(&self, edge: EdgeIndex) -> Option<(NodeIndex, NodeIndex)> {
+        petgraph::Graph::edge_endpoints(self, edge)
+    }
+
+    #[inline]
+    fn weight(&self, edge: EdgeIndex) -> Option<W> {
+        petgraph::Graph::edge_weight(self, edge).map(|w| w.1)
+    }
+
+    #[inline]
+    fn edge_sequence(&self, edge: EdgeIndex) -> Option<Vec<u8>> {
+        petgraph::Graph::edge_weight(self, edge).map(|w| w.0.byte_name())
+    }
+}
+
+impl<W: Weight> GraphMut for WeightedPtGraph<W> {
+    #[inline]
+    fn edge_weight(&self, edge: EdgeIndex) -> Option<&(EdgeSlice, W)> {
+        petgraph::Graph::edge_weight(self, edge)
+    }
+
+    #[inline]
+    fn edge_weight_mut(&mut self, edge: EdgeIndex) -> Option<&mut (EdgeSlice, W)> {
+        petgraph::Graph::edge_weight_mut(self, edge)
+    }
+
+    #[inline]
+    fn add_node(&mut self) -> NodeIndex {
+        petgraph::Graph::add_node(self, ())
+    }
+
+    #[inline]
+    fn add_edge(&mut self, source: NodeIndex, target: NodeIndex, weight: (EdgeSlice, W))
+                -> EdgeIndex {
+        petgraph::Graph::add_edge(self, source, target, weight)
+    }
+
+    #[inline]
+    fn remove_edge(&mut self, edge: EdgeIndex) -> Option<(EdgeSlice, W)> {
+        petgraph::Graph::remove_edge(self, edge)
+    }
+
+    #[inline]
+    fn remove_node(&mut self, node: NodeIndex) {
+        petgraph::Graph::remove_node(self, node);
+    }
 }
 
 // SeenNodes stores information about already seen nodes. Due to the nature of
@@ -98,15 +196,19 @@ type ReadsToNodes = HashMap<NodeSlice, NodeIndex, BuildHash<MetroHash>>;
 const BLOCKS_PER_NUMBER: usize = 512;
 const NODES_PER_NUMBER: usize = BLOCKS_PER_NUMBER * 32;
 
-// Graph builder which stores information about already seen vertices
+/// Graph builder which stores information about already seen vertices,
+/// `Builder` of `WeightedPtGraph`.
 // TODO maybe change that to the tagged union?
 #[derive(Default)]
-struct PtGraphBuilder {
-    graph: PtGraph,
+pub struct PtGraphBuilder<W> {
+    graph: WeightedPtGraph<W>,
     seen_nodes: SeenNodes,
     reads_to_nodes: ReadsToNodes,
     fb: FixedBitSet,
     regions: Vec<Idx>,
+    // index of the first sequence added by the builder, sequences before it
+    // may belong to other graphs
+    first_sequence: Idx,
 }
 
 #[inline]
@@ -114,7 +216,7 @@ fn div_rem(x: usize) -> (usize, usize) {
     (x / NODES_PER_NUMBER, x % NODES_PER_NUMBER)
 }
 
-impl PtGraphBuilder {
+impl<W: Weight> PtGraphBuilder<W> {
     #[inline]
     fn add_bfc_node(&mut self, mut node: NodeSlice) -> NodeIndex {
         let mut insert = false;
@@ -128,6 +230,11 @@ impl PtGraphBuilder {
         }
         if insert {
             self.seen_nodes.insert(node);
+            // more k-mers than estimated could have been added
+            if node.offset() >= self.fb.len() {
+                self.fb.grow(2 * node.offset() + 2);
+                self.regions.resize((2 * node.offset() + 2) / NODES_PER_NUMBER + 1, 0);
+            }
             self.fb.set(node.offset(), true);
             let block = node.offset() / NODES_PER_NUMBER;
             self.regions[block] += 1;
@@ -170,12 +277,12 @@ impl PtGraphBuilder {
 
     #[inline]
     fn add_single_edge_fastaq(&mut self, first_edge: bool, compressed: Vec<CDC>,
-                              s: &mut NodeIndex, t: &mut NodeIndex) {
+                              s: &mut NodeIndex, t: &mut NodeIndex, weight: W) {
         let offset;
         {
             let mut s = SEQUENCES.write();
             offset = s.len();
-            s.push(compressed.into_boxed_slice());
+            s.push(&compressed);
         }
         if first_edge {
             let source = NodeSlice::new(2 * offset);
@@ -187,23 +294,25 @@ impl PtGraphBuilder {
             // edge already in the graph, update it's weight
             Some(e) => {
                 SEQUENCES.write().pop();
-                self.graph.edge_weight_mut(e).expect("This should never fail").1 += 1;
+                let w = self.graph.edge_weight_mut(e).expect("This should never fail");
+                w.1 = w.1.saturating_add(weight);
             }
             // insert new edge
             None => {
-                self.graph.add_edge(*s, *t, (EdgeSlice::from(NodeSlice::new(2 * offset)), 1));
+                let slice = EdgeSlice::from(NodeSlice::new(2 * offset));
+                self.graph.add_edge(*s, *t, (slice, weight));
             }
         }
         *s = *t;
     }
 
     #[inline]
-    fn add_single_edge_bfc(&mut self, compressed_kmer: Vec<CDC>, weight: EdgeWeight) {
+    fn add_single_edge_bfc(&mut self, compressed_kmer: Vec<CDC>, weight: W) {
         let offset;
         {
             let mut s = SEQUENCES.write();
             offset = s.len();
-            s.push(compressed_kmer.into_boxed_slice());
+            s.push(&compressed_kmer);
         }
         let source = NodeSlice::new(2 * offset);
         let target = NodeSlice::new(2 * offset + 1);
@@ -213,23 +322,17 @@ impl PtGraphBuilder {
     }
 }
 
-impl Init for PtGraph {
-    fn init(edge_count: Option<usize>, node_count: Option<usize>, _ft: InputFileType) -> PtGraph {
-        let nodes = match node_count {
-            Some(n) => n,
-            None => 0,
-        };
-        let edges = match edge_count {
-            Some(n) => n,
-            None => 0,
-        };
-        PtGraph::with_capacity(nodes, edges)
+impl<W: Weight> HeapSize for PtGraphBuilder<W> {
+    fn heap_size(&self) -> usize {
+        self.graph.heap_size() + table_size::<NodeSlice>(self.seen_nodes.capacity()) +
+        table_size::<(NodeSlice, NodeIndex)>(self.reads_to_nodes.capacity()) +
+        self.fb.len() / 8 + self.regions.capacity() * size_of::<Idx>()
     }
 }
 
-impl Init for PtGraphBuilder {
+impl<W: Weight> Init for PtGraphBuilder<W> {
     fn init(edge_count: Option<usize>, node_count: Option<usize>, ft: InputFileType)
-            -> PtGraphBuilder {
+            -> PtGraphBuilder<W> {
         let nodes = match node_count {
             Some(n) => n,
             None => 0,
@@ -241,7 +344,7 @@ impl Init for PtGraphBuilder {
         match ft {
             InputFileType::Fasta | InputFileType::Fastq => {
                 PtGraphBuilder {
-                    graph: PtGraph::with_capacity(nodes, edges),
+                    graph: WeightedPtGraph::with_capacity(nodes, edges),
                     seen_nodes:
                         SeenNodes::with_capacity_and_hasher(0, BuildHash::<MetroHash>::default()),
                     reads_to_nodes:
@@ -249,11 +352,12 @@ impl Init for PtGraphBuilder {
                                                                BuildHash::<MetroHash>::default()),
                     fb: FixedBitSet::with_capacity(0),
                     regions: Vec::with_capacity(0),
+                    first_sequence: SEQUENCES.read().len(),
                 }
             }
             InputFileType::BFCounter => {
                 PtGraphBuilder {
-                    graph: PtGraph::with_capacity(nodes, edges),
+                    graph: WeightedPtGraph::with_capacity(nodes, edges),
                     seen_nodes:
                         SeenNodes::with_capacity_and_hasher(nodes,
                                                             BuildHash::<MetroHash>::default()),
@@ -266,13 +370,16 @@ impl Init for PtGraphBuilder {
                     fb: FixedBitSet::with_capacity(4 * edges + 2),
                     regions:
                         vec![0; ((4 * edges + 2) as f64 / NODES_PER_NUMBER as f64).ceil() as usize],
+                    first_sequence: SEQUENCES.read().len(),
                 }
             }
         }
     }
 }
 
-impl Build for PtGraphBuilder {
+impl<W: Weight> AddReads for PtGraphBuilder<W> {
+    type Weight = W;
+
     #[inline]
     fn add_read_fastaq(&mut self, read: &[u8], reverse_complement: bool) {
         assert!(read.len() as Idx >= unsafe { K_SIZE }, "Read is too short!");
@@ -297,27 +404,41 @@ impl Build for PtGraphBuilder {
                 // generated
                 let (compressed_kmer, rev_compl_compr) = compress_kmer_with_rev_compl(window);
                 reversed.push(rev_compl_compr);
-                self.add_single_edge_fastaq(cnt == 0, compressed_kmer, &mut s, &mut t);
+                self.add_single_edge_fastaq(cnt == 0, compressed_kmer, &mut s, &mut t, W::one());
             }
             // add reverse complements
             let rev = reversed.len() - 1;
-            self.add_single_edge_fastaq(true, reversed.remove(rev), &mut s, &mut t);
+            self.add_single_edge_fastaq(true, reversed.remove(rev), &mut s, &mut t, W::one());
             for r in reversed.drain(..).rev() {
-                self.add_single_edge_fastaq(false, r, &mut s, &mut t);
+                self.add_single_edge_fastaq(false, r, &mut s, &mut t, W::one());
             }
         }
         else {
             for (cnt, window) in read.windows(unsafe { K_SIZE } as usize).enumerate() {
                 let compressed_kmer = compress_kmer(window);
-                self.add_single_edge_fastaq(cnt == 0, compressed_kmer, &mut s, &mut t);
+                self.add_single_edge_fastaq(cnt == 0, compressed_kmer, &mut s, &mut t, W::one());
             }
         }
     }
 
     #[inline]
-    fn add_read_bfc(&mut self, read: &[u8], weight: EdgeWeight, reverse_complement: bool) {
+    fn add_read_bfc(&mut self, read: &[u8], weight: W, reverse_complement: bool) {
         assert!(read.len() as Idx >= unsafe { K_SIZE }, "Read is too short!");
-        if reverse_complement {
+        if !self.reads_to_nodes.is_empty() {
+            // counted k-mers added to the graph built from reads, its nodes
+            // can be located only through `reads_to_nodes`
+            let mut s = NodeIndex::default();
+            let mut t = NodeIndex::default();
+            if reverse_complement {
+                let (compressed_kmer, rev_compl_compr) = compress_kmer_with_rev_compl(read);
+                self.add_single_edge_fastaq(true, compressed_kmer, &mut s, &mut t, weight);
+                self.add_single_edge_fastaq(true, rev_compl_compr, &mut s, &mut t, weight);
+            }
+            else {
+                self.add_single_edge_fastaq(true, compress_kmer(read), &mut s, &mut t, weight);
+            }
+        }
+        else if reverse_complement {
             let (compressed_kmer, rev_compl_compr) = compress_kmer_with_rev_compl(read);
             self.add_single_edge_bfc(compressed_kmer, weight);
             self.add_single_edge_bfc(rev_compl_compr, weight);
@@ -327,24 +448,191 @@ impl Build for PtGraphBuilder {
             self.add_single_edge_bfc(compressed_kmer, weight);
         }
     }
+
+    fn kmer_count(&self) -> Option<usize> {
+        Some(self.graph.edge_count())
+    }
+}
+
+impl<W: Weight> Build for WeightedPtGraph<W> {
+    type Weight = W;
+    type Builder = PtGraphBuilder<W>;
+
+    fn from_builder(builder: PtGraphBuilder<W>) -> WeightedPtGraph<W> {
+        kmers_to_edges(builder.first_sequence);
+        builder.graph
+    }
 }
 
-impl Build for PtGraph {
-    fn create<P: AsRef<Path>>(input_files: &[P], ft: InputFileType, reverse_complement: bool,
-                              minimal_weight_threshold: EdgeWeight)
-                              -> (Self, usize)
-        where Self: Sized {
-        let (builder, number_of_read_bytes) =
-            PtGraphBuilder::create(input_files, ft, reverse_complement, minimal_weight_threshold);
-        let mut s = SEQUENCES.write();
-        for mut e in s.iter_mut().skip(1) {
-            let new_box = kmer_to_edge(e).into_boxed_slice();
-            *e = new_box;
+impl<W: Weight> Update for WeightedPtGraph<W> {
+    fn update<P: AsRef<Path>>(self, input_files: &[P], ft: InputFileType,
+                              reverse_complement: bool, minimal_weight_threshold: W)
+                              -> (WeightedPtGraph<W>, usize) {
+        let mut updater = PtGraphUpdater::from(self);
+        let number_of_read_bytes = add_reads(&mut updater,
+                                             input_files,
+                                             ft,
+                                             reverse_complement,
+                                             minimal_weight_threshold);
+        (updater.graph, number_of_read_bytes)
+    }
+}
+
+// Graph updater which locates nodes of the already built graph by their
+// compressed sequences. Sequences are recovered from the edges - first and last
+// (k-1)-mer of the edge are its source and target. Nodes inside of edges longer
+// than k-mer (e.g. after shrinking) couldn't be located, so such graphs are
+// rejected. Every edge is a k-mer then, and it is identified by its endpoints.
+#[derive(Default)]
+struct PtGraphUpdater<W> {
+    graph: WeightedPtGraph<W>,
+    nodes: SequencesToNodes<NodeIndex>,
+    // compressed node, reused between the lookups
+    key: Vec<CDC>,
+}
+
+impl<W: Weight> From<WeightedPtGraph<W>> for PtGraphUpdater<W> {
+    fn from(graph: WeightedPtGraph<W>) -> PtGraphUpdater<W> {
+        let (k, k1) = unsafe { (K_SIZE, K1_SIZE) };
+        let mut nodes =
+            SequencesToNodes::with_capacity_and_hasher(graph.node_count(),
+                                                       BuildHash::<MetroHash>::default());
+        {
+            let s = SEQUENCES.read();
+            for e in graph.raw_edges() {
+                let edge = decompress_edge(&s[e.weight.0.idx()]);
+                assert!(edge.len() == k,
+                        "Only graphs with k-mer long edges can be updated, shrunk graph given");
+                nodes.entry(compress_sequence(&edge[..k1])).or_insert(e.source());
+                nodes.entry(compress_sequence(&edge[1..])).or_insert(e.target());
+            }
+        }
+        PtGraphUpdater {
+            graph: graph,
+            nodes: nodes,
+            key: vec![],
+        }
+    }
+}
+
+impl<W: Weight> PtGraphUpdater<W> {
+    fn get_or_add_node(&mut self, node: &[u8]) -> NodeIndex {
+        self.key.clear();
+        compress_node(node, &mut self.key);
+        if let Some(&n) = self.nodes.get(&self.key[..]) {
+            return n;
+        }
+        let n = self.graph.add_node(());
+        self.nodes.insert(self.key.clone(), n);
+        n
+    }
+
+    // Adds k-mer as the edge, or increases weight of the existing one.
+    fn add_kmer(&mut self, kmer: &[u8], weight: W) {
+        let k1 = unsafe { K1_SIZE };
+        assert!(kmer.len() == k1 + 1,
+                "Only k-mers can be added to the graph, edge of length {} given",
+                kmer.len());
+        let source = self.get_or_add_node(&kmer[..k1]);
+        let target = self.get_or_add_node(&kmer[1..]);
+        match self.graph.find_edge(source, target) {
+            // edge already in the graph, update it's weight
+            Some(e) => {
+                let w = self.graph.edge_weight_mut(e).expect("This should never fail");
+                w.1 = w.1.saturating_add(weight);
+            }
+            // insert new edge
+            None => {
+                let slice = {
+                    let mut s = SEQUENCES.write();
+                    s.push(&compress_edge(kmer));
+                    EdgeSlice::new(s.len() - 1)
+                };
+                self.graph.add_edge(source, target, (slice, weight));
+            }
+        }
+    }
+
+    fn add_sequence(&mut self, read: &[u8], weight: W, reverse_complement: bool) {
+        assert!(read.len() as Idx >= unsafe { K_SIZE }, "Read is too short!");
+        for window in read.windows(unsafe { K_SIZE }) {
+            self.add_kmer(window, weight);
+        }
+        if reverse_complement {
+            let rc = reverse_complement_of(read);
+            for window in rc.windows(unsafe { K_SIZE }) {
+                self.add_kmer(window, weight);
+            }
         }
-        (builder.graph, number_of_read_bytes)
     }
+}
+
+impl<W: Weight> Init for PtGraphUpdater<W> {}
+
+impl<W: Weight> AddReads for PtGraphUpdater<W> {
+    type Weight = W;
+
+    fn add_read_fastaq(&mut self, read: &[u8], reverse_complement: bool) {
+        self.add_sequence(read, W::one(), reverse_complement);
+    }
+
+    fn add_read_bfc(&mut self, read: &[u8], weight: W, reverse_complement: bool) {
+        self.add_sequence(read, weight, reverse_complement);
+    }
+}
+
+fn compress_sequence(sequence: &[u8]) -> Vec<CDC> {
+    let mut compressed = Vec::with_capacity(sequence.len() / 4 + 1);
+    compress_node(sequence, &mut compressed);
+    compressed
+}
+
+/// Merges graphs which store their edges in the global `SEQUENCES`. Nodes with
+/// identical sequences are unified and weights of identical edges are summed.
+/// Edges of both graphs have to be k-mers, i.e. graphs can't be shrunk.
+pub fn merge<W: Weight>(graph: WeightedPtGraph<W>, other: &WeightedPtGraph<W>)
+                        -> WeightedPtGraph<W> {
+    let edges = other.raw_edges()
+        .iter()
+        .map(|e| (e.weight.0.byte_name(), e.weight.1))
+        .collect::<Vec<(Vec<u8>, W)>>();
+    merge_edges(graph, edges)
+}
+
+/// Merges `other` graph, which stores its edges in `other_sequences`, into
+/// `graph`, which stores its edges in the global `SEQUENCES`. Nodes with
+/// identical sequences are unified and weights of identical edges are summed.
+/// Edges of `other` are copied to the `SEQUENCES`, so `other_sequences` can be
+/// dropped afterwards. It must not be the global `SEQUENCES` - use `merge` in
+/// such case. Edges of both graphs have to be k-mers, i.e. graphs can't be
+/// shrunk.
+pub fn merge_with_sequences<W: Weight>(graph: WeightedPtGraph<W>, other: &WeightedPtGraph<W>,
+                                       other_sequences: &Sequences)
+                                       -> WeightedPtGraph<W> {
+    let edges = other.raw_edges()
+        .iter()
+        .map(|e| (decompress_edge(&other_sequences[e.weight.0.idx()]), e.weight.1))
+        .collect::<Vec<(Vec<u8>, W)>>();
+    merge_edges(graph, edges)
+}
+
+fn merge_edges<W: Weight>(graph: WeightedPtGraph<W>, edges: Vec<(Vec<u8>, W)>)
+                          -> WeightedPtGraph<W> {
+    info!("Merging {} edges into the graph", edges.len());
+    let mut updater = PtGraphUpdater::from(graph);
+    for (edge, weight) in edges {
+        updater.add_kmer(&edge, weight);
+    }
+    updater.graph
+}
 
-    fn add_read_fastaq(&mut self, _read: &[u8], _reverse_complement: bool) {
-        unimplemented!();
+/// Recompress all k-mers stored by `PtGraphBuilder` as edges, starting at the
+/// given index of `SEQUENCES`.
+fn kmers_to_edges(first_sequence: Idx) {
+    let mut s = SEQUENCES.write();
+    for idx in cmp::max(first_sequence, 1)..s.len() {
+        let edge = kmer_to_edge(&s[idx]);
+        s.replace(idx, &edge);
     }
+    s.compact();
 }
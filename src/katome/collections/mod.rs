@@ -5,8 +5,11 @@ pub mod girs;
 pub mod graphs;
 // reexport basic collections and traits
 
-pub use self::girs::{GIR, Convert};
+pub use self::girs::{GIR, Convert, ConvertCompacted, ConvertWithMemory};
 pub use self::girs::hm_gir::HmGIR;
 pub use self::girs::hs_gir::HsGIR;
-pub use self::graphs::Graph;
+pub use self::girs::vec_gir::VecGIR;
+pub use self::graphs::{Graph, GraphMut};
+pub use self::graphs::boss::BossGraph;
+pub use self::graphs::csr_graph::CsrGraph;
 pub use self::graphs::pt_graph::PtGraph;
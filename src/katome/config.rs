@@ -1,6 +1,13 @@
 //! Configuration for the assembler.
 
-use std::path::Path;
+use algorithms::bubbles::BubbleParams;
+use algorithms::builder::check_files;
+use algorithms::partitioner::PartitionParams;
+use prelude::EdgeWeight;
+
+use std::cmp;
+use std::env::temp_dir;
+use std::path::{Path, PathBuf};
 
 config_option_enum! {
     /// Format of the input file.
@@ -13,6 +20,35 @@ config_option_enum! {
         BFCounter,
 }
 
+config_option_enum! {
+    /// Storage used for the compressed sequences.
+    SequencesStorage:
+        /// Keep sequences on the heap.
+        Memory,
+        /// Keep sequences in memory-mapped files inside the scratch directory.
+        Mmap,
+}
+
+config_option_enum! {
+    /// Method of counting k-mers during creation of the collection.
+    KmerCounting:
+        /// Count k-mers directly in the collection.
+        InMemory,
+        /// Count k-mers in on-disk buckets partitioned by minimizers, keep only
+        /// the solid ones.
+        Partitioned,
+}
+
+config_option_enum! {
+    /// Number of times each edge is used during the collapse.
+    CollapseMode:
+        /// Use the edge as many times as its (standardized) weight.
+        Weights,
+        /// Use the edge as many times as its copy number inferred with the
+        /// min-cost flow.
+        CopyNumbers,
+}
+
 /// Config for assembler.
 #[derive(Debug, RustcDecodable)]
 pub struct Config<P: AsRef<Path>> {
@@ -34,4 +70,267 @@ pub struct Config<P: AsRef<Path>> {
     ///  usually will create higher quality output. It is highly
     ///  advisable to use that option when using BFCounter file input.
     pub reverse_complement: bool,
+    /// Storage used for the compressed sequences, `Memory` if not specified.
+    ///
+    /// `Mmap` allows graph construction and simplification to exceed the
+    /// physical memory at the cost of page faults.
+    pub sequences_storage: Option<SequencesStorage>,
+    /// Directory for the scratch files. Defaults to the system temporary
+    /// directory.
+    pub scratch_directory: Option<P>,
+    /// Method of counting k-mers, `InMemory` if not specified.
+    pub kmer_counting: Option<KmerCounting>,
+    /// Size of the minimizer used by the `Partitioned` counting. Defaults to 11.
+    pub minimizer_size: Option<usize>,
+    /// Number of on-disk buckets used by the `Partitioned` counting. Defaults
+    /// to 64.
+    pub partitions: Option<usize>,
+    /// Minimal weight of the k-mer kept by the `Partitioned` counting.
+    /// Defaults to 2. If `minimal_weight_threshold` is higher, it is used
+    /// instead.
+    pub solid_kmer_threshold: Option<usize>,
+    /// False positive rate of the Bloom filter pre-pass, which drops k-mers
+    /// occurring only once in the FASTA/FASTQ input. Pre-pass is disabled if
+    /// not specified.
+    pub bloom_filter_fp_rate: Option<f64>,
+    /// Minimal number of occurrences of the solid k-mer, used by the error
+    /// correction pre-pass of the FASTA/FASTQ input. Pre-pass is disabled if
+    /// not specified.
+    pub error_correction_threshold: Option<usize>,
+    /// Whether the graph is created already compacted out of the `VecGIR`,
+    /// with every maximal non-branching path as a single edge. Used only by
+    /// the client, defaults to false.
+    pub compacted_graph: Option<bool>,
+    /// Sizes of the k-mer used by the multi-k assembler, one per round.
+    /// Defaults to `k_mer_size` only.
+    pub k_mer_sizes: Option<Vec<usize>>,
+    /// Weight of the k-mers taken from contigs of the previous round of the
+    /// multi-k assembler. Defaults to 100.
+    pub contig_kmer_weight: Option<usize>,
+    /// Maximal number of k-mers in the popped branch of the bubble. Bubble
+    /// popping is disabled if not specified.
+    pub bubble_max_length: Option<usize>,
+    /// Maximal ratio of the average weight of the popped branch to the kept
+    /// one. Defaults to 1.0.
+    pub bubble_max_coverage_ratio: Option<f64>,
+    /// Minimal similarity of sequences of the popped and the kept branch.
+    /// Defaults to 0.9.
+    pub bubble_min_similarity: Option<f64>,
+    /// Fraction of the weight of the strongest edge of the branching node,
+    /// below which other edges of that node are removed as erroneous
+    /// connections. Has to lie strictly between 0 and 1. Removal is disabled if
+    /// not specified.
+    pub erroneous_connection_fraction: Option<f64>,
+    /// Number of times each edge is used during the collapse, `Weights` if not
+    /// specified.
+    pub collapse_mode: Option<CollapseMode>,
+    /// Ratio by which weight of the outgoing edge of the branching node has to
+    /// exceed weights of other edges of that node, for the contig to continue
+    /// along it. Contigs stop at every branching node if not specified.
+    pub dominant_branch_ratio: Option<f64>,
+    /// Whether cycles with the single entry and the single exit are unrolled
+    /// before the collapse, with the number of repeats estimated from the
+    /// weights. Defaults to false.
+    pub unroll_cycles: Option<bool>,
+    /// Minimal number of reads which have to span the repeat from its incoming
+    /// to its outgoing edge for the repeat to be split. Read threading is
+    /// disabled if not specified, or if the input consists of BFCounter k-mers.
+    pub read_threading_min_support: Option<usize>,
+    /// Minimal total length of the weakly connected component of the graph,
+    /// counted in k-mers. Smaller components are removed before collapsing.
+    pub min_component_length: Option<usize>,
+    /// Minimal total weight of the weakly connected component of the graph.
+    /// Lighter components are removed before collapsing.
+    pub min_component_weight: Option<usize>,
+    /// Two files with the first and the second mates of read pairs, in the
+    /// same order and format as the input files. If specified, contigs are
+    /// scaffolded and the scaffolds are saved next to the output file, along
+    /// with their AGP description.
+    pub paired_input_files: Option<Vec<P>>,
+    /// Minimal number of read pairs linking two contigs, for them to be joined
+    /// in the scaffold. Defaults to 3.
+    pub scaffold_min_links: Option<usize>,
+    /// Maximal difference between the estimated length of the gap in the
+    /// scaffold and the length of the sequence closing it. Gaps are closed
+    /// with unique paths of the graph from before collapsing, if specified.
+    pub gap_closing_tolerance: Option<usize>,
+}
+
+impl<P: AsRef<Path> + Default> Default for Config<P> {
+    /// Creates config without input files, with FASTA input, k-mers of size 40
+    /// and every optional step left at its default.
+    fn default() -> Config<P> {
+        Config {
+            input_files: vec![],
+            input_file_type: InputFileType::Fasta,
+            output_file: P::default(),
+            original_genome_length: 0,
+            minimal_weight_threshold: 0,
+            k_mer_size: 40,
+            reverse_complement: false,
+            sequences_storage: None,
+            scratch_directory: None,
+            kmer_counting: None,
+            minimizer_size: None,
+            partitions: None,
+            solid_kmer_threshold: None,
+            bloom_filter_fp_rate: None,
+            error_correction_threshold: None,
+            compacted_graph: None,
+            k_mer_sizes: None,
+            contig_kmer_weight: None,
+            bubble_max_length: None,
+            bubble_max_coverage_ratio: None,
+            bubble_min_similarity: None,
+            erroneous_connection_fraction: None,
+            collapse_mode: None,
+            dominant_branch_ratio: None,
+            unroll_cycles: None,
+            read_threading_min_support: None,
+            min_component_length: None,
+            min_component_weight: None,
+            paired_input_files: None,
+            scaffold_min_links: None,
+            gap_closing_tolerance: None,
+        }
+    }
+}
+
+impl<P: AsRef<Path>> Config<P> {
+    /// Checks options which would otherwise fail only late in the assembly.
+    /// Panics if any of them is invalid.
+    pub fn validate(&self) {
+        if let Some(ref files) = self.paired_input_files {
+            assert!(files.len() == 2,
+                    "Paired input consists of two files, with the first and the second mates");
+            assert!(self.input_file_type != InputFileType::BFCounter,
+                    "Paired input has to be given as FASTA or FASTQ reads, not BFCounter k-mers");
+            check_files(files);
+        }
+        if self.kmer_counting == Some(KmerCounting::Partitioned) {
+            assert!(self.input_file_type != InputFileType::BFCounter,
+                    "Partitioned k-mer counting supports only FASTA and FASTQ input, BFCounter \
+                     k-mers are already counted");
+            assert!(self.error_correction_threshold.is_none() &&
+                    self.bloom_filter_fp_rate.is_none(),
+                    "Partitioned k-mer counting drops weak k-mers on its own, it can't be \
+                     combined with the error correction or the Bloom filter pre-pass");
+        }
+        assert!(self.error_correction_threshold.is_none() || self.bloom_filter_fp_rate.is_none(),
+                "Error correction and the Bloom filter pre-pass can't be enabled together, \
+                 only one pre-pass drops erroneous k-mers");
+        if let Some(fraction) = self.erroneous_connection_fraction {
+            assert!(fraction > 0.0 && fraction < 1.0,
+                    "erroneous_connection_fraction has to lie between 0 and 1, {} given",
+                    fraction);
+        }
+    }
+
+    /// Gets directory for the scratch files.
+    pub fn scratch_directory(&self) -> PathBuf {
+        match self.scratch_directory {
+            Some(ref d) => d.as_ref().to_path_buf(),
+            None => temp_dir(),
+        }
+    }
+
+    /// Gets sizes of the k-mer used by the consecutive rounds of the multi-k
+    /// assembler, in increasing order.
+    pub fn k_schedule(&self) -> Vec<usize> {
+        let mut sizes = match self.k_mer_sizes {
+            Some(ref s) if !s.is_empty() => s.clone(),
+            _ => vec![self.k_mer_size],
+        };
+        sizes.sort();
+        sizes.dedup();
+        sizes
+    }
+
+    /// Gets criteria of the bubble popping, if it is enabled.
+    pub fn bubble_params(&self) -> Option<BubbleParams> {
+        self.bubble_max_length.map(|length| {
+            BubbleParams {
+                max_length: length,
+                max_coverage_ratio: self.bubble_max_coverage_ratio.unwrap_or(1.0),
+                min_similarity: self.bubble_min_similarity.unwrap_or(0.9),
+            }
+        })
+    }
+
+    /// Gets parameters of the `Partitioned` k-mer counting.
+    pub fn partition_params(&self) -> PartitionParams {
+        PartitionParams {
+            scratch_directory: self.scratch_directory(),
+            minimizer_size: self.minimizer_size.unwrap_or(11),
+            partitions: self.partitions.unwrap_or(64),
+            // the stricter of both thresholds applies
+            solid_threshold: cmp::max(self.solid_kmer_threshold.unwrap_or(2),
+                                      self.minimal_weight_threshold) as EdgeWeight,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::catch_unwind;
+
+    #[test]
+    fn rejects_paired_bfcounter_input() {
+        let mut config = Config::<String>::default();
+        config.input_file_type = InputFileType::BFCounter;
+        config.paired_input_files = Some(vec!["tests/test_files/data1.txt".to_string(),
+                                              "tests/test_files/data1.txt".to_string()]);
+        assert!(catch_unwind(|| config.validate()).is_err());
+    }
+
+    #[test]
+    fn rejects_partitioned_bfcounter_input() {
+        let mut config = Config::<String>::default();
+        config.input_file_type = InputFileType::BFCounter;
+        config.kmer_counting = Some(KmerCounting::Partitioned);
+        assert!(catch_unwind(|| config.validate()).is_err());
+    }
+
+    #[test]
+    fn rejects_partitioned_counting_with_pre_pass() {
+        let mut config = Config::<String>::default();
+        config.kmer_counting = Some(KmerCounting::Partitioned);
+        config.validate();
+        config.error_correction_threshold = Some(3);
+        assert!(catch_unwind(|| config.validate()).is_err());
+        config.error_correction_threshold = None;
+        config.bloom_filter_fp_rate = Some(0.01);
+        assert!(catch_unwind(|| config.validate()).is_err());
+    }
+
+    #[test]
+    fn rejects_error_correction_with_bloom_filter() {
+        let mut config = Config::<String>::default();
+        config.error_correction_threshold = Some(3);
+        config.validate();
+        config.bloom_filter_fp_rate = Some(0.01);
+        assert!(catch_unwind(|| config.validate()).is_err());
+    }
+
+    #[test]
+    fn keeps_stricter_solid_threshold() {
+        let mut config = Config::<String>::default();
+        config.solid_kmer_threshold = Some(3);
+        assert_eq!(config.partition_params().solid_threshold, 3);
+        config.minimal_weight_threshold = 5;
+        assert_eq!(config.partition_params().solid_threshold, 5);
+    }
+
+    #[test]
+    fn rejects_erroneous_connection_fraction_outside_unit_interval() {
+        for &fraction in &[0.0, 1.0, 1.5] {
+            let mut config = Config::<String>::default();
+            config.erroneous_connection_fraction = Some(fraction);
+            assert!(catch_unwind(|| config.validate()).is_err());
+        }
+        let mut config = Config::<String>::default();
+        config.erroneous_connection_fraction = Some(0.1);
+        config.validate();
+    }
 }
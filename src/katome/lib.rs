@@ -10,6 +10,7 @@ extern crate petgraph;
 extern crate metrohash;
 extern crate rustc_serialize;
 extern crate fixedbitset;
+extern crate memmap;
 
 
 #[macro_use]
@@ -17,15 +18,18 @@ mod utils;
 #[macro_use]
 pub mod slices;
 pub mod algorithms;
+pub mod arena;
 
 pub mod asm;
 pub use asm::Assemble;
 pub use asm::basic_assembler::BasicAsm;
+pub use asm::multi_k_assembler::MultiKAsm;
 
 pub mod config;
 pub use config::Config;
 
 pub mod collections;
 pub mod compress;
+pub mod mmap;
 pub mod stats;
 pub mod prelude;
@@ -0,0 +1,257 @@
+//! Growable vector living in a memory-mapped file.
+//!
+//! Used as an out-of-core backend for `Sequences`, so that data which does not
+//! fit in the physical memory is paged out to the scratch file by the operating
+//! system instead of getting the process killed.
+
+use memmap::{Mmap, Protection};
+
+use std::fmt;
+use std::fs::{File, OpenOptions, remove_file};
+use std::io;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::slice;
+use std::sync::atomic::{ATOMIC_USIZE_INIT, AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// number of bytes by which the file grows at least
+const MIN_GROWTH: usize = 1 << 20;
+
+// counter of created scratch files, makes names unique within the process
+static FILE_COUNTER: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// Vector of plain values stored in a memory-mapped scratch file.
+///
+/// The file is removed when the vector is dropped.
+pub struct MmapVec<T: Copy> {
+    dir: PathBuf,
+    path: PathBuf,
+    file: File,
+    map: Option<Mmap>,
+    len: usize,
+    capacity: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Copy> MmapVec<T> {
+    /// Creates empty vector backed by a new file in the given directory.
+    pub fn new<P: AsRef<Path>>(dir: P) -> io::Result<MmapVec<T>> {
+        let dir = dir.as_ref().to_path_buf();
+        let path = dir.join(scratch_file_name("seq"));
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&path)?;
+        Ok(MmapVec {
+            dir: dir,
+            path: path,
+            file: file,
+            map: None,
+            len: 0,
+            capacity: 0,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Creates empty vector backed by a new file in the same directory.
+    pub fn empty_like(&self) -> io::Result<MmapVec<T>> {
+        MmapVec::new(&self.dir)
+    }
+
+    /// Gets path of the backing file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Gets number of elements in the vector.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the vector is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Gets number of elements which fit in the file without remapping.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Appends element to the end of the vector.
+    #[inline]
+    pub fn push(&mut self, value: T) {
+        let len = self.len;
+        self.reserve(1);
+        self.len += 1;
+        self[len] = value;
+    }
+
+    /// Appends all elements of the slice to the end of the vector.
+    #[inline]
+    pub fn extend_from_slice(&mut self, values: &[T]) {
+        let len = self.len;
+        self.reserve(values.len());
+        self.len += values.len();
+        self[len..].copy_from_slice(values);
+    }
+
+    /// Removes the last element.
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        let value = self[self.len - 1];
+        self.len -= 1;
+        Some(value)
+    }
+
+    /// Shortens the vector to the given length.
+    #[inline]
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len {
+            self.len = len;
+        }
+    }
+
+    /// Removes all elements.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Makes sure that at least `additional` elements can be appended without
+    /// remapping the file.
+    pub fn reserve(&mut self, additional: usize) {
+        let required = self.len + additional;
+        if required <= self.capacity {
+            return;
+        }
+        let min_growth = MIN_GROWTH / mem::size_of::<T>();
+        let new_capacity = *[required, 2 * self.capacity, min_growth].iter().max().unwrap();
+        self.remap(new_capacity);
+    }
+
+    /// Truncates the file to the current length of the vector.
+    pub fn shrink_to_fit(&mut self) {
+        let len = self.len;
+        if len < self.capacity {
+            self.remap(len);
+        }
+    }
+
+    fn remap(&mut self, capacity: usize) {
+        // unmap the old region before resizing the file
+        self.map = None;
+        if let Err(why) = self.file.set_len((capacity * mem::size_of::<T>()) as u64) {
+            panic!("Couldn't resize {}: {}", self.path.display(), why);
+        }
+        self.capacity = capacity;
+        if capacity == 0 {
+            return;
+        }
+        self.map = match Mmap::open(&self.file, Protection::ReadWrite) {
+            Ok(m) => Some(m),
+            Err(why) => panic!("Couldn't map {}: {}", self.path.display(), why),
+        };
+    }
+}
+
+/// Creates unique name for the scratch file with the given extension.
+pub fn scratch_file_name(extension: &str) -> String {
+    let time = match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() * 1_000_000_000 + d.subsec_nanos() as u64,
+        Err(_) => 0,
+    };
+    format!("katome_{}_{}.{}",
+            time,
+            FILE_COUNTER.fetch_add(1, Ordering::SeqCst),
+            extension)
+}
+
+impl<T: Copy> Deref for MmapVec<T> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &[T] {
+        match self.map {
+            Some(ref m) => unsafe { slice::from_raw_parts(m.ptr() as *const T, self.len) },
+            None => &[],
+        }
+    }
+}
+
+impl<T: Copy> DerefMut for MmapVec<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [T] {
+        match self.map {
+            Some(ref mut m) => unsafe { slice::from_raw_parts_mut(m.mut_ptr() as *mut T, self.len) },
+            None => &mut [],
+        }
+    }
+}
+
+impl<T: Copy> Drop for MmapVec<T> {
+    fn drop(&mut self) {
+        self.map = None;
+        if let Err(why) = remove_file(&self.path) {
+            warn!("Couldn't remove scratch file {}: {}", self.path.display(), why);
+        }
+    }
+}
+
+impl<T: Copy> fmt::Debug for MmapVec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "MmapVec {{ path: {}, len: {}, capacity: {} }}",
+               self.path.display(),
+               self.len,
+               self.capacity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+    use super::*;
+
+    #[test]
+    fn pushes_and_grows() {
+        let mut v: MmapVec<usize> = MmapVec::new(temp_dir()).unwrap();
+        assert!(v.is_empty());
+        for i in 0..200_000 {
+            v.push(i);
+        }
+        assert_eq!(v.len(), 200_000);
+        assert!(v.capacity() >= 200_000);
+        assert!(v.iter().cloned().eq(0..200_000));
+        assert_eq!(v.pop(), Some(199_999));
+    }
+
+    #[test]
+    fn extends_and_truncates() {
+        let mut v: MmapVec<u8> = MmapVec::new(temp_dir()).unwrap();
+        v.extend_from_slice(&[1, 2, 3, 4]);
+        v[0] = 10;
+        assert_eq!(&v[..], &[10, 2, 3, 4]);
+        v.truncate(2);
+        assert_eq!(&v[..], &[10, 2]);
+        v.shrink_to_fit();
+        assert_eq!(v.capacity(), 2);
+        assert_eq!(&v[..], &[10, 2]);
+    }
+
+    #[test]
+    fn removes_file_on_drop() {
+        let path = {
+            let v: MmapVec<u8> = MmapVec::new(temp_dir()).unwrap();
+            let p = v.path().to_path_buf();
+            assert!(p.exists());
+            p
+        };
+        assert!(!path.exists());
+    }
+}
@@ -1,13 +1,103 @@
 //! Basic type and const values declarations used throughout katome.
 extern crate parking_lot;
 use self::parking_lot::RwLock;
+use arena::SequenceArena;
 use compress::CHARS_PER_CARRIER;
 
+use std::fmt::{Debug, Display};
+use std::ops::{Add, Sub};
+
 /// Index type for both nodes and edges in the graph/gir.
 pub type Idx = usize;
-/// Type for representing weight of the `Edge`.
+/// Default type for representing weight of the `Edge`.
 pub type EdgeWeight = u32;
 
+/// Type which can be used as the weight of the `Edge`.
+///
+/// Narrow integers save memory of the collections, wide integers avoid
+/// overflows for ultra-deep data and floats allow for normalized coverage.
+pub trait Weight
+    : Copy + Default + PartialOrd + Debug + Display + Add<Output = Self> + Sub<Output = Self> {
+    /// Gets zero weight.
+    fn zero() -> Self;
+    /// Gets weight of the single occurrence.
+    fn one() -> Self;
+    /// Adds weights, saturating at the maximal value instead of overflowing.
+    fn saturating_add(self, other: Self) -> Self;
+    /// Converts `f64` to the weight, rounding and saturating if needed.
+    fn from_f64(f64) -> Self;
+    /// Converts weight to `f64`.
+    fn to_f64(self) -> f64;
+    /// Parses weight from the string.
+    fn parse(&str) -> Result<Self, String>;
+}
+
+macro_rules! impl_integer_weight {
+    ($($t:ty),*) => ($(
+        impl Weight for $t {
+            fn zero() -> $t {
+                0
+            }
+
+            fn one() -> $t {
+                1
+            }
+
+            fn saturating_add(self, other: $t) -> $t {
+                <$t>::saturating_add(self, other)
+            }
+
+            fn from_f64(x: f64) -> $t {
+                if x >= <$t>::max_value() as f64 {
+                    <$t>::max_value()
+                }
+                else if x <= 0.0 {
+                    0
+                }
+                else {
+                    x.round() as $t
+                }
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn parse(s: &str) -> Result<$t, String> {
+                s.parse::<$t>().map_err(|e| e.to_string())
+            }
+        }
+    )*)
+}
+
+impl_integer_weight!(u8, u16, u32, u64);
+
+impl Weight for f32 {
+    fn zero() -> f32 {
+        0.0
+    }
+
+    fn one() -> f32 {
+        1.0
+    }
+
+    fn saturating_add(self, other: f32) -> f32 {
+        self + other
+    }
+
+    fn from_f64(x: f64) -> f32 {
+        x as f32
+    }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn parse(s: &str) -> Result<f32, String> {
+        s.parse::<f32>().map_err(|e| e.to_string())
+    }
+}
+
 /// Compressed Data Carrier - single building block for the compressed
 /// representations of sequences.
 ///
@@ -23,8 +113,8 @@ pub static mut K_SIZE: Idx = 40;
 pub static mut K1_SIZE: Idx = 39;
 /// Size of the compressed K1 size, calculated as (`K1_SIZE` / `CHARS_PER_CARRIER`).ceil()
 pub static mut COMPRESSED_K1_SIZE: Idx = 10;
-/// Stores non-repeating k-mers. Boxed slice at index 0 is always used as a temporary value.
-pub type Sequences = Vec<Box<[CDC]>>;
+/// Stores non-repeating k-mers. Sequence at index 0 is always used as a temporary value.
+pub type Sequences = SequenceArena;
 /// Wrapper around `Sequences`, which allows for `lazy_static` initialization.
 pub type LockedSequences = RwLock<Sequences>;
 
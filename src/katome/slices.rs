@@ -24,13 +24,13 @@ impl EdgeSlice {
         let self_idx = self.idx();
         let other_idx = other.idx();
         let mut s = SEQUENCES.write();
-        let other_uncompressed = decompress_edge(&*s[other_idx]);
+        let other_uncompressed = decompress_edge(&s[other_idx]);
         assert!(other_uncompressed.len() > unsafe { K1_SIZE });
-        let tmp = extend_edge(&*s[self_idx], &other_uncompressed[unsafe { K1_SIZE }..]);
+        let tmp = extend_edge(&s[self_idx], &other_uncompressed[unsafe { K1_SIZE }..]);
         // clear other as we won't use it anymore
-        s[other_idx] = Vec::new().into_boxed_slice();
+        s.clear_at(other_idx);
         // swap to the new value
-        s[self_idx] = tmp.into_boxed_slice();
+        s.replace(self_idx, &tmp);
     }
 
     /// Get the remainder of the uncompressed edge. Remainder is slice
@@ -146,7 +146,7 @@ macro_rules! get_slice_node {
 
 macro_rules! get_slice_edge {
     ($i:ident, $s: ident) => {{
-        &*$s[$i.idx()]
+        &$s[$i.idx()]
     }};
 }
 add_impls!(EdgeSlice, NodeSlice);
@@ -256,11 +256,11 @@ mod tests {
                 let compressed_kmer = compress_kmer(&$n);
                 let compressed_shifted_kmer = compress_kmer(&shifted_kmer);
                 let compressed_edge = kmer_to_edge(&compressed_kmer);
-                seq.push(compressed_kmer.clone().into_boxed_slice());
-                seq.push(compressed_kmer.clone().into_boxed_slice());
-                seq.push(compressed_shifted_kmer.clone().into_boxed_slice());
-                seq.push(compressed_edge.clone().into_boxed_slice());
-                seq.push(compressed_edge.clone().into_boxed_slice());
+                seq.push(&compressed_kmer);
+                seq.push(&compressed_kmer);
+                seq.push(&compressed_shifted_kmer);
+                seq.push(&compressed_edge);
+                seq.push(&compressed_edge);
             }
         }
     }
@@ -1,7 +1,9 @@
 //! Various statistics for `Graph`s and `GIR`s.
 
-use collections::{Graph, HmGIR, HsGIR, PtGraph};
-use prelude::EdgeWeight;
+use collections::{BossGraph, CsrGraph, Graph, HmGIR, HsGIR, VecGIR};
+use collections::graphs::Id;
+use collections::graphs::pt_graph::WeightedPtGraph;
+use prelude::{EdgeWeight, Weight};
 use stats::Stats;
 
 use petgraph::EdgeDirection;
@@ -35,13 +37,13 @@ pub struct Counts {
 
 /// Various statistics which are created based on `Graph`s or `GIR`s.
 #[derive(Default, Debug, Copy, Clone)]
-pub struct CollectionStats {
+pub struct CollectionStats<W = EdgeWeight> {
     /// Capacity of the collection.
     pub capacity: (usize, Opt<usize>),
     /// Counts of the collection.
     pub counts: Counts,
     /// Biggest edge weight found in collection.
-    pub max_edge_weight: Opt<EdgeWeight>,
+    pub max_edge_weight: Opt<W>,
     /// Average edge weight.
     pub avg_edge_weight: Opt<f64>,
     /// Maximal number of incoming edges for the node.
@@ -56,9 +58,9 @@ pub struct CollectionStats {
     pub outgoing_vert_count: Opt<usize>,
 }
 
-impl CollectionStats {
+impl<W: Weight> CollectionStats<W> {
     /// Creates `CollectionStats` with supplied counts.
-    pub fn with_counts(node_count_: usize, edge_count_: usize) -> CollectionStats {
+    pub fn with_counts(node_count_: usize, edge_count_: usize) -> CollectionStats<W> {
         let mut stats = CollectionStats::default();
         stats.counts = Counts {
             node_count: node_count_,
@@ -68,9 +70,9 @@ impl CollectionStats {
     }
 }
 
-impl PartialEq for CollectionStats {
+impl<W: Weight> PartialEq for CollectionStats<W> {
     // ignore capacity during comparison
-    fn eq(&self, other: &CollectionStats) -> bool {
+    fn eq(&self, other: &CollectionStats<W>) -> bool {
         self.counts == other.counts && self.max_edge_weight == other.max_edge_weight &&
         round(self.avg_edge_weight) == round(other.avg_edge_weight) &&
         self.max_in_degree == other.max_in_degree &&
@@ -103,7 +105,7 @@ impl Display for Counts {
     }
 }
 
-impl Display for CollectionStats {
+impl<W: Weight> Display for CollectionStats<W> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f,
                  "Collection has the capacity of {}, {} for {}",
@@ -134,14 +136,16 @@ impl Display for CollectionStats {
     }
 }
 
-impl Stats<CollectionStats> for PtGraph {
-    fn stats(&self) -> CollectionStats {
-        let max_weight = self.raw_edges().iter().map(|w| w.weight.1).max().unwrap_or(0);
+impl<W: Weight> Stats<CollectionStats<W>> for WeightedPtGraph<W> {
+    fn stats(&self) -> CollectionStats<W> {
+        let max_weight = self.raw_edges()
+            .iter()
+            .map(|w| w.weight.1)
+            .fold(W::zero(), |m, w| if w > m { w } else { m });
         let avg_edge_weight_ = self.raw_edges()
             .iter()
             .map(|w| w.weight.1)
-            .fold(0_usize, |s, w| s + w as usize) as f64 /
-                               self.edge_count() as f64;
+            .fold(0.0, |s, w| s + w.to_f64()) / self.edge_count() as f64;
         let max_out_degree_ = self.node_indices().map(|n| self.out_degree(n)).max().unwrap_or(0);
         let avg_out_degree_ = (self.node_indices()
             .fold(0_usize, |m, n| m + self.out_degree(n))) as f64 /
@@ -167,8 +171,46 @@ impl Stats<CollectionStats> for PtGraph {
     }
 }
 
-impl Stats<CollectionStats> for HsGIR {
-    fn stats(&self) -> CollectionStats {
+impl<W: Weight> Stats<CollectionStats<W>> for CsrGraph<W> {
+    fn stats(&self) -> CollectionStats<W> {
+        let (node_cap, edge_cap) = self.capacity();
+        read_only_stats(self, (node_cap, Opt::Full(edge_cap)))
+    }
+}
+
+impl<W: Weight> Stats<CollectionStats<W>> for BossGraph<W> {
+    fn stats(&self) -> CollectionStats<W> {
+        read_only_stats(self, (self.node_count(), Opt::Full(self.edge_count())))
+    }
+}
+
+// Gathers statistics with the `Graph` primitives only.
+fn read_only_stats<G: Graph>(graph: &G, capacity: (usize, Opt<usize>))
+                             -> CollectionStats<G::Weight> {
+    let weights = (0..graph.edge_count())
+        .map(|e| unwrap!(graph.weight(G::EdgeIdentifier::new(e))))
+        .collect::<Vec<G::Weight>>();
+    let nodes = || (0..graph.node_count()).map(G::NodeIdentifier::new);
+    CollectionStats {
+        capacity: capacity,
+        counts: Counts {
+            node_count: graph.node_count(),
+            edge_count: graph.edge_count(),
+        },
+        max_edge_weight: Opt::Full(weights.iter()
+            .fold(G::Weight::zero(), |m, &w| if w > m { w } else { m })),
+        avg_edge_weight: Opt::Full(weights.iter().fold(0.0, |s, &w| s + w.to_f64()) /
+                                   graph.edge_count() as f64),
+        max_in_degree: Opt::Full(nodes().map(|n| graph.in_degree(n)).max().unwrap_or(0)),
+        max_out_degree: Opt::Full(nodes().map(|n| graph.out_degree(n)).max().unwrap_or(0)),
+        avg_out_degree: Opt::Full(graph.edge_count() as f64 / graph.node_count() as f64),
+        incoming_vert_count: Opt::Full(nodes().filter(|&n| graph.in_degree(n) == 0).count()),
+        outgoing_vert_count: Opt::Full(nodes().filter(|&n| graph.out_degree(n) == 0).count()),
+    }
+}
+
+impl<W: Weight> Stats<CollectionStats<W>> for HsGIR<W> {
+    fn stats(&self) -> CollectionStats<W> {
         let edge_count_ = self.iter().map(|e| e.edges.outgoing.len()).sum::<usize>();
         CollectionStats {
             capacity: (self.capacity(), Opt::Empty),
@@ -187,8 +229,8 @@ impl Stats<CollectionStats> for HsGIR {
     }
 }
 
-impl Stats<CollectionStats> for HmGIR {
-    fn stats(&self) -> CollectionStats {
+impl<W: Weight> Stats<CollectionStats<W>> for HmGIR<W> {
+    fn stats(&self) -> CollectionStats<W> {
         let edge_count_ = self.values().map(|e| e.len()).sum::<usize>();
         CollectionStats {
             capacity: (self.capacity(), Opt::Empty),
@@ -207,12 +249,31 @@ impl Stats<CollectionStats> for HmGIR {
     }
 }
 
+impl<W: Weight> Stats<CollectionStats<W>> for VecGIR<W> {
+    fn stats(&self) -> CollectionStats<W> {
+        CollectionStats {
+            capacity: (self.capacity(), Opt::Empty),
+            counts: Counts {
+                node_count: self.node_count(),
+                edge_count: self.edge_count(),
+            },
+            max_edge_weight: Opt::Empty,
+            avg_edge_weight: Opt::Empty,
+            max_in_degree: Opt::Empty,
+            max_out_degree: Opt::Empty,
+            avg_out_degree: Opt::Empty,
+            incoming_vert_count: Opt::Empty,
+            outgoing_vert_count: Opt::Empty,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     #[test]
     fn compares_two_collection_stats() {
-        let st = CollectionStats {
+        let st: CollectionStats = CollectionStats {
             capacity: (1024, Opt::Full(1024)),
             counts: Counts {
                 node_count: 1,
@@ -231,7 +292,7 @@ mod tests {
 
     #[test]
     fn detects_difference_in_counts() {
-        let st = CollectionStats {
+        let st: CollectionStats = CollectionStats {
             capacity: (1024, Opt::Full(1024)),
             counts: Counts {
                 node_count: 1,
@@ -256,7 +317,7 @@ mod tests {
 
     #[test]
     fn rounds_floats_properly() {
-        let st = CollectionStats {
+        let st: CollectionStats = CollectionStats {
             capacity: (1024, Opt::Full(1024)),
             counts: Counts {
                 node_count: 1,
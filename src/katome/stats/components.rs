@@ -0,0 +1,29 @@
+//! Statistics of the removal of small components.
+
+use std::fmt;
+use std::fmt::Display;
+
+/// Weakly connected components of the `Graph` before and after the removal of
+/// small ones.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ComponentStats {
+    /// Number of components before the removal.
+    pub components: usize,
+    /// Number of removed components.
+    pub removed_components: usize,
+    /// Number of edges of removed components.
+    pub removed_edges: usize,
+    /// Total length of removed components, counted in k-mers.
+    pub removed_length: usize,
+}
+
+impl Display for ComponentStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f,
+                 "Removed {} out of {} weakly connected components, with {} edges and {} k-mers",
+                 self.removed_components,
+                 self.components,
+                 self.removed_edges,
+                 self.removed_length)
+    }
+}
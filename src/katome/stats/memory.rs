@@ -0,0 +1,108 @@
+//! Memory usage of the collections during conversion between them.
+
+use collections::{HmGIR, HsGIR};
+use collections::girs::edges::{Edge, Outgoing};
+use collections::girs::hs_gir::Vertex;
+use collections::graphs::pt_graph::{Node, WeightedPtGraph};
+use prelude::{Idx, Weight};
+use slices::{EdgeSlice, NodeSlice};
+
+use petgraph::graph::Edge as PtEdge;
+
+use std::fmt;
+use std::fmt::Display;
+use std::mem::size_of;
+
+/// Approximate number of bytes allocated by the collection on the heap.
+pub trait HeapSize {
+    /// Gets number of allocated bytes.
+    fn heap_size(&self) -> usize;
+}
+
+/// Gets number of bytes allocated by the hash table with `capacity` entries of
+/// type `T`. Hashes are stored next to the entries.
+pub fn table_size<T>(capacity: usize) -> usize {
+    capacity * (size_of::<T>() + size_of::<u64>())
+}
+
+impl<W: Weight> HeapSize for HmGIR<W> {
+    fn heap_size(&self) -> usize {
+        table_size::<(NodeSlice, Outgoing<W>)>(self.capacity()) +
+        self.values().map(|o| o.len()).sum::<usize>() * size_of::<Edge<W>>()
+    }
+}
+
+impl<W: Weight> HeapSize for HsGIR<W> {
+    fn heap_size(&self) -> usize {
+        table_size::<Box<Vertex<W>>>(self.capacity()) +
+        self.iter()
+            .map(|v| size_of::<Vertex<W>>() + v.edges.outgoing.len() * size_of::<Edge<W>>())
+            .sum::<usize>()
+    }
+}
+
+impl<W: Weight> HeapSize for WeightedPtGraph<W> {
+    fn heap_size(&self) -> usize {
+        let (node_cap, edge_cap) = self.capacity();
+        node_cap * size_of::<Node>() +
+        edge_cap * size_of::<PtEdge<(EdgeSlice, W), Idx>>()
+    }
+}
+
+/// Tracks memory usage of the conversion.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// Number of bytes used at the beginning of the conversion.
+    pub initial: usize,
+    /// Number of bytes used after the last update.
+    pub current: usize,
+    /// Highest number of bytes seen by the updates.
+    pub peak: usize,
+}
+
+impl MemoryStats {
+    /// Creates tracker with the initial usage.
+    pub fn new(initial: usize) -> MemoryStats {
+        MemoryStats {
+            initial: initial,
+            current: initial,
+            peak: initial,
+        }
+    }
+
+    /// Records current usage.
+    pub fn update(&mut self, current: usize) {
+        self.current = current;
+        if current > self.peak {
+            self.peak = current;
+        }
+    }
+}
+
+impl Display for MemoryStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f,
+                 "Conversion started with {} bytes, ended with {} bytes, peak usage was {} bytes",
+                 self.initial,
+                 self.current,
+                 self.peak)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_peak_usage() {
+        let mut stats = MemoryStats::new(10);
+        stats.update(30);
+        stats.update(20);
+        assert_eq!(stats,
+                   MemoryStats {
+                       initial: 10,
+                       current: 20,
+                       peak: 30,
+                   });
+    }
+}
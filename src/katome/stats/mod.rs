@@ -16,5 +16,11 @@ pub trait Stats<T: Display> {
 
 mod contigs;
 mod collections;
+mod components;
+mod sequences;
+mod memory;
 pub use self::collections::{Opt, CollectionStats, Counts};
+pub use self::components::ComponentStats;
 pub use self::contigs::ContigsStats;
+pub use self::sequences::SequencesStats;
+pub use self::memory::{HeapSize, MemoryStats, table_size};
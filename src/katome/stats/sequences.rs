@@ -0,0 +1,50 @@
+//! Memory statistics for the global storage of sequences.
+
+use prelude::Sequences;
+use stats::Stats;
+
+use std::fmt;
+use std::fmt::Display;
+
+/// Memory usage of `Sequences`.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SequencesStats {
+    /// Number of stored sequences.
+    pub count: usize,
+    /// Number of bytes of compressed data referenced by sequences.
+    pub payload: usize,
+    /// Number of bytes of compressed data which are no longer used.
+    pub wasted: usize,
+    /// Number of bytes allocated by the storage.
+    pub allocated: usize,
+    /// Number of bytes which would be needed to store sequences as separate
+    /// boxed slices.
+    pub boxed: usize,
+}
+
+impl Display for SequencesStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f,
+                 "Storage holds {} sequences with {} bytes of compressed data ({} bytes wasted)",
+                 self.count,
+                 self.payload,
+                 self.wasted)?;
+        writeln!(f,
+                 "Allocated {} bytes, boxed representation would need {} bytes -- saved {:.2}%",
+                 self.allocated,
+                 self.boxed,
+                 (self.boxed as f64 - self.allocated as f64) * 100.0 / self.boxed as f64)
+    }
+}
+
+impl Stats<SequencesStats> for Sequences {
+    fn stats(&self) -> SequencesStats {
+        SequencesStats {
+            count: self.len(),
+            payload: self.payload_size(),
+            wasted: self.wasted_size(),
+            allocated: self.heap_size(),
+            boxed: self.boxed_size(),
+        }
+    }
+}
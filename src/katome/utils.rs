@@ -48,3 +48,29 @@ macro_rules! config_option_enum {
         impl_enum_decodable!($e, $( $x ),+);
     }
 }
+
+/// Creates reverse complement of the uncompressed sequence.
+pub fn reverse_complement_of(sequence: &[u8]) -> Vec<u8> {
+    sequence.iter()
+        .rev()
+        .map(|&c| {
+            match c {
+                b'A' => b'T',
+                b'C' => b'G',
+                b'G' => b'C',
+                b'T' => b'A',
+                x => x,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn creates_reverse_complement() {
+        assert_eq!(reverse_complement_of(b"AACGT"), b"ACGTT".to_vec());
+    }
+}
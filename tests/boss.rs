@@ -0,0 +1,63 @@
+#[macro_use]
+extern crate lazy_static;
+extern crate katome;
+
+pub use katome::config::InputFileType;
+pub use katome::algorithms::builder::Build;
+pub use katome::asm::SEQUENCES;
+pub use katome::asm::lock::LOCK;
+pub use katome::collections::{BossGraph, PtGraph};
+pub use katome::prelude::set_global_k_sizes;
+pub use katome::stats::{CollectionStats, Stats};
+pub use std::sync::Mutex;
+pub use std::panic::catch_unwind;
+
+macro_rules! before_each {
+    ($l:ident, $f:ident) => {
+        // get global lock over sequences for testing
+        let $l = LOCK.lock().unwrap();
+        // Clear up SEQUENCES
+        {
+            let mut s = SEQUENCES.write();
+            s.clear();
+            s.push(&[]);
+        }
+        unsafe { set_global_k_sizes(40); }
+        let $f = vec![
+            "./tests/test_files/data1.txt".to_string(),
+            "./tests/test_files/data2.txt".to_string(),
+            "./tests/test_files/data3.txt".to_string(),
+        ];
+    }
+}
+
+macro_rules! matches_pt_graph {
+    ($i: expr, $rc: expr, $n: ident) => {
+        #[test]
+        fn $n() {
+            let result = {
+                before_each!(_l, filenames);
+                catch_unwind(|| {
+                    let (graph, _) =
+                        PtGraph::create(&filenames[$i..$i+1], InputFileType::Fastq, $rc, 0);
+                    let stats: CollectionStats = graph.stats();
+                    drop(graph);
+                    {
+                        let mut s = SEQUENCES.write();
+                        s.clear();
+                        s.push(&[]);
+                    }
+                    let (boss, _) =
+                        BossGraph::create(&filenames[$i..$i+1], InputFileType::Fastq, $rc, 0);
+                    assert_eq!(stats, boss.stats());
+                })
+            };
+            assert!(result.is_ok());
+        }
+    }
+}
+
+matches_pt_graph!(0, false, matches_data1);
+matches_pt_graph!(1, false, matches_data2);
+matches_pt_graph!(2, false, matches_data3);
+matches_pt_graph!(0, true, matches_data1_with_reverse_complements);
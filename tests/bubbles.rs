@@ -0,0 +1,78 @@
+#[macro_use]
+extern crate lazy_static;
+extern crate katome;
+
+#[macro_use]
+mod common;
+
+pub use common::random_sequence;
+pub use katome::config::InputFileType;
+pub use katome::algorithms::builder::Build;
+pub use katome::algorithms::bubbles::{BubbleParams, Poppable};
+pub use katome::algorithms::collapser::Collapsable;
+pub use katome::collections::PtGraph;
+pub use katome::stats::{Opt, Stats};
+pub use std::fs::{File, remove_file};
+pub use std::io::Write;
+pub use std::path::Path;
+pub use std::sync::Mutex;
+pub use std::panic::catch_unwind;
+
+// Pseudorandom sequence of 200 nucleotides.
+fn reference() -> String {
+    random_sequence(42, 200)
+}
+
+// Writes 9 error-free reads and one read with the substitution in the middle.
+fn write_reads(reference: &str, path: &Path) {
+    let mut variant = reference.as_bytes().to_vec();
+    variant[100] = if variant[100] == b'A' { b'C' } else { b'A' };
+    let mut file = File::create(path).unwrap();
+    for i in 0..9 {
+        writeln!(file, ">read{}\n{}", i, reference).unwrap();
+    }
+    writeln!(file, ">variant\n{}", String::from_utf8(variant).unwrap()).unwrap();
+}
+
+fn params() -> BubbleParams {
+    BubbleParams {
+        max_length: 80,
+        max_coverage_ratio: 1.0,
+        min_similarity: 0.9,
+    }
+}
+
+macro_rules! pops {
+    ($p:expr, $c:expr, $n:ident) => {
+        #[test]
+        fn $n() {
+            let result = {
+                before_each!(_l, reference, path);
+                let result = catch_unwind(|| {
+                    let (mut graph, _) = PtGraph::create(&[&path], InputFileType::Fasta, false, 0);
+                    assert_eq!(graph.edge_count(), 161 + 40);
+                    assert_eq!(graph.pop_bubbles(&$p), $c);
+                    if $c > 0 {
+                        assert_eq!(graph.edge_count(), 161);
+                        assert_eq!(graph.stats().max_edge_weight, Opt::Full(10));
+                        // every traversal of the path decreases its weight by one
+                        let contigs = graph.collapse();
+                        assert_eq!(contigs.len(), 10);
+                        assert!(contigs.iter().all(|c| *c == reference));
+                    }
+                    else {
+                        assert_eq!(graph.edge_count(), 161 + 40);
+                    }
+                });
+                remove_file(&path).unwrap();
+                result
+            };
+            assert!(result.is_ok());
+        }
+    }
+}
+
+pops!(params(), 1, pops_snp_bubble);
+pops!(BubbleParams { max_length: 10, ..params() }, 0, keeps_long_bubble);
+pops!(BubbleParams { max_coverage_ratio: 0.05, ..params() }, 0, keeps_covered_branch);
+pops!(BubbleParams { min_similarity: 1.0, ..params() }, 0, keeps_dissimilar_branch);
@@ -7,7 +7,8 @@ pub use katome::config::InputFileType;
 pub use katome::algorithms::builder::Build;
 pub use katome::asm::SEQUENCES;
 pub use katome::asm::lock::LOCK;
-pub use katome::collections::{HmGIR, HsGIR, PtGraph};
+pub use katome::collections::{HmGIR, HsGIR, PtGraph, VecGIR};
+pub use katome::collections::graphs::pt_graph::WeightedPtGraph;
 pub use katome::prelude::set_global_k_sizes;
 pub use katome::stats::{Counts, CollectionStats, Stats, Opt};
 pub use std::sync::Mutex;
@@ -21,7 +22,7 @@ macro_rules! before_each {
         {
             let mut s = SEQUENCES.write();
             s.clear();
-            s.push(vec![].into_boxed_slice());
+            s.push(&[]);
         }
         unsafe { set_global_k_sizes(40); }
         let $r = vec![200, 9200, 23300];
@@ -98,7 +99,7 @@ macro_rules! build_data_gir {
                 before_each!(_l, read_bytes, counts, filenames);
                 catch_unwind(|| {
                     setup_gir!(counts, stats);
-                    let (gir, number_of_read_bytes) = $t::create(&filenames[$i..$i+1], InputFileType::Fastq, false, 0);
+                    let (gir, number_of_read_bytes) = <$t>::create(&filenames[$i..$i+1], InputFileType::Fastq, false, 0);
                     assert_eq!(number_of_read_bytes, read_bytes[$i]);
                     assert_eq!(stats[$i], gir.stats());
                 })
@@ -132,13 +133,36 @@ macro_rules! fail_build {
         fn $n() {
             before_each!(_l, _read_bytes, _counts, filenames);
             let result = catch_unwind(|| {
-                $t::create(&filenames[$i..$i+1], InputFileType::Fastq, false, 0);
+                <$t>::create(&filenames[$i..$i+1], InputFileType::Fastq, false, 0);
             });
             assert!(result.is_err());
         }
     }
 }
 
+macro_rules! build_weighted_graph {
+    ($w:ty, $n:ident) => {
+        #[test]
+        fn $n() {
+            let result = {
+                before_each!(_l, _read_bytes, counts, filenames);
+                catch_unwind(|| {
+                    let (graph, _) = PtGraph::create(&filenames[..1], InputFileType::Fastq, false, 0);
+                    let (weighted, _) = WeightedPtGraph::<$w>::create(&filenames[..1], InputFileType::Fastq,
+                                                                      false, 0 as $w);
+                    let stats = graph.stats();
+                    let weighted_stats = weighted.stats();
+                    assert_eq!(weighted_stats.counts.node_count, counts[0].0);
+                    assert_eq!(weighted_stats.counts.edge_count, counts[0].1);
+                    assert_eq!(weighted_stats.max_edge_weight, Opt::Full(2 as $w));
+                    assert_eq!(weighted_stats.avg_edge_weight, stats.avg_edge_weight);
+                })
+            };
+            assert!(result.is_ok());
+        }
+    }
+}
+
 macro_rules! test_gir {
     ($t:tt, $i:ident) => {
         mod $i {
@@ -168,5 +192,12 @@ mod build {
     pub use super::*;
     test_gir!(HmGIR, hm_gir);
     test_gir!(HsGIR, hs_gir);
+    test_gir!(VecGIR, vec_gir);
     test_graph!(PtGraph, pt_graph);
+
+    mod weighted_pt_graph {
+        use super::*;
+        build_weighted_graph!(u16, builds_with_u16_weights);
+        build_weighted_graph!(f32, builds_with_f32_weights);
+    }
 }
@@ -6,7 +6,7 @@ pub use katome::config::InputFileType;
 pub use katome::algorithms::builder::Build;
 pub use katome::asm::SEQUENCES;
 pub use katome::asm::lock::LOCK;
-pub use katome::collections::{Convert, HmGIR, HsGIR, PtGraph};
+pub use katome::collections::{Convert, HmGIR, HsGIR, PtGraph, VecGIR};
 pub use katome::prelude::{set_global_k_sizes};
 pub use katome::algorithms::collapser::Collapsable;
 pub use katome::stats::{Counts, CollectionStats, Stats, Opt};
@@ -21,7 +21,7 @@ macro_rules! before_each {
         {
             let mut s = SEQUENCES.write();
             s.clear();
-            s.push(vec![].into_boxed_slice());
+            s.push(&[]);
         }
         unsafe { set_global_k_sizes(40); }
         let $f = vec![
@@ -98,4 +98,5 @@ mod collapse {
     test_graph!(PtGraph, pt_graph);
     test_gir!(HsGIR, PtGraph, hs_gir);
     test_gir!(HmGIR, PtGraph, hm_gir);
+    test_gir!(VecGIR, PtGraph, vec_gir);
 }
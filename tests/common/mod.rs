@@ -0,0 +1,80 @@
+//! Fixtures shared by the tests which assemble synthetic reads.
+#![allow(dead_code)]
+
+use katome::asm::SEQUENCES;
+use katome::asm::lock::LOCK;
+use katome::prelude::set_global_k_sizes;
+
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::MutexGuard;
+
+/// Locks the global lock over sequences, binds `$r` to `reference()` and
+/// writes reads of it with `write_reads` into the temporary file `$f`. Both
+/// functions are taken from the calling test file.
+macro_rules! before_each {
+    ($l:ident, $r:ident, $f:ident) => {
+        let $l = common::lock_sequences();
+        let $r = reference();
+        let $f = common::temp_file(file!(), line!(), ".fa");
+        write_reads(&$r, &$f);
+    }
+}
+
+/// Gets global lock over sequences for testing, clears them up and sets size
+/// of the k-mer to 40.
+pub fn lock_sequences() -> MutexGuard<'static, ()> {
+    let lock = LOCK.lock().unwrap();
+    {
+        let mut s = SEQUENCES.write();
+        s.clear();
+        s.push(&[]);
+    }
+    unsafe { set_global_k_sizes(40); }
+    lock
+}
+
+/// Gets path of the temporary file named after the test file and the line of
+/// the test.
+pub fn temp_file(source: &str, line: u32, suffix: &str) -> PathBuf {
+    let name = Path::new(source).file_stem().unwrap().to_str().unwrap();
+    env::temp_dir().join(format!("katome_{}_{}{}", name, line, suffix))
+}
+
+/// Pseudorandom sequence of the given length.
+pub fn random_sequence(seed: u64, length: usize) -> String {
+    let mut x = seed;
+    (0..length)
+        .map(|_| {
+            x = x.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ['A', 'C', 'G', 'T'][(x >> 33) as usize & 3]
+        })
+        .collect()
+}
+
+/// Writes 10 error-free reads of each of the sequences.
+pub fn write_reads<S: AsRef<str>>(references: &[S], path: &Path) {
+    let mut file = File::create(path).unwrap();
+    for (i, reference) in references.iter().enumerate() {
+        for j in 0..10 {
+            writeln!(file, ">read{}_{}\n{}", i, j, reference.as_ref()).unwrap();
+        }
+    }
+}
+
+/// Reverse complement of the sequence.
+pub fn reverse_complement_of(sequence: &str) -> String {
+    sequence.chars()
+        .rev()
+        .map(|c| {
+            match c {
+                'A' => 'T',
+                'C' => 'G',
+                'G' => 'C',
+                _ => 'A',
+            }
+        })
+        .collect()
+}
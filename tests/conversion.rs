@@ -5,12 +5,16 @@ extern crate lazy_static;
 extern crate katome;
 
 pub use katome::config::InputFileType;
-pub use katome::algorithms::builder::Build;
+pub use katome::algorithms::builder::{Build, create_builder};
 pub use katome::asm::SEQUENCES;
 pub use katome::asm::lock::LOCK;
-pub use katome::collections::{Convert, HmGIR, HsGIR, PtGraph};
+pub use katome::collections::{Convert, ConvertCompacted, ConvertWithMemory, HmGIR, HsGIR,
+                              PtGraph, VecGIR};
+pub use katome::collections::graphs::pt_graph::PtGraphBuilder;
+pub use katome::prelude::EdgeWeight;
 pub use katome::prelude::set_global_k_sizes;
-pub use katome::stats::{Counts, CollectionStats, Stats, Opt};
+pub use katome::stats::{Counts, CollectionStats, HeapSize, Stats, Opt};
+pub use std::path::PathBuf;
 pub use std::sync::Mutex;
 pub use std::panic::catch_unwind;
 
@@ -23,7 +27,7 @@ macro_rules! before_each {
         {
             let mut s = SEQUENCES.write();
             s.clear();
-            s.push(vec![].into_boxed_slice());
+            s.push(&[]);
         }
         unsafe { set_global_k_sizes(40); }
         let counts = vec![(62, 61), (5704, 5612), (14446, 14213)];
@@ -98,6 +102,55 @@ macro_rules! converts_gir {
     }
 }
 
+macro_rules! converts_gir_compacted {
+    ($t:tt, $g:tt, $i:expr, $n:ident) => {
+        #[test]
+        fn $n() {
+            let result = {
+                before_each!(_l, filenames, stats);
+                catch_unwind(|| {
+                    let (gir, _) = $t::create(&filenames[$i..$i+1], InputFileType::Fastq, false, 0);
+                    let graph = $g::create_compacted_from(gir);
+                    // every input file consists of straight paths only
+                    let paths = stats[$i].counts.node_count - stats[$i].counts.edge_count;
+                    let compacted = graph.stats();
+                    assert_eq!(compacted.counts, Counts { node_count: 2 * paths, edge_count: paths });
+                    assert_eq!(compacted.max_edge_weight, stats[$i].max_edge_weight);
+                })
+            };
+            assert!(result.is_ok());
+        }
+    }
+}
+
+macro_rules! converts_gir_within_memory {
+    ($t:tt, $g:tt, $i:expr, $n:ident) => {
+        #[test]
+        fn $n() {
+            let result = {
+                before_each!(_l, filenames, _stats);
+                catch_unwind(|| {
+                    let files = vec![PathBuf::from(&filenames[$i])];
+                    let direct_peak = {
+                        let (builder, _) = create_builder::<PtGraphBuilder<EdgeWeight>>(
+                            &files, InputFileType::Fastq, false, 0);
+                        builder.heap_size() + SEQUENCES.read().heap_size()
+                    };
+                    {
+                        let mut s = SEQUENCES.write();
+                        s.clear();
+                        s.push(&[]);
+                    }
+                    let (gir, _) = $t::create(&filenames[$i..$i+1], InputFileType::Fastq, false, 0);
+                    let (_, memory) = $g::create_with_memory_from(gir);
+                    assert!(memory.peak <= direct_peak);
+                })
+            };
+            assert!(result.is_ok());
+        }
+    }
+}
+
 macro_rules! test_gir {
     ($t:tt, $g:tt, $i:ident) => {
         mod $i {
@@ -105,6 +158,20 @@ macro_rules! test_gir {
             converts_gir!($t, $g, 0, converts1);
             converts_gir!($t, $g, 1, converts2);
             converts_gir!($t, $g, 2, converts3);
+            converts_gir_compacted!($t, $g, 0, converts_compacted1);
+            converts_gir_compacted!($t, $g, 1, converts_compacted2);
+            converts_gir_compacted!($t, $g, 2, converts_compacted3);
+        }
+    }
+}
+
+macro_rules! test_gir_memory {
+    ($t:tt, $g:tt, $i:ident) => {
+        mod $i {
+            use super::*;
+            converts_gir_within_memory!($t, $g, 0, converts_within_memory1);
+            converts_gir_within_memory!($t, $g, 1, converts_within_memory2);
+            converts_gir_within_memory!($t, $g, 2, converts_within_memory3);
         }
     }
 }
@@ -114,4 +181,7 @@ mod conversion {
     pub use super::*;
     test_gir!(HsGIR, PtGraph, hs_gir);
     test_gir!(HmGIR, PtGraph, hm_gir);
+    test_gir!(VecGIR, PtGraph, vec_gir);
+    test_gir_memory!(HsGIR, PtGraph, hs_gir_memory);
+    test_gir_memory!(HmGIR, PtGraph, hm_gir_memory);
 }
@@ -0,0 +1,71 @@
+#[macro_use]
+extern crate lazy_static;
+extern crate katome;
+
+#[macro_use]
+mod common;
+
+pub use common::random_sequence;
+pub use katome::config::{CollapseMode, InputFileType};
+pub use katome::algorithms::builder::Build;
+pub use katome::algorithms::collapser::Collapsable;
+pub use katome::algorithms::copy_number::{copy_numbers, unit_coverage};
+pub use katome::algorithms::shrinker::Shrinkable;
+pub use katome::collections::PtGraph;
+pub use std::fs::remove_file;
+pub use std::path::Path;
+pub use std::sync::Mutex;
+pub use std::panic::catch_unwind;
+
+// Sequence with the repeat occurring twice: a + r + b + r + c.
+fn reference() -> String {
+    let r = random_sequence(1, 80);
+    format!("{}{}{}{}{}",
+            random_sequence(2, 60),
+            r,
+            random_sequence(3, 60),
+            r,
+            random_sequence(4, 60))
+}
+
+fn write_reads(reference: &str, path: &Path) {
+    common::write_reads(&[reference], path);
+}
+
+#[test]
+fn infers_copy_numbers_of_repeat() {
+    let result = {
+        before_each!(_l, _reference, path);
+        let result = catch_unwind(|| {
+            let (mut graph, _) = PtGraph::create(&[&path], InputFileType::Fasta, false, 0);
+            graph.shrink();
+            assert_eq!(graph.edge_count(), 4);
+            assert_eq!(unit_coverage(&graph), 10.0);
+            let copies = copy_numbers(&graph, 10.0);
+            for (edge, copy_number) in graph.raw_edges().iter().zip(copies) {
+                assert_eq!(copy_number, if edge.weight.1 == 20 { 2 } else { 1 });
+            }
+        });
+        remove_file(&path).unwrap();
+        result
+    };
+    assert!(result.is_ok());
+}
+
+#[test]
+fn collapses_with_copy_numbers() {
+    let result = {
+        before_each!(_l, reference, path);
+        let result = catch_unwind(|| {
+            let (graph, _) = PtGraph::create(&[&path], InputFileType::Fasta, false, 0);
+            // the repeat forms the cycle with b, entered from a and left to c
+            let (contigs, _) = graph.collapse_in_mode(CollapseMode::CopyNumbers, None, true);
+            // every edge is used once, the repeat twice
+            assert!(!contigs.is_empty() && contigs.len() <= 5);
+            assert!(contigs.iter().all(|c| reference.contains(c.as_str())));
+        });
+        remove_file(&path).unwrap();
+        result
+    };
+    assert!(result.is_ok());
+}
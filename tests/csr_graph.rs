@@ -0,0 +1,101 @@
+#[macro_use]
+extern crate lazy_static;
+extern crate katome;
+
+pub use katome::config::InputFileType;
+pub use katome::algorithms::builder::Build;
+pub use katome::asm::SEQUENCES;
+pub use katome::asm::lock::LOCK;
+pub use katome::collections::{ConvertCompacted, CsrGraph, PtGraph, VecGIR};
+pub use katome::collections::graphs::csr_graph::write_to_gfa;
+pub use katome::prelude::set_global_k_sizes;
+pub use katome::stats::{CollectionStats, Stats};
+pub use std::env;
+pub use std::fs::{File, remove_file};
+pub use std::io::Read;
+pub use std::sync::Mutex;
+pub use std::panic::catch_unwind;
+
+macro_rules! before_each {
+    ($l:ident, $f:ident) => {
+        // get global lock over sequences for testing
+        let $l = LOCK.lock().unwrap();
+        // Clear up SEQUENCES
+        {
+            let mut s = SEQUENCES.write();
+            s.clear();
+            s.push(&[]);
+        }
+        unsafe { set_global_k_sizes(40); }
+        let $f = vec![
+            "./tests/test_files/data1.txt".to_string(),
+            "./tests/test_files/data2.txt".to_string(),
+            "./tests/test_files/data3.txt".to_string(),
+        ];
+    }
+}
+
+macro_rules! converts_pt_graph {
+    ($i: expr, $n: ident) => {
+        #[test]
+        fn $n() {
+            let result = {
+                before_each!(_l, filenames);
+                catch_unwind(|| {
+                    let (graph, _) =
+                        PtGraph::create(&filenames[$i..$i+1], InputFileType::Fastq, false, 0);
+                    let stats: CollectionStats = graph.stats();
+                    let csr = CsrGraph::from(graph);
+                    assert_eq!(stats, csr.stats());
+                    let contigs = csr.contigs();
+                    assert!(!contigs.is_empty());
+                    assert!(contigs.iter().all(|c| c.len() >= 40));
+                })
+            };
+            assert!(result.is_ok());
+        }
+    }
+}
+
+converts_pt_graph!(0, converts_data1);
+converts_pt_graph!(1, converts_data2);
+converts_pt_graph!(2, converts_data3);
+
+#[test]
+fn writes_gfa() {
+    let result = {
+        before_each!(_l, filenames);
+        catch_unwind(|| {
+            let (graph, _) = <CsrGraph>::create(&filenames[..1], InputFileType::Fastq, false, 0);
+            let path = env::temp_dir().join("katome_csr_graph_test.gfa");
+            write_to_gfa(&graph, path.to_str().unwrap());
+            let mut gfa = String::new();
+            File::open(&path).unwrap().read_to_string(&mut gfa).unwrap();
+            remove_file(&path).unwrap();
+            let segments = gfa.lines().filter(|l| l.starts_with("S\t")).count();
+            assert_eq!(segments, graph.stats().counts.edge_count);
+        })
+    };
+    assert!(result.is_ok());
+}
+
+#[test]
+fn writes_total_kmer_count_of_compacted_edges() {
+    let result = {
+        before_each!(_l, filenames);
+        catch_unwind(|| {
+            // two copies of the read, 61 k-mers long
+            let (gir, _) = <VecGIR>::create(&filenames[..1], InputFileType::Fastq, false, 0);
+            let graph = CsrGraph::from(<PtGraph>::create_compacted_from(gir));
+            let path = env::temp_dir().join("katome_csr_graph_compacted_test.gfa");
+            write_to_gfa(&graph, path.to_str().unwrap());
+            let mut gfa = String::new();
+            File::open(&path).unwrap().read_to_string(&mut gfa).unwrap();
+            remove_file(&path).unwrap();
+            let segments = gfa.lines().filter(|l| l.starts_with("S\t")).collect::<Vec<_>>();
+            assert_eq!(segments.len(), 1);
+            assert!(segments[0].ends_with("\tKC:i:122"));
+        })
+    };
+    assert!(result.is_ok());
+}
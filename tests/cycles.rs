@@ -0,0 +1,73 @@
+#[macro_use]
+extern crate lazy_static;
+extern crate katome;
+
+#[macro_use]
+mod common;
+
+pub use common::random_sequence;
+pub use katome::config::{CollapseMode, InputFileType};
+pub use katome::algorithms::builder::Build;
+pub use katome::algorithms::collapser::Collapsable;
+pub use katome::collections::PtGraph;
+pub use std::fs::remove_file;
+pub use std::path::Path;
+pub use std::sync::Mutex;
+pub use std::panic::catch_unwind;
+
+// Sequence with the tandem repeat: a + r + r + r + c.
+fn reference() -> String {
+    let r = random_sequence(1, 50);
+    format!("{}{}{}{}{}",
+            random_sequence(2, 60),
+            r,
+            r,
+            r,
+            random_sequence(3, 60))
+}
+
+fn write_reads(reference: &str, path: &Path) {
+    common::write_reads(&[reference], path);
+}
+
+macro_rules! unrolls {
+    ($m:expr, $c:expr, $n:ident) => {
+        #[test]
+        fn $n() {
+            let result = {
+                before_each!(_l, reference, path);
+                let result = catch_unwind(|| {
+                    let (graph, _) = PtGraph::create(&[&path], InputFileType::Fasta, false, 0);
+                    let (contigs, estimated) = graph.collapse_in_mode($m, None, true);
+                    // number of repeats is estimated from weights of the cycle,
+                    // each copy of the contig traverses the unrolled entry
+                    assert_eq!(contigs, vec![reference.clone(); $c]);
+                    assert_eq!(estimated, (0..$c).collect::<Vec<usize>>());
+                });
+                remove_file(&path).unwrap();
+                result
+            };
+            assert!(result.is_ok());
+        }
+    }
+}
+
+unrolls!(CollapseMode::Weights, 10, unrolls_tandem_repeat);
+unrolls!(CollapseMode::CopyNumbers, 1, unrolls_tandem_repeat_with_copy_numbers);
+
+#[test]
+fn keeps_cycles_unless_asked() {
+    let result = {
+        before_each!(_l, reference, path);
+        let result = catch_unwind(|| {
+            let (graph, _) = PtGraph::create(&[&path], InputFileType::Fasta, false, 0);
+            let (contigs, estimated) =
+                graph.collapse_in_mode(CollapseMode::CopyNumbers, None, false);
+            assert!(!contigs.contains(&reference));
+            assert!(estimated.is_empty());
+        });
+        remove_file(&path).unwrap();
+        result
+    };
+    assert!(result.is_ok());
+}
@@ -0,0 +1,100 @@
+#[macro_use]
+extern crate lazy_static;
+extern crate katome;
+
+pub use katome::config::InputFileType;
+pub use katome::algorithms::estimator::{estimate_size, estimate_size_from_sample};
+pub use katome::asm::lock::LOCK;
+pub use katome::prelude::set_global_k_sizes;
+pub use std::env;
+pub use std::fs::{File, remove_file};
+pub use std::io::{Read, Write};
+pub use std::path::PathBuf;
+pub use std::sync::Mutex;
+pub use std::panic::catch_unwind;
+
+macro_rules! before_each {
+    ($l:ident, $c:ident, $f:ident) => {
+        // get global lock over k sizes for testing
+        let $l = LOCK.lock().unwrap();
+        unsafe { set_global_k_sizes(40); }
+        let $c = vec![(62, 61), (5704, 5612), (14446, 14213)];
+        let $f = vec![
+            PathBuf::from("./tests/test_files/data1.txt"),
+            PathBuf::from("./tests/test_files/data2.txt"),
+            PathBuf::from("./tests/test_files/data3.txt"),
+        ];
+    }
+}
+
+macro_rules! estimates_size {
+    ($i: expr, $n: ident) => {
+        #[test]
+        fn $n() {
+            let result = {
+                before_each!(_l, counts, filenames);
+                catch_unwind(|| {
+                    let estimate =
+                        estimate_size(&filenames[$i..$i+1], InputFileType::Fastq, false);
+                    let (nodes, kmers) = counts[$i];
+                    let error = |e: usize, c: usize| (e as f64 - c as f64).abs() / c as f64;
+                    assert!(error(estimate.kmers, kmers) < 0.05);
+                    assert!(error(estimate.nodes, nodes) < 0.05);
+                })
+            };
+            assert!(result.is_ok());
+        }
+    }
+}
+
+estimates_size!(0, estimates_data1);
+estimates_size!(1, estimates_data2);
+estimates_size!(2, estimates_data3);
+
+#[test]
+fn saturates_on_repeated_input() {
+    let result = {
+        before_each!(_l, counts, filenames);
+        catch_unwind(|| {
+            // the same reads over and over, parts of the sample cover every read a
+            // few times
+            let mut reads = vec![];
+            File::open(&filenames[2]).unwrap().read_to_end(&mut reads).unwrap();
+            let path = env::temp_dir().join("katome_estimator_repeated.txt");
+            {
+                let mut file = File::create(&path).unwrap();
+                for _ in 0..20 {
+                    file.write_all(&reads).unwrap();
+                }
+            }
+            let estimate = estimate_size_from_sample(&[path.clone()],
+                                                     InputFileType::Fastq,
+                                                     false,
+                                                     8 * reads.len());
+            remove_file(&path).unwrap();
+            let (nodes, kmers) = counts[2];
+            let error = |e: usize, c: usize| (e as f64 - c as f64).abs() / c as f64;
+            assert!(error(estimate.kmers, kmers) < 0.1);
+            assert!(error(estimate.nodes, nodes) < 0.1);
+        })
+    };
+    assert!(result.is_ok());
+}
+
+#[test]
+fn samples_across_input() {
+    let result = {
+        before_each!(_l, counts, filenames);
+        catch_unwind(|| {
+            let estimate = estimate_size_from_sample(&filenames[2..3],
+                                                     InputFileType::Fastq,
+                                                     false,
+                                                     20_000);
+            let (nodes, kmers) = counts[2];
+            let error = |e: usize, c: usize| (e as f64 - c as f64).abs() / c as f64;
+            assert!(error(estimate.kmers, kmers) < 0.1);
+            assert!(error(estimate.nodes, nodes) < 0.1);
+        })
+    };
+    assert!(result.is_ok());
+}
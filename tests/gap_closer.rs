@@ -0,0 +1,124 @@
+#[macro_use]
+extern crate lazy_static;
+extern crate katome;
+
+mod common;
+
+pub use common::{random_sequence, reverse_complement_of, write_reads};
+pub use katome::config::InputFileType;
+pub use katome::algorithms::builder::Build;
+pub use katome::algorithms::collapser::Collapsable;
+pub use katome::algorithms::gap_closer::{close_gaps, copy_graph};
+pub use katome::algorithms::scaffolder::{ScaffoldPart, Scaffolds};
+pub use katome::collections::PtGraph;
+pub use std::fs::remove_file;
+pub use std::sync::Mutex;
+pub use std::panic::catch_unwind;
+
+macro_rules! before_each {
+    ($l:ident, $r:ident, $f:ident) => {
+        let $l = common::lock_sequences();
+        let $r = random_sequence(1, 500);
+        let $f = common::temp_file(file!(), line!(), ".fa");
+    }
+}
+
+// Contigs with the gap of length 60 between them.
+fn contigs(reference: &str) -> Vec<String> {
+    vec![reference[..200].to_string(), reference[260..].to_string()]
+}
+
+fn scaffolds(parts: Vec<ScaffoldPart>) -> Scaffolds {
+    Scaffolds {
+        insert_size: Some(150.0),
+        scaffolds: vec![parts],
+    }
+}
+
+#[test]
+fn closes_gap_with_unique_path() {
+    let result = {
+        before_each!(_l, reference, path);
+        write_reads(&[&reference], &path);
+        let result = catch_unwind(|| {
+            let (graph, _) = PtGraph::create(&[&path], InputFileType::Fasta, false, 0);
+            let copy = copy_graph(&graph);
+            // collapsing doesn't affect the copy
+            graph.collapse();
+            let contigs = contigs(&reference);
+            let mut scaffolds = scaffolds(vec![ScaffoldPart::Contig(0, true),
+                                               ScaffoldPart::Gap(62),
+                                               ScaffoldPart::Contig(1, true)]);
+            assert_eq!(close_gaps(&copy, &contigs, &mut scaffolds, 5), 1);
+            assert_eq!(scaffolds.scaffolds[0][1],
+                       ScaffoldPart::Fill(reference[200..260].to_string()));
+            assert_eq!(scaffolds.sequences(&contigs), vec![reference.clone()]);
+        });
+        remove_file(&path).unwrap();
+        result
+    };
+    assert!(result.is_ok());
+}
+
+#[test]
+fn closes_gap_on_the_other_strand() {
+    let result = {
+        before_each!(_l, reference, path);
+        write_reads(&[&reference], &path);
+        let result = catch_unwind(|| {
+            let (graph, _) = PtGraph::create(&[&path], InputFileType::Fasta, false, 0);
+            let contigs = contigs(&reference);
+            let mut scaffolds = scaffolds(vec![ScaffoldPart::Contig(1, false),
+                                               ScaffoldPart::Gap(60),
+                                               ScaffoldPart::Contig(0, false)]);
+            assert_eq!(close_gaps(&graph, &contigs, &mut scaffolds, 5), 1);
+            assert_eq!(scaffolds.sequences(&contigs), vec![reverse_complement_of(&reference)]);
+        });
+        remove_file(&path).unwrap();
+        result
+    };
+    assert!(result.is_ok());
+}
+
+#[test]
+fn keeps_gap_of_different_length() {
+    let result = {
+        before_each!(_l, reference, path);
+        write_reads(&[&reference], &path);
+        let result = catch_unwind(|| {
+            let (graph, _) = PtGraph::create(&[&path], InputFileType::Fasta, false, 0);
+            let contigs = contigs(&reference);
+            let mut scaffolds = scaffolds(vec![ScaffoldPart::Contig(0, true),
+                                               ScaffoldPart::Gap(100),
+                                               ScaffoldPart::Contig(1, true)]);
+            assert_eq!(close_gaps(&graph, &contigs, &mut scaffolds, 5), 0);
+            assert_eq!(scaffolds.scaffolds[0][1], ScaffoldPart::Gap(100));
+        });
+        remove_file(&path).unwrap();
+        result
+    };
+    assert!(result.is_ok());
+}
+
+#[test]
+fn keeps_gap_with_several_paths() {
+    let result = {
+        before_each!(_l, reference, path);
+        // variant differing in the middle of the gap makes a bubble
+        let base = if &reference[230..231] == "A" { "C" } else { "A" };
+        let variant = format!("{}{}{}", &reference[..230], base, &reference[231..]);
+        write_reads(&[&reference, &variant], &path);
+        let result = catch_unwind(|| {
+            let (graph, _) = PtGraph::create(&[&path], InputFileType::Fasta, false, 0);
+            let contigs = contigs(&reference);
+            let mut scaffolds = scaffolds(vec![ScaffoldPart::Contig(0, true),
+                                               ScaffoldPart::Gap(60),
+                                               ScaffoldPart::Contig(1, true)]);
+            assert_eq!(close_gaps(&graph, &contigs, &mut scaffolds, 5), 0);
+            assert_eq!(scaffolds.scaffolds[0][1], ScaffoldPart::Gap(60));
+        });
+        remove_file(&path).unwrap();
+        result
+    };
+    assert!(result.is_ok());
+}
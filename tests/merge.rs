@@ -0,0 +1,105 @@
+#[macro_use]
+extern crate lazy_static;
+extern crate katome;
+
+pub use katome::config::InputFileType;
+pub use katome::algorithms::builder::Build;
+pub use katome::asm::SEQUENCES;
+pub use katome::asm::lock::LOCK;
+pub use katome::collections::PtGraph;
+pub use katome::collections::graphs::pt_graph::{merge, merge_with_sequences};
+pub use katome::prelude::{Sequences, set_global_k_sizes};
+pub use katome::stats::{CollectionStats, Stats};
+pub use std::mem;
+pub use std::sync::Mutex;
+pub use std::panic::catch_unwind;
+
+macro_rules! clear_sequences {
+    () => {
+        let mut s = SEQUENCES.write();
+        s.clear();
+        s.push(&[]);
+    }
+}
+
+macro_rules! before_each {
+    ($l:ident, $f:ident) => {
+        // get global lock over sequences for testing
+        let $l = LOCK.lock().unwrap();
+        // Clear up SEQUENCES
+        {
+            clear_sequences!();
+        }
+        unsafe { set_global_k_sizes(40); }
+        let $f = vec![
+            "./tests/test_files/data1.txt".to_string(),
+            "./tests/test_files/data2.txt".to_string(),
+            "./tests/test_files/data3.txt".to_string(),
+        ];
+    }
+}
+
+macro_rules! expected_stats {
+    ($f:ident, $i:expr) => {{
+        let (graph, _) = PtGraph::create(&[&$f[0], &$f[$i]], InputFileType::Fastq, false, 0);
+        let stats: CollectionStats = graph.stats();
+        {
+            clear_sequences!();
+        }
+        stats
+    }}
+}
+
+macro_rules! merges_shared {
+    ($i: expr, $n: ident) => {
+        #[test]
+        fn $n() {
+            let result = {
+                before_each!(_l, filenames);
+                catch_unwind(|| {
+                    let expected = expected_stats!(filenames, $i);
+                    let (first, _) =
+                        PtGraph::create(&filenames[..1], InputFileType::Fastq, false, 0);
+                    let (second, _) =
+                        PtGraph::create(&filenames[$i..$i+1], InputFileType::Fastq, false, 0);
+                    let merged = merge(first, &second);
+                    assert_eq!(expected, merged.stats());
+                })
+            };
+            assert!(result.is_ok());
+        }
+    }
+}
+
+macro_rules! merges_separate {
+    ($i: expr, $n: ident) => {
+        #[test]
+        fn $n() {
+            let result = {
+                before_each!(_l, filenames);
+                catch_unwind(|| {
+                    let expected = expected_stats!(filenames, $i);
+                    let (other, _) =
+                        PtGraph::create(&filenames[$i..$i+1], InputFileType::Fastq, false, 0);
+                    // move sequences of the other graph out of the global storage
+                    let other_sequences = {
+                        let mut s = SEQUENCES.write();
+                        let other_sequences = mem::replace(&mut *s, Sequences::new());
+                        s.push(&[]);
+                        other_sequences
+                    };
+                    let (graph, _) =
+                        PtGraph::create(&filenames[..1], InputFileType::Fastq, false, 0);
+                    let merged = merge_with_sequences(graph, &other, &other_sequences);
+                    assert_eq!(expected, merged.stats());
+                })
+            };
+            assert!(result.is_ok());
+        }
+    }
+}
+
+merges_shared!(1, merges_shared_data12);
+merges_shared!(2, merges_shared_data13);
+merges_separate!(1, merges_separate_data12);
+merges_separate!(2, merges_separate_data13);
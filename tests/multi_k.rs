@@ -0,0 +1,98 @@
+#[macro_use]
+extern crate lazy_static;
+extern crate katome;
+
+mod common;
+
+pub use common::random_sequence;
+pub use katome::{Assemble, BasicAsm, Config, MultiKAsm};
+pub use katome::collections::PtGraph;
+pub use katome::prelude::set_global_k_sizes;
+pub use std::env;
+pub use std::fs::{File, remove_file};
+pub use std::io::{Read, Write};
+pub use std::path::{Path, PathBuf};
+pub use std::sync::Mutex;
+pub use std::panic::catch_unwind;
+
+fn config(input_file: String, k_mer_sizes: Option<Vec<usize>>, output_file: String)
+          -> Config<String> {
+    Config {
+        input_files: vec![input_file],
+        output_file: output_file,
+        original_genome_length: 400,
+        k_mer_sizes: k_mer_sizes,
+        ..Default::default()
+    }
+}
+
+// Writes reads of the given length, starting every `step` bases of the
+// reference, three times each.
+fn write_reads(reference: &str, length: usize, step: usize, path: &PathBuf) {
+    let mut file = File::create(path).unwrap();
+    let last = reference.len() - length;
+    let mut starts = (0..last + 1).filter(|s| s % step == 0).collect::<Vec<usize>>();
+    if last % step != 0 {
+        starts.push(last);
+    }
+    for start in starts {
+        for i in 0..3 {
+            writeln!(file, ">read{}_{}\n{}", start, i, &reference[start..start + length]).unwrap();
+        }
+    }
+}
+
+// Assembles reads with the single k-mer of size 40 or, if sizes are given, in
+// rounds. Returns contigs.
+fn assemble(reads: &Path, k_mer_sizes: Option<Vec<usize>>) -> Vec<String> {
+    let path = env::temp_dir().join(format!("katome_multi_k_{}.fa", k_mer_sizes.is_some()));
+    let output = path.to_str().unwrap().to_string();
+    let config = config(reads.to_str().unwrap().to_string(), k_mer_sizes.clone(), output);
+    match k_mer_sizes {
+        Some(_) => MultiKAsm::assemble::<String, PtGraph>(config),
+        None => BasicAsm::assemble::<String, PtGraph>(config),
+    }
+    let mut contigs = String::new();
+    File::open(&path).unwrap().read_to_string(&mut contigs).unwrap();
+    remove_file(&path).unwrap();
+    contigs.lines().filter(|l| !l.starts_with('>')).map(|l| l.to_string()).collect()
+}
+
+macro_rules! matches_or_extends_single_k {
+    ($length:expr, $step:expr, $n:ident) => {
+        #[test]
+        fn $n() {
+            let result = {
+                let _l = common::lock_sequences();
+                let reads = common::temp_file(file!(), line!(), ".fa");
+                let reference = random_sequence(1, 400);
+                write_reads(&reference, $length, $step, &reads);
+                let result = catch_unwind(|| {
+                    let single = assemble(&reads, None);
+                    let multi = assemble(&reads, Some(vec![25, 40]));
+                    // every contig of the single k-mer is covered by some
+                    // contig assembled in rounds
+                    assert!(single.iter().all(|s| multi.iter().any(|m| m.contains(s.as_str()))));
+                    assert!(multi.iter().map(|m| m.len()).max() >=
+                            single.iter().map(|s| s.len()).max());
+                    assert_eq!(multi, vec![reference.clone()]);
+                });
+                remove_file(&reads).unwrap();
+                result
+            };
+            unsafe { set_global_k_sizes(40); }
+            assert!(result.is_ok());
+        }
+    }
+}
+
+#[test]
+fn sorts_k_schedule() {
+    let config = config(String::new(), Some(vec![40, 21, 33, 21]), String::new());
+    assert_eq!(config.k_schedule(), vec![21, 33, 40]);
+}
+
+// reads overlap enough to be joined with both k-mers
+matches_or_extends_single_k!(100, 50, matches_single_k);
+// reads overlap by 30 bases, only the smaller k-mer joins them
+matches_or_extends_single_k!(60, 30, bridges_short_overlaps);
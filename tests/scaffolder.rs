@@ -0,0 +1,105 @@
+#[macro_use]
+extern crate lazy_static;
+extern crate katome;
+
+mod common;
+
+pub use common::{random_sequence, reverse_complement_of};
+pub use katome::config::InputFileType;
+pub use katome::algorithms::scaffolder::{ScaffoldPart, scaffold};
+pub use std::env;
+pub use std::fs::{File, remove_file};
+pub use std::io::{Read, Write};
+pub use std::path::PathBuf;
+pub use std::sync::Mutex;
+pub use std::panic::catch_unwind;
+
+macro_rules! before_each {
+    ($l:ident, $r:ident, $c:ident, $f:ident, $s:ident) => {
+        let $l = common::lock_sequences();
+        let $r = random_sequence(1, 500);
+        // second contig is reverse complemented, gap between contigs is 60
+        let $c = vec![$r[..200].to_string(), reverse_complement_of(&$r[260..])];
+        let $f = common::temp_file(file!(), line!(), "_1.fa");
+        let $s = common::temp_file(file!(), line!(), "_2.fa");
+        write_pairs(&$r, &$f, &$s);
+    }
+}
+
+// Writes pairs of mates of length 50 facing each other, from fragments of
+// length 150.
+fn write_pairs(reference: &str, first: &PathBuf, second: &PathBuf) {
+    let mut first = File::create(first).unwrap();
+    let mut second = File::create(second).unwrap();
+    for (i, start) in (0..reference.len() - 150 + 1).filter(|s| s % 2 == 0).enumerate() {
+        let fragment = &reference[start..start + 150];
+        writeln!(first, ">pair{}/1\n{}", i, &fragment[..50]).unwrap();
+        writeln!(second, ">pair{}/2\n{}", i, reverse_complement_of(&fragment[100..])).unwrap();
+    }
+}
+
+#[test]
+fn joins_linked_contigs() {
+    let result = {
+        before_each!(_l, reference, contigs, first, second);
+        let result = catch_unwind(|| {
+            let scaffolds = scaffold(&contigs, &first, &second, InputFileType::Fasta, 3);
+            assert_eq!(scaffolds.insert_size, Some(150.0));
+            assert_eq!(scaffolds.scaffolds,
+                       vec![vec![ScaffoldPart::Contig(0, true),
+                                 ScaffoldPart::Gap(60),
+                                 ScaffoldPart::Contig(1, false)]]);
+            let gap = (0..60).map(|_| 'N').collect::<String>();
+            assert_eq!(scaffolds.sequences(&contigs),
+                       vec![format!("{}{}{}", &reference[..200], gap, &reference[260..])]);
+        });
+        remove_file(&first).unwrap();
+        remove_file(&second).unwrap();
+        result
+    };
+    assert!(result.is_ok());
+}
+
+#[test]
+fn keeps_weakly_linked_contigs_apart() {
+    let result = {
+        before_each!(_l, _reference, contigs, first, second);
+        let result = catch_unwind(|| {
+            // only 6 pairs span the gap
+            let scaffolds = scaffold(&contigs, &first, &second, InputFileType::Fasta, 7);
+            assert_eq!(scaffolds.scaffolds,
+                       vec![vec![ScaffoldPart::Contig(0, true)],
+                            vec![ScaffoldPart::Contig(1, true)]]);
+        });
+        remove_file(&first).unwrap();
+        remove_file(&second).unwrap();
+        result
+    };
+    assert!(result.is_ok());
+}
+
+#[test]
+fn saves_agp_file() {
+    let result = {
+        before_each!(_l, _reference, contigs, first, second);
+        let agp = env::temp_dir().join("katome_scaffolder_test.agp");
+        let fasta = env::temp_dir().join("katome_scaffolder_test.fa");
+        let result = catch_unwind(|| {
+            let scaffolds = scaffold(&contigs, &first, &second, InputFileType::Fasta, 3);
+            scaffolds.save_to_files(&contigs, &fasta, &agp);
+            let mut content = String::new();
+            File::open(&agp).unwrap().read_to_string(&mut content).unwrap();
+            assert_eq!(content,
+                       "##agp-version\t2.0\n\
+                        katome_scaffold_0\t1\t200\t1\tW\tkatome_0\t1\t200\t+\n\
+                        katome_scaffold_0\t201\t260\t2\tN\t60\tscaffold\tyes\tpaired-ends\n\
+                        katome_scaffold_0\t261\t500\t3\tW\tkatome_1\t1\t240\t-\n");
+        });
+        remove_file(&first).unwrap();
+        remove_file(&second).unwrap();
+        let _ = remove_file(&agp);
+        let _ = remove_file(&fasta);
+        result
+    };
+    assert!(result.is_ok());
+}
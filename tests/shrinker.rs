@@ -22,7 +22,7 @@ macro_rules! before_each {
         {
             let mut s = SEQUENCES.write();
             s.clear();
-            s.push(vec![].into_boxed_slice());
+            s.push(&[]);
         }
         unsafe { set_global_k_sizes(40); }
         let $f = vec![
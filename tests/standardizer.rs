@@ -24,7 +24,7 @@ pub use std::sync::Mutex;
         {
             let mut s = SEQUENCES.write();
             s.clear();
-            s.push(vec![].into_boxed_slice());
+            s.push(&[]);
         }
         // hardcoded K_SIZE value for now :/
         assert_eq!(K_SIZE, 40);
@@ -0,0 +1,58 @@
+#[macro_use]
+extern crate lazy_static;
+extern crate katome;
+
+#[macro_use]
+mod common;
+
+pub use common::{random_sequence, write_reads};
+pub use katome::config::InputFileType;
+pub use katome::algorithms::builder::Build;
+pub use katome::algorithms::collapser::Collapsable;
+pub use katome::algorithms::threading::Threadable;
+pub use katome::collections::PtGraph;
+pub use std::fs::remove_file;
+pub use std::sync::Mutex;
+pub use std::panic::catch_unwind;
+
+// Two sequences sharing the repeat shorter than reads: a + r + b, c + r + d.
+fn reference() -> Vec<String> {
+    let r = random_sequence(1, 50);
+    vec![format!("{}{}{}", random_sequence(2, 60), r, random_sequence(3, 60)),
+         format!("{}{}{}", random_sequence(4, 60), r, random_sequence(5, 60))]
+}
+
+#[test]
+fn splits_repeat_spanned_by_reads() {
+    let result = {
+        before_each!(_l, references, path);
+        let result = catch_unwind(|| {
+            let (mut graph, _) = PtGraph::create(&[&path], InputFileType::Fasta, false, 0);
+            assert_eq!(graph.thread_reads(&[&path], InputFileType::Fasta, false, 5), 1);
+            assert_eq!(graph.edge_count(), 6);
+            // every traversal of the path decreases its weight by one
+            let contigs = graph.collapse();
+            assert_eq!(contigs.len(), 20);
+            assert!(references.iter().all(|r| contigs.contains(r)));
+            assert!(contigs.iter().all(|c| references.contains(c)));
+        });
+        remove_file(&path).unwrap();
+        result
+    };
+    assert!(result.is_ok());
+}
+
+#[test]
+fn keeps_repeat_without_enough_support() {
+    let result = {
+        before_each!(_l, _references, path);
+        let result = catch_unwind(|| {
+            let (mut graph, _) = PtGraph::create(&[&path], InputFileType::Fasta, false, 0);
+            assert_eq!(graph.thread_reads(&[&path], InputFileType::Fasta, false, 11), 0);
+            assert_eq!(graph.edge_count(), 5);
+        });
+        remove_file(&path).unwrap();
+        result
+    };
+    assert!(result.is_ok());
+}
@@ -0,0 +1,97 @@
+#[macro_use]
+extern crate lazy_static;
+extern crate katome;
+
+pub use katome::config::InputFileType;
+pub use katome::algorithms::builder::{Build, Update};
+pub use katome::algorithms::shrinker::Shrinkable;
+pub use katome::asm::SEQUENCES;
+pub use katome::asm::lock::LOCK;
+pub use katome::collections::PtGraph;
+pub use katome::prelude::set_global_k_sizes;
+pub use katome::stats::{CollectionStats, Stats};
+pub use std::sync::Mutex;
+pub use std::panic::catch_unwind;
+
+macro_rules! clear_sequences {
+    () => {
+        let mut s = SEQUENCES.write();
+        s.clear();
+        s.push(&[]);
+    }
+}
+
+macro_rules! before_each {
+    ($l:ident, $f:ident) => {
+        // get global lock over sequences for testing
+        let $l = LOCK.lock().unwrap();
+        // Clear up SEQUENCES
+        {
+            clear_sequences!();
+        }
+        unsafe { set_global_k_sizes(40); }
+        let $f = vec![
+            "./tests/test_files/data1.txt".to_string(),
+            "./tests/test_files/data2.txt".to_string(),
+            "./tests/test_files/data3.txt".to_string(),
+        ];
+    }
+}
+
+#[test]
+fn doubles_weights() {
+    let result = {
+        before_each!(_l, filenames);
+        catch_unwind(|| {
+            let (graph, _) = PtGraph::create(&filenames[..1], InputFileType::Fastq, false, 0);
+            let weights = graph.raw_edges().iter().map(|e| e.weight.1).collect::<Vec<_>>();
+            let (graph, bytes) = graph.update(&filenames[..1], InputFileType::Fastq, false, 0);
+            assert!(bytes > 0);
+            let updated = graph.raw_edges().iter().map(|e| e.weight.1).collect::<Vec<_>>();
+            assert_eq!(updated, weights.iter().map(|w| 2 * w).collect::<Vec<_>>());
+        })
+    };
+    assert!(result.is_ok());
+}
+
+macro_rules! matches_single_build {
+    ($i: expr, $n: ident) => {
+        #[test]
+        fn $n() {
+            let result = {
+                before_each!(_l, filenames);
+                catch_unwind(|| {
+                    let (graph, _) =
+                        PtGraph::create(&filenames[..$i+1], InputFileType::Fastq, false, 0);
+                    let expected: CollectionStats = graph.stats();
+                    {
+                        clear_sequences!();
+                    }
+                    let (mut graph, _) =
+                        PtGraph::create(&filenames[..1], InputFileType::Fastq, false, 0);
+                    for file in &filenames[1..$i+1] {
+                        graph = graph.update(&[file], InputFileType::Fastq, false, 0).0;
+                    }
+                    assert_eq!(expected, graph.stats());
+                })
+            };
+            assert!(result.is_ok());
+        }
+    }
+}
+
+matches_single_build!(1, matches_data12);
+matches_single_build!(2, matches_data123);
+
+#[test]
+fn rejects_shrunk_graph() {
+    let result = {
+        before_each!(_l, filenames);
+        catch_unwind(|| {
+            let (mut graph, _) = PtGraph::create(&filenames[..1], InputFileType::Fastq, false, 0);
+            graph.shrink();
+            graph.update(&filenames[..1], InputFileType::Fastq, false, 0);
+        })
+    };
+    assert!(result.is_err());
+}